@@ -0,0 +1,156 @@
+// Headless renderer: bounces a saved project to WAV without opening an
+// audio device or a window.
+//
+//     remdaw-render song.remdaw -o song.wav --bpm 140 --start 1 --end 9
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use remdaw_engine::export::{self, BitDepth, ExportSettings};
+use remdaw_engine::models::{MAX_BPM, MIN_BPM};
+use remdaw_engine::project::{self, Project};
+use remdaw_engine::resample::Interpolation;
+use remdaw_engine::Session;
+
+const USAGE: &str = "\
+usage: remdaw-render <project.remdaw> [options]
+
+options:
+  -o, --output <path>      WAV file to write (default: project name with .wav)
+      --bpm <bpm>          override the project tempo
+      --start <bar>        first bar to render (default: 1)
+      --end <bar>          bar to stop at (default: end of the last clip)
+      --sample-rate <hz>   output sample rate (default: 44100)
+      --bit-depth <depth>  16, 24 or 32f (default: 16)
+      --linear             faster, lower quality resampling of samples
+      --allow-missing      render even if samples can't be loaded
+      --no-tail            stop at --end instead of letting effects ring out
+  -h, --help               show this message";
+
+/// Exit code when the project references samples that aren't on disk
+const EXIT_MISSING_SAMPLES: u8 = 2;
+
+struct Args {
+    project: PathBuf,
+    output: Option<PathBuf>,
+    bpm: Option<i16>,
+    start_bar: Option<f64>,
+    end_bar: Option<f64>,
+    sample_rate: u32,
+    bit_depth: BitDepth,
+    interpolation: Interpolation,
+    allow_missing: bool,
+    tail: bool,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let project = match Project::load(&args.project) {
+        Ok(project) => project,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut session = Session::new();
+    let missing = project.apply(&mut session, &project::project_dir(&args.project));
+    for err in &missing {
+        eprintln!("warning: {}", err);
+    }
+    if !missing.is_empty() && !args.allow_missing {
+        return ExitCode::from(EXIT_MISSING_SAMPLES);
+    }
+
+    if let Some(bpm) = args.bpm {
+        session.bpm = bpm;
+    }
+
+    let beats_per_bar = session.time_signature.bar_length();
+    let settings = ExportSettings {
+        sample_rate: args.sample_rate,
+        bit_depth: args.bit_depth,
+        interpolation: args.interpolation,
+        start_beat: args.start_bar.map_or(0.0, |bar| (bar - 1.0) * beats_per_bar),
+        end_beat: args.end_bar.map_or_else(|| session.playlist.length(), |bar| (bar - 1.0) * beats_per_bar),
+        tail: args.tail,
+    };
+    let output = args.output.unwrap_or_else(|| args.project.with_extension("wav"));
+
+    let offline = export::offline_engine(&session, &settings);
+    if let Err(err) = export::write_wav(offline, &settings, &output, |_| {}) {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    println!("wrote {}", output.display());
+    ExitCode::SUCCESS
+}
+
+/// Parses command line flags. Returns `Ok(None)` when help was requested.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut project = None;
+    let mut parsed = Args {
+        project: PathBuf::new(),
+        output: None,
+        bpm: None,
+        start_bar: None,
+        end_bar: None,
+        sample_rate: 44100,
+        bit_depth: BitDepth::Int16,
+        interpolation: Interpolation::Sinc,
+        allow_missing: false,
+        tail: true,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => parsed.output = Some(PathBuf::from(value(&arg)?)),
+            "--bpm" => parsed.bpm = Some(parse_number(&arg, &value(&arg)?, MIN_BPM..=MAX_BPM)?),
+            "--start" => parsed.start_bar = Some(parse_number(&arg, &value(&arg)?, 1.0..=f64::MAX)?),
+            "--end" => parsed.end_bar = Some(parse_number(&arg, &value(&arg)?, 1.0..=f64::MAX)?),
+            "--sample-rate" => parsed.sample_rate = parse_number(&arg, &value(&arg)?, 8000..=384000)?,
+            "--bit-depth" => {
+                parsed.bit_depth = match value(&arg)?.as_str() {
+                    "16" => BitDepth::Int16,
+                    "24" => BitDepth::Int24,
+                    "32f" | "32" => BitDepth::Float32,
+                    other => return Err(format!("unsupported bit depth '{}'", other)),
+                }
+            }
+            "--linear" => parsed.interpolation = Interpolation::Linear,
+            "--allow-missing" => parsed.allow_missing = true,
+            "--no-tail" => parsed.tail = false,
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path if project.is_none() => project = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument '{}'", extra)),
+        }
+    }
+
+    parsed.project = project.ok_or("no project file given")?;
+    Ok(Some(parsed))
+}
+
+fn parse_number<T>(flag: &str, value: &str, range: std::ops::RangeInclusive<T>) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let number: T = value.parse().map_err(|_| format!("{} expects a number, got '{}'", flag, value))?;
+    if !range.contains(&number) {
+        return Err(format!("{} must be between {} and {}", flag, range.start(), range.end()));
+    }
+    Ok(number)
+}
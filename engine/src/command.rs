@@ -0,0 +1,143 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use rtrb::{Consumer, Producer, RingBuffer};
+use crate::effects::{EffectChain, EffectTarget};
+use crate::meter::Meters;
+use crate::mixer::ChannelMix;
+use crate::models::{AuxBus, Instrument, NotePattern, Pattern, PlacedClip, Session, Step, TimeSignature, Track};
+use crate::resample::Interpolation;
+use crate::routing::{AuxSend, SendSource};
+use crate::sampler::SamplerSettings;
+use crate::synth::SynthPatch;
+
+/// How many commands can be waiting for the audio thread at once
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Edits the UI sends to the engine.
+///
+/// Small edits are applied in place. Structural edits carry a replacement
+/// built on the UI thread; the engine swaps it in and sends the command back
+/// holding the old data, so nothing is allocated or freed on the audio thread.
+pub enum Command {
+    // transport
+    Play,
+    Pause,
+    Stop,
+    Seek(f64),
+    SetBpm(i16),
+    SetMetronome(bool),
+    SetTimeSignature(TimeSignature),
+    SetSwing(f32),
+    SetInterpolation(Interpolation),
+    TriggerInstrument(usize),
+    /// Plays a note until the matching `ReleaseNote`, e.g. from the piano roll's keys
+    TriggerNote { instrument: usize, key: u8 },
+    ReleaseNote { instrument: usize, key: u8 },
+    Preview(Option<Instrument>),
+
+    // in place edits
+    SetStep { pattern: usize, instrument: usize, step: usize, value: Step },
+    MoveClip { index: usize, track_index: usize, start_time: f64, length: f64, offset: f64 },
+    SetTrackMix(usize, ChannelMix),
+    SetInstrumentMix(usize, ChannelMix),
+    /// Changes the patch of a synth instrument. Playing notes pick it up straight away.
+    SetSynth(usize, SynthPatch),
+    /// Changes how an instrument plays its sample. Playing voices pick it up straight away.
+    SetSampler(usize, SamplerSettings),
+    SetAuxMix(usize, ChannelMix),
+    /// Changes an existing send. Adding or removing one replaces the channels.
+    SetSend { source: SendSource, index: usize, send: AuxSend },
+    SetMasterVolume(f32),
+    SetEffectParam { target: EffectTarget, slot: usize, param: usize, value: f32 },
+    SetEffectBypass { target: EffectTarget, slot: usize, bypass: bool },
+
+    // replacements
+    SetInstruments(Vec<Instrument>),
+    SetPatterns(Vec<Pattern>),
+    SetNotePatterns(Vec<NotePattern>),
+    SetClips(Vec<PlacedClip>),
+    SetTracks(Vec<Track>),
+    SetAuxBuses(Vec<AuxBus>),
+    SetEffects(EffectTarget, Box<EffectChain>),
+    LoadSession(Box<Session>),
+}
+
+/// Transport state and levels published by the audio thread for the UI to read
+#[derive(Default)]
+pub struct EngineStatus {
+    playhead_position: AtomicU64, // f64 bits, in beats
+    is_playing: AtomicBool,
+    pub meters: Meters,
+}
+
+impl EngineStatus {
+    pub fn publish(&self, playhead_position: f64, is_playing: bool) {
+        self.playhead_position.store(playhead_position.to_bits(), Ordering::Relaxed);
+        self.is_playing.store(is_playing, Ordering::Relaxed);
+    }
+}
+
+/// The engine's end of the queues
+pub(crate) struct Controller {
+    pub commands: Consumer<Command>,
+    pub returned: Producer<Command>,
+    pub status: Arc<EngineStatus>,
+}
+
+/// The UI's end of the queues: sends commands and reads the transport state
+pub struct EngineHandle {
+    commands: Producer<Command>,
+    returned: Consumer<Command>,
+    status: Arc<EngineStatus>,
+    pub sampling_rate: f32,
+}
+
+impl EngineHandle {
+    /// Queues a command for the next audio callback. Returns false when the
+    /// queue is full and the command was dropped, so the edit never arrives.
+    #[must_use = "a dropped command leaves the engine out of sync with the UI"]
+    pub fn send(&mut self, command: Command) -> bool {
+        self.collect_garbage();
+        // no engine on the other end, e.g. no audio device could be opened
+        self.commands.is_abandoned() || self.commands.push(command).is_ok()
+    }
+
+    /// Frees data the engine has replaced. Call once per UI frame.
+    pub fn collect_garbage(&mut self) {
+        while self.returned.pop().is_ok() {}
+    }
+
+    /// Playhead position in beats as of the last audio callback
+    pub fn playhead_position(&self) -> f64 {
+        f64::from_bits(self.status.playhead_position.load(Ordering::Relaxed))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.status.is_playing.load(Ordering::Relaxed)
+    }
+
+    /// Levels of every instrument, track and the master bus
+    pub fn meters(&self) -> &Meters {
+        &self.status.meters
+    }
+}
+
+/// Creates both ends of the command and return queues
+pub(crate) fn channel(sampling_rate: f32) -> (EngineHandle, Controller) {
+    let (command_tx, command_rx) = RingBuffer::new(QUEUE_CAPACITY);
+    let (returned_tx, returned_rx) = RingBuffer::new(QUEUE_CAPACITY);
+    let status = Arc::new(EngineStatus::default());
+
+    let handle = EngineHandle {
+        commands: command_tx,
+        returned: returned_rx,
+        status: status.clone(),
+        sampling_rate,
+    };
+    let controller = Controller {
+        commands: command_rx,
+        returned: returned_tx,
+        status,
+    };
+    (handle, controller)
+}
@@ -0,0 +1,224 @@
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use crate::error::{Error, Result};
+use crate::sample::{load_wav, Sample};
+
+/// What the file browser shows about an audio file before it's loaded
+#[derive(Clone, Debug)]
+pub struct SampleInfo {
+    pub format: &'static str,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub bits_per_sample: Option<u32>, // lossy formats don't have one
+    pub frames: Option<u64>,          // not every container stores its length
+}
+
+impl SampleInfo {
+    /// Length in seconds, when known
+    pub fn duration(&self) -> Option<f64> {
+        self.frames.map(|frames| frames as f64 / self.sample_rate as f64)
+    }
+}
+
+/// Reads one family of audio files.
+/// Add an implementation to `DECODERS` to support a new format everywhere
+/// samples are loaded.
+pub trait Decoder: Sync {
+    /// Lowercase file extensions this decoder claims
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Reads the header only
+    fn probe(&self, path: &Path) -> Result<SampleInfo>;
+
+    /// Decodes the whole file to stereo frames
+    fn decode(&self, path: &Path) -> Result<Sample>;
+}
+
+/// WAV through `hound`
+pub struct WavDecoder;
+
+impl Decoder for WavDecoder {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["wav", "wave"]
+    }
+
+    fn probe(&self, path: &Path) -> Result<SampleInfo> {
+        let reader = hound::WavReader::open(path)
+            .map_err(|err| Error::load(path, err))?;
+        let spec = reader.spec();
+        Ok(SampleInfo {
+            format: "WAV",
+            sample_rate: spec.sample_rate,
+            channels: spec.channels as usize,
+            bits_per_sample: Some(spec.bits_per_sample as u32),
+            frames: Some(reader.duration() as u64),
+        })
+    }
+
+    fn decode(&self, path: &Path) -> Result<Sample> {
+        load_wav(path)
+    }
+}
+
+/// FLAC, Ogg Vorbis, MP3 and AIFF through `symphonia`
+pub struct SymphoniaDecoder;
+
+impl SymphoniaDecoder {
+    /// Opens the container, letting symphonia work out the format
+    fn open(path: &Path) -> Result<Box<dyn FormatReader>> {
+        let fail = |err: SymphoniaError| Error::load(path, err);
+
+        let file = File::open(path).map_err(|err| Error::load(path, err))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(fail)?;
+        Ok(probed.format)
+    }
+
+    fn format_name(path: &Path) -> &'static str {
+        match extension(path).as_deref() {
+            Some("flac") => "FLAC",
+            Some("ogg" | "oga") => "Ogg Vorbis",
+            Some("mp3") => "MP3",
+            Some("aif" | "aiff" | "aifc") => "AIFF",
+            _ => "Audio",
+        }
+    }
+}
+
+impl Decoder for SymphoniaDecoder {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["flac", "ogg", "oga", "mp3", "aif", "aiff", "aifc"]
+    }
+
+    fn probe(&self, path: &Path) -> Result<SampleInfo> {
+        let format = Self::open(path)?;
+        let track = format.tracks().iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| Error::load(path, "no audio track"))?;
+        let params = &track.codec_params;
+
+        Ok(SampleInfo {
+            format: Self::format_name(path),
+            sample_rate: params.sample_rate.unwrap_or(0),
+            channels: params.channels.map_or(0, |channels| channels.count()),
+            bits_per_sample: params.bits_per_sample,
+            frames: params.n_frames,
+        })
+    }
+
+    fn decode(&self, path: &Path) -> Result<Sample> {
+        let fail = |err: SymphoniaError| Error::load(path, err);
+
+        let mut format = Self::open(path)?;
+        let track = format.tracks().iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| Error::load(path, "no audio track"))?;
+        let track_id = track.id;
+        let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+        let mut channels = track.codec_params.channels.map_or(0, |channels| channels.count());
+
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(fail)?;
+
+        let mut interleaved = Vec::new();
+
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) => packet,
+                // symphonia reports the end of the stream as an unexpected EOF
+                Err(SymphoniaError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(fail(err)),
+            };
+            if packet.track_id() != track_id {
+                continue;
+            }
+
+            match decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let spec = *decoded.spec();
+                    sample_rate = spec.rate;
+                    channels = spec.channels.count();
+
+                    let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                    buffer.copy_interleaved_ref(decoded);
+                    interleaved.extend_from_slice(buffer.samples());
+                }
+                // a corrupt packet only loses a few milliseconds, keep going
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(err) => return Err(fail(err)),
+            }
+        }
+
+        Sample::from_interleaved(&interleaved, channels, sample_rate)
+            .ok_or_else(|| Error::load(path, "file has no channels"))
+    }
+}
+
+/// Every decoder. The first to claim an extension reads it.
+pub static DECODERS: &[&dyn Decoder] = &[&WavDecoder, &SymphoniaDecoder];
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+}
+
+/// The decoder that claims `path`'s extension
+pub fn decoder_for(path: &Path) -> Option<&'static dyn Decoder> {
+    let extension = extension(path)?;
+    DECODERS.iter()
+        .copied()
+        .find(|decoder| decoder.extensions().contains(&extension.as_str()))
+}
+
+/// Whether `path` looks like an audio file remdaw can load
+pub fn is_supported(path: &Path) -> bool {
+    decoder_for(path).is_some()
+}
+
+/// Every extension some decoder claims, for file dialogs
+pub fn supported_extensions() -> Vec<&'static str> {
+    DECODERS.iter()
+        .flat_map(|decoder| decoder.extensions().iter().copied())
+        .collect()
+}
+
+/// Reads an audio file's header
+pub fn probe(path: &Path) -> Result<SampleInfo> {
+    decoder_for(path)
+        .ok_or_else(|| Error::UnsupportedFile(path.to_path_buf()))?
+        .probe(path)
+}
+
+/// Loads any supported audio file as a stereo sample, with its peaks ready
+/// to draw.
+///
+/// # Arguments
+/// * `path` - File path to the audio file
+///
+/// # Returns
+/// * `Result<Sample>` - The decoded audio, or why it couldn't be read
+pub fn load_sample(path: &Path) -> Result<Sample> {
+    let sample = decoder_for(path)
+        .ok_or_else(|| Error::UnsupportedFile(path.to_path_buf()))?
+        .decode(path)?;
+    sample.build_peaks();
+    Ok(sample)
+}
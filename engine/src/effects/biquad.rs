@@ -0,0 +1,258 @@
+use std::f32::consts::PI;
+use super::{clamp_param, Effect, Param};
+
+/// Lowest filter frequency, and the highest as a fraction of the sample rate
+const MIN_FREQUENCY: f32 = 10.0;
+const MAX_FREQUENCY_RATIO: f32 = 0.49;
+
+pub(super) const FILTER_PARAMS: &[Param] = &[
+    Param::choice("Type", &["Low Pass", "High Pass", "Band Pass", "Notch", "Peak", "Low Shelf", "High Shelf"], 0),
+    Param::new("Frequency", 20.0, 20000.0, 1000.0, "Hz").logarithmic(),
+    Param::new("Q", 0.1, 10.0, 0.707, "").logarithmic(),
+    Param::new("Gain", -24.0, 24.0, 0.0, "dB"),
+];
+
+pub(super) const EQ_PARAMS: &[Param] = &[
+    Param::new("Low Freq", 20.0, 1000.0, 100.0, "Hz").logarithmic(),
+    Param::new("Low Gain", -24.0, 24.0, 0.0, "dB"),
+    Param::new("Mid Freq", 100.0, 10000.0, 1000.0, "Hz").logarithmic(),
+    Param::new("Mid Gain", -24.0, 24.0, 0.0, "dB"),
+    Param::new("Mid Q", 0.1, 10.0, 1.0, "").logarithmic(),
+    Param::new("High Freq", 1000.0, 20000.0, 8000.0, "Hz").logarithmic(),
+    Param::new("High Gain", -24.0, 24.0, 0.0, "dB"),
+];
+
+/// Response shapes from the Audio EQ Cookbook
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterType {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+    Peak,
+    LowShelf,
+    HighShelf,
+}
+
+impl FilterType {
+    pub const ALL: [FilterType; 7] = [
+        FilterType::LowPass,
+        FilterType::HighPass,
+        FilterType::BandPass,
+        FilterType::Notch,
+        FilterType::Peak,
+        FilterType::LowShelf,
+        FilterType::HighShelf,
+    ];
+}
+
+/// Normalized biquad coefficients, `a0` divided out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coefficients {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Default for Coefficients {
+    /// Passes audio through unchanged
+    fn default() -> Self {
+        Coefficients { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0 }
+    }
+}
+
+impl Coefficients {
+    /// # Arguments
+    /// * `filter` - Response shape
+    /// * `sample_rate` - Rate the filter runs at
+    /// * `frequency` - Cutoff or centre frequency in Hz
+    /// * `q` - Resonance, or bandwidth for peaks and shelves
+    /// * `gain_db` - Boost or cut of peaks and shelves, ignored by the others
+    pub fn new(filter: FilterType, sample_rate: f32, frequency: f32, q: f32, gain_db: f32) -> Self {
+        let frequency = frequency.clamp(MIN_FREQUENCY, sample_rate * MAX_FREQUENCY_RATIO);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(0.01));
+        let a = 10f32.powf(gain_db / 40.0);
+
+        let (b0, b1, b2, a0, a1, a2) = match filter {
+            FilterType::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterType::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterType::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterType::Notch => (1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterType::Peak => (
+                1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a,
+                1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a,
+            ),
+            FilterType::LowShelf => {
+                let root = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + root),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - root),
+                    (a + 1.0) + (a - 1.0) * cos + root,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - root,
+                )
+            }
+            FilterType::HighShelf => {
+                let root = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + root),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - root),
+                    (a + 1.0) - (a - 1.0) * cos + root,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - root,
+                )
+            }
+        };
+
+        Coefficients { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0 }
+    }
+}
+
+/// A stereo second order filter, transposed direct form II
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Biquad {
+    pub coefficients: Coefficients,
+    state: [[f32; 2]; 2], // two delay elements per channel
+}
+
+impl Biquad {
+    pub fn new(coefficients: Coefficients) -> Self {
+        Biquad { coefficients, state: [[0.0; 2]; 2] }
+    }
+
+    pub fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        let Coefficients { b0, b1, b2, a1, a2 } = self.coefficients;
+        for frame in buffer {
+            for (sample, state) in frame.iter_mut().zip(&mut self.state) {
+                let input = *sample;
+                let output = b0 * input + state[0];
+                state[0] = b1 * input - a1 * output + state[1];
+                state[1] = b2 * input - a2 * output;
+                *sample = output;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.state = [[0.0; 2]; 2];
+    }
+}
+
+/// A single filter of any cookbook type
+pub struct Filter {
+    sample_rate: f32,
+    params: [f32; 4],
+    biquad: Biquad,
+}
+
+impl Filter {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut filter = Filter {
+            sample_rate,
+            params: [0.0; 4],
+            biquad: Biquad::default(),
+        };
+        for (index, param) in FILTER_PARAMS.iter().enumerate() {
+            filter.params[index] = param.default;
+        }
+        filter.update();
+        filter
+    }
+
+    fn update(&mut self) {
+        let [filter, frequency, q, gain] = self.params;
+        let filter = FilterType::ALL[filter as usize];
+        self.biquad.coefficients = Coefficients::new(filter, self.sample_rate, frequency, q, gain);
+    }
+}
+
+impl Effect for Filter {
+    fn params(&self) -> &'static [Param] {
+        FILTER_PARAMS
+    }
+
+    fn param(&self, index: usize) -> f32 {
+        self.params.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn set_param(&mut self, index: usize, value: f32) {
+        if let Some(param) = FILTER_PARAMS.get(index) {
+            self.params[index] = clamp_param(param, value);
+            self.update();
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        self.biquad.process(buffer);
+    }
+
+    fn reset(&mut self) {
+        self.biquad.reset();
+    }
+}
+
+/// Three band equalizer: low shelf, a peak in the middle and a high shelf
+pub struct Eq {
+    sample_rate: f32,
+    params: [f32; 7],
+    bands: [Biquad; 3],
+}
+
+impl Eq {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut eq = Eq {
+            sample_rate,
+            params: [0.0; 7],
+            bands: [Biquad::default(); 3],
+        };
+        for (index, param) in EQ_PARAMS.iter().enumerate() {
+            eq.params[index] = param.default;
+        }
+        eq.update();
+        eq
+    }
+
+    fn update(&mut self) {
+        let [low_freq, low_gain, mid_freq, mid_gain, mid_q, high_freq, high_gain] = self.params;
+        // shelves use the cookbook's slope of 1, which is a Q of 1/sqrt(2)
+        let shelf_q = std::f32::consts::FRAC_1_SQRT_2;
+        self.bands[0].coefficients = Coefficients::new(FilterType::LowShelf, self.sample_rate, low_freq, shelf_q, low_gain);
+        self.bands[1].coefficients = Coefficients::new(FilterType::Peak, self.sample_rate, mid_freq, mid_q, mid_gain);
+        self.bands[2].coefficients = Coefficients::new(FilterType::HighShelf, self.sample_rate, high_freq, shelf_q, high_gain);
+    }
+}
+
+impl Effect for Eq {
+    fn params(&self) -> &'static [Param] {
+        EQ_PARAMS
+    }
+
+    fn param(&self, index: usize) -> f32 {
+        self.params.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn set_param(&mut self, index: usize, value: f32) {
+        if let Some(param) = EQ_PARAMS.get(index) {
+            self.params[index] = clamp_param(param, value);
+            self.update();
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        for band in &mut self.bands {
+            band.process(buffer);
+        }
+    }
+
+    fn reset(&mut self) {
+        for band in &mut self.bands {
+            band.reset();
+        }
+    }
+}
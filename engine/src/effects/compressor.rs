@@ -0,0 +1,86 @@
+use super::{clamp_param, Effect, Param};
+use crate::meter::{db_to_gain, gain_to_db};
+
+pub(super) const PARAMS: &[Param] = &[
+    Param::new("Threshold", -60.0, 0.0, -18.0, "dB"),
+    Param::new("Ratio", 1.0, 20.0, 4.0, ":1").logarithmic(),
+    Param::new("Attack", 0.1, 100.0, 10.0, "ms").logarithmic(),
+    Param::new("Release", 10.0, 1000.0, 100.0, "ms").logarithmic(),
+    Param::new("Makeup", 0.0, 24.0, 0.0, "dB"),
+];
+
+/// Feed forward peak compressor with both channels linked, so the stereo
+/// image doesn't shift when one side is louder
+pub struct Compressor {
+    sample_rate: f32,
+    params: [f32; 5],
+    attack: f32,  // smoothing coefficients per frame
+    release: f32,
+    reduction: f32, // current gain reduction in dB
+}
+
+/// Per frame coefficient of a one pole smoother with the given time constant
+fn smoothing(ms: f32, sample_rate: f32) -> f32 {
+    (-1.0 / (ms * 0.001 * sample_rate)).exp()
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut compressor = Compressor {
+            sample_rate,
+            params: [0.0; 5],
+            attack: 0.0,
+            release: 0.0,
+            reduction: 0.0,
+        };
+        for (index, param) in PARAMS.iter().enumerate() {
+            compressor.set_param(index, param.default);
+        }
+        compressor
+    }
+
+    /// Gain reduction in dB applied to the last frame
+    pub fn reduction(&self) -> f32 {
+        self.reduction
+    }
+}
+
+impl Effect for Compressor {
+    fn params(&self) -> &'static [Param] {
+        PARAMS
+    }
+
+    fn param(&self, index: usize) -> f32 {
+        self.params.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn set_param(&mut self, index: usize, value: f32) {
+        if let Some(param) = PARAMS.get(index) {
+            self.params[index] = clamp_param(param, value);
+            self.attack = smoothing(self.params[2], self.sample_rate);
+            self.release = smoothing(self.params[3], self.sample_rate);
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        let [threshold, ratio, _, _, makeup] = self.params;
+        let slope = 1.0 - 1.0 / ratio;
+
+        for frame in buffer {
+            let level = gain_to_db(frame[0].abs().max(frame[1].abs()));
+            let target = (level - threshold).max(0.0) * slope;
+
+            // reduce quickly on the way in, recover slowly on the way out
+            let coefficient = if target > self.reduction { self.attack } else { self.release };
+            self.reduction = target + coefficient * (self.reduction - target);
+
+            let gain = db_to_gain(makeup - self.reduction);
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.reduction = 0.0;
+    }
+}
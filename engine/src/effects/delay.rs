@@ -0,0 +1,72 @@
+use super::{clamp_param, Effect, Param};
+
+/// Longest delay time, in seconds. The buffer is sized for it up front.
+const MAX_DELAY: f32 = 2.0;
+
+pub(super) const PARAMS: &[Param] = &[
+    Param::new("Time", 1.0, MAX_DELAY * 1000.0, 250.0, "ms").logarithmic(),
+    Param::new("Feedback", 0.0, 0.95, 0.4, ""),
+    Param::new("Mix", 0.0, 1.0, 0.3, ""),
+];
+
+/// Stereo echo with feedback
+pub struct Delay {
+    sample_rate: f32,
+    params: [f32; 3],
+    buffer: Vec<[f32; 2]>,
+    write: usize,
+    delay: usize, // in frames
+}
+
+impl Delay {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut delay = Delay {
+            sample_rate,
+            params: [0.0; 3],
+            buffer: vec![[0.0; 2]; (MAX_DELAY * sample_rate) as usize + 1],
+            write: 0,
+            delay: 1,
+        };
+        for (index, param) in PARAMS.iter().enumerate() {
+            delay.set_param(index, param.default);
+        }
+        delay
+    }
+}
+
+impl Effect for Delay {
+    fn params(&self) -> &'static [Param] {
+        PARAMS
+    }
+
+    fn param(&self, index: usize) -> f32 {
+        self.params.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn set_param(&mut self, index: usize, value: f32) {
+        if let Some(param) = PARAMS.get(index) {
+            self.params[index] = clamp_param(param, value);
+            let frames = (self.params[0] * 0.001 * self.sample_rate).round() as usize;
+            self.delay = frames.clamp(1, self.buffer.len() - 1);
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        let [_, feedback, mix] = self.params;
+        let len = self.buffer.len();
+
+        for frame in buffer {
+            let read = (self.write + len - self.delay) % len;
+            let echo = self.buffer[read];
+            for channel in 0..2 {
+                self.buffer[self.write][channel] = frame[channel] + echo[channel] * feedback;
+                frame[channel] = frame[channel] * (1.0 - mix) + echo[channel] * mix;
+            }
+            self.write = (self.write + 1) % len;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill([0.0; 2]);
+    }
+}
@@ -0,0 +1,51 @@
+use super::{clamp_param, Effect, Param};
+use crate::meter::db_to_gain;
+
+pub(super) const PARAMS: &[Param] = &[Param::new("Gain", -60.0, 24.0, 0.0, "dB")];
+
+/// Turns the signal up or down
+pub struct Gain {
+    db: f32,
+    gain: f32,
+}
+
+impl Default for Gain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gain {
+    pub fn new() -> Self {
+        Gain { db: 0.0, gain: 1.0 }
+    }
+}
+
+impl Effect for Gain {
+    fn params(&self) -> &'static [Param] {
+        PARAMS
+    }
+
+    fn param(&self, index: usize) -> f32 {
+        match index {
+            0 => self.db,
+            _ => 0.0,
+        }
+    }
+
+    fn set_param(&mut self, index: usize, value: f32) {
+        if index == 0 {
+            self.db = clamp_param(&PARAMS[0], value);
+            self.gain = db_to_gain(self.db);
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        for frame in buffer {
+            frame[0] *= self.gain;
+            frame[1] *= self.gain;
+        }
+    }
+
+    fn reset(&mut self) {}
+}
@@ -0,0 +1,246 @@
+//! Insert effects for mixer tracks and the master bus.
+//!
+//! A track stores its chain as `EffectSlot`s: which effect and its parameter
+//! values, which is what gets saved with the project. The engine plays an
+//! `EffectChain` of live instances built from those slots on the UI thread,
+//! since effects allocate their delay lines up front.
+
+mod biquad;
+mod compressor;
+mod delay;
+mod gain;
+mod reverb;
+
+use serde::{Deserialize, Serialize};
+
+pub use biquad::{Biquad, Coefficients, Eq, Filter, FilterType};
+pub use compressor::Compressor;
+pub use delay::Delay;
+pub use gain::Gain;
+pub use reverb::Reverb;
+
+/// One adjustable value of an effect
+#[derive(Clone, Copy, Debug)]
+pub struct Param {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub unit: &'static str,
+    pub labels: &'static [&'static str], // names of whole number values, for choices like a filter's type
+    pub logarithmic: bool,               // frequencies and times feel better on a log scale
+}
+
+impl Param {
+    pub const fn new(name: &'static str, min: f32, max: f32, default: f32, unit: &'static str) -> Self {
+        Param { name, min, max, default, unit, labels: &[], logarithmic: false }
+    }
+
+    pub const fn logarithmic(self) -> Self {
+        Param { logarithmic: true, ..self }
+    }
+
+    /// A choice between named options, stored as the option's index
+    pub const fn choice(name: &'static str, labels: &'static [&'static str], default: usize) -> Self {
+        Param {
+            name,
+            min: 0.0,
+            max: labels.len() as f32 - 1.0,
+            default: default as f32,
+            unit: "",
+            labels,
+            logarithmic: false,
+        }
+    }
+}
+
+/// Audio processing that can be inserted on a mixer channel.
+/// `process` runs on the audio thread and must not allocate or block.
+pub trait Effect: Send {
+    /// The parameters `param` and `set_param` index into
+    fn params(&self) -> &'static [Param];
+
+    fn param(&self, index: usize) -> f32;
+
+    /// Changes a parameter, clamped to its range. Out of range indices are ignored.
+    fn set_param(&mut self, index: usize, value: f32);
+
+    /// Processes interleaved stereo frames in place
+    fn process(&mut self, buffer: &mut [[f32; 2]]);
+
+    /// Forgets any signal the effect is holding, like a delay's echoes or a reverb's tail
+    fn reset(&mut self);
+
+    /// Frames the output lags behind the input
+    fn latency(&self) -> usize {
+        0
+    }
+}
+
+/// Clamps `value` to a parameter's range, rounding choices to a whole option
+pub(crate) fn clamp_param(param: &Param, value: f32) -> f32 {
+    let value = value.clamp(param.min, param.max);
+    if param.labels.is_empty() { value } else { value.round() }
+}
+
+/// Every built-in effect
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectKind {
+    Gain,
+    Filter,
+    Eq,
+    Compressor,
+    Delay,
+    Reverb,
+}
+
+impl EffectKind {
+    pub const ALL: [EffectKind; 6] = [
+        EffectKind::Gain,
+        EffectKind::Filter,
+        EffectKind::Eq,
+        EffectKind::Compressor,
+        EffectKind::Delay,
+        EffectKind::Reverb,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EffectKind::Gain => "Gain",
+            EffectKind::Filter => "Filter",
+            EffectKind::Eq => "EQ",
+            EffectKind::Compressor => "Compressor",
+            EffectKind::Delay => "Delay",
+            EffectKind::Reverb => "Reverb",
+        }
+    }
+
+    /// A new instance with default parameters
+    pub fn create(self, sample_rate: f32) -> Box<dyn Effect> {
+        match self {
+            EffectKind::Gain => Box::new(Gain::new()),
+            EffectKind::Filter => Box::new(Filter::new(sample_rate)),
+            EffectKind::Eq => Box::new(Eq::new(sample_rate)),
+            EffectKind::Compressor => Box::new(Compressor::new(sample_rate)),
+            EffectKind::Delay => Box::new(Delay::new(sample_rate)),
+            EffectKind::Reverb => Box::new(Reverb::new(sample_rate)),
+        }
+    }
+
+    pub fn params(self) -> &'static [Param] {
+        match self {
+            EffectKind::Gain => gain::PARAMS,
+            EffectKind::Filter => biquad::FILTER_PARAMS,
+            EffectKind::Eq => biquad::EQ_PARAMS,
+            EffectKind::Compressor => compressor::PARAMS,
+            EffectKind::Delay => delay::PARAMS,
+            EffectKind::Reverb => reverb::PARAMS,
+        }
+    }
+}
+
+/// A saved effect: what it is, its settings and whether it's bypassed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EffectSlot {
+    pub kind: EffectKind,
+    pub params: Vec<f32>,
+    #[serde(default)]
+    pub bypass: bool,
+}
+
+impl EffectSlot {
+    /// An active effect at its default settings
+    pub fn new(kind: EffectKind) -> Self {
+        EffectSlot {
+            kind,
+            params: kind.params().iter().map(|param| param.default).collect(),
+            bypass: false,
+        }
+    }
+
+    /// A live instance with this slot's settings
+    pub fn build(&self, sample_rate: f32) -> Box<dyn Effect> {
+        let mut effect = self.kind.create(sample_rate);
+        for (index, &value) in self.params.iter().enumerate() {
+            effect.set_param(index, value);
+        }
+        effect
+    }
+}
+
+/// Which chain an edit is for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectTarget {
+    Master,
+    Track(usize),
+    Aux(usize),
+}
+
+struct Insert {
+    effect: Box<dyn Effect>,
+    bypass: bool,
+}
+
+/// Live effects of one mixer channel, processed in order
+#[derive(Default)]
+pub struct EffectChain {
+    inserts: Vec<Insert>,
+}
+
+impl EffectChain {
+    /// Builds every slot. Allocates, so call it off the audio thread.
+    pub fn build(slots: &[EffectSlot], sample_rate: f32) -> Self {
+        EffectChain {
+            inserts: slots.iter()
+                .map(|slot| Insert { effect: slot.build(sample_rate), bypass: slot.bypass })
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inserts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inserts.is_empty()
+    }
+
+    pub fn set_param(&mut self, slot: usize, param: usize, value: f32) {
+        if let Some(insert) = self.inserts.get_mut(slot) {
+            insert.effect.set_param(param, value);
+        }
+    }
+
+    /// Bypassed effects pass audio through untouched. Effects coming back
+    /// are reset so they don't play out a stale tail.
+    pub fn set_bypass(&mut self, slot: usize, bypass: bool) {
+        if let Some(insert) = self.inserts.get_mut(slot) {
+            if insert.bypass && !bypass {
+                insert.effect.reset();
+            }
+            insert.bypass = bypass;
+        }
+    }
+
+    pub fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        for insert in &mut self.inserts {
+            if !insert.bypass {
+                insert.effect.process(buffer);
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for insert in &mut self.inserts {
+            insert.effect.reset();
+        }
+    }
+
+    /// Frames the active effects delay the signal by in total
+    pub fn latency(&self) -> usize {
+        self.inserts.iter()
+            .filter(|insert| !insert.bypass)
+            .map(|insert| insert.effect.latency())
+            .sum()
+    }
+}
@@ -0,0 +1,145 @@
+use super::{clamp_param, Effect, Param};
+
+/// Comb and allpass lengths of the Freeverb design, in frames at 44.1 kHz
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+/// Extra length of the right channel's filters, which decorrelates the sides
+const STEREO_SPREAD: usize = 23;
+const TUNING_RATE: f32 = 44100.0;
+/// Keeps the sum of eight combs from getting too loud
+const INPUT_GAIN: f32 = 0.015;
+/// Brings the wet signal back up to roughly the level of the dry
+const WET_GAIN: f32 = 3.0;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+pub(super) const PARAMS: &[Param] = &[
+    Param::new("Room Size", 0.0, 1.0, 0.5, ""),
+    Param::new("Damping", 0.0, 1.0, 0.5, ""),
+    Param::new("Width", 0.0, 1.0, 1.0, ""),
+    Param::new("Mix", 0.0, 1.0, 0.25, ""),
+];
+
+/// Feedback comb filter with a low pass in the loop
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    filter: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Comb { buffer: vec![0.0; len.max(1)], index: 0, filter: 0.0 }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter = output * (1.0 - damping) + self.filter * damping;
+        self.buffer[self.index] = input + self.filter * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(len: usize) -> Self {
+        Allpass { buffer: vec![0.0; len.max(1)], index: 0 }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * ALLPASS_FEEDBACK;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+/// Freeverb style algorithmic reverb: parallel combs into series allpasses per channel
+pub struct Reverb {
+    params: [f32; 4],
+    combs: [Vec<Comb>; 2],
+    allpasses: [Vec<Allpass>; 2],
+}
+
+impl Reverb {
+    pub fn new(sample_rate: f32) -> Self {
+        let scale = |len: usize| (len as f32 * sample_rate / TUNING_RATE) as usize;
+        let side = |spread: usize| {
+            (
+                COMB_TUNING.iter().map(|&len| Comb::new(scale(len + spread))).collect(),
+                ALLPASS_TUNING.iter().map(|&len| Allpass::new(scale(len + spread))).collect(),
+            )
+        };
+        let (left_combs, left_allpasses) = side(0);
+        let (right_combs, right_allpasses) = side(STEREO_SPREAD);
+
+        let mut reverb = Reverb {
+            params: [0.0; 4],
+            combs: [left_combs, right_combs],
+            allpasses: [left_allpasses, right_allpasses],
+        };
+        for (index, param) in PARAMS.iter().enumerate() {
+            reverb.set_param(index, param.default);
+        }
+        reverb
+    }
+}
+
+impl Effect for Reverb {
+    fn params(&self) -> &'static [Param] {
+        PARAMS
+    }
+
+    fn param(&self, index: usize) -> f32 {
+        self.params.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn set_param(&mut self, index: usize, value: f32) {
+        if let Some(param) = PARAMS.get(index) {
+            self.params[index] = clamp_param(param, value);
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [[f32; 2]]) {
+        let [room_size, damping, width, mix] = self.params;
+        // Freeverb's scaling: feedback between 0.7 and 0.98, damping up to 0.4
+        let feedback = 0.7 + room_size * 0.28;
+        let damping = damping * 0.4;
+        let wet_same = mix * WET_GAIN * (1.0 + width) / 2.0;
+        let wet_cross = mix * WET_GAIN * (1.0 - width) / 2.0;
+
+        for frame in buffer {
+            let input = (frame[0] + frame[1]) * INPUT_GAIN;
+            let mut wet = [0.0; 2];
+
+            for (channel, out) in wet.iter_mut().enumerate() {
+                let mut sum = 0.0;
+                for comb in &mut self.combs[channel] {
+                    sum += comb.process(input, feedback, damping);
+                }
+                for allpass in &mut self.allpasses[channel] {
+                    sum = allpass.process(sum);
+                }
+                *out = sum;
+            }
+
+            let dry = 1.0 - mix;
+            frame[0] = frame[0] * dry + wet[0] * wet_same + wet[1] * wet_cross;
+            frame[1] = frame[1] * dry + wet[1] * wet_same + wet[0] * wet_cross;
+        }
+    }
+
+    fn reset(&mut self) {
+        for comb in self.combs.iter_mut().flatten() {
+            comb.buffer.fill(0.0);
+            comb.filter = 0.0;
+        }
+        for allpass in self.allpasses.iter_mut().flatten() {
+            allpass.buffer.fill(0.0);
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use std::mem;
+use std::path::PathBuf;
+use crate::command::{self, Command, Controller, EngineHandle};
+use crate::mixer::{any_solo, Mixer, MAX_BLOCK};
+use crate::models::{Generator, Instrument, Session, Step};
+use crate::sample::Sample;
+use crate::sequencer::{schedule_block, schedule_resume, Block, Event, EventKind, Rng, MAX_EVENTS};
+use crate::synth::{key_frequency, SynthVoice};
+use crate::voice::{Source, StealPolicy, Voice, VoicePool};
+
+/// Beats a manual trigger holds synths and looping samples for: a 16th note
+const TRIGGER_GATE: f64 = 0.25;
+
+/// Plays back a session: sequences the playlist and mixes every sound source
+/// into an interleaved stereo buffer.
+pub struct Engine {
+    pub session: Session,
+    pub sampling_rate: f32,
+    pub samples_per_beat: f32,
+    pub playhead_position: f64, // in beats
+    pub is_playing: bool,
+    pub is_metronome: bool,
+    pub metronome: Instrument,
+    pub preview_sound: Option<Instrument>,
+    pub voices: VoicePool,
+    pub mixer: Mixer,
+    events: Vec<Event>,
+    resume: bool, // playback starts or the playhead moved, so audio clips under way start mid-clip
+    rng: Rng, // rolls step probabilities
+    controller: Option<Controller>,
+}
+
+impl Engine {
+    /// An empty session with no instruments or patterns
+    pub fn new(sampling_rate: f32) -> Self {
+        Self::with_session(Session::new(), sampling_rate)
+    }
+
+    pub fn with_session(session: Session, sampling_rate: f32) -> Self {
+        let mut mixer = Mixer::new(sampling_rate);
+        mixer.build_effects(&session, sampling_rate);
+
+        Engine {
+            samples_per_beat: sampling_rate * 60.0 / session.bpm as f32,
+            session,
+            sampling_rate,
+            playhead_position: 0.0,
+            is_playing: false,
+            is_metronome: false,
+            metronome: Instrument::new(PathBuf::new(), Sample::default()),
+            preview_sound: None,
+            voices: VoicePool::new(sampling_rate),
+            mixer,
+            events: Vec::with_capacity(MAX_EVENTS),
+            resume: false,
+            rng: Rng::default(),
+            controller: None,
+        }
+    }
+
+    /// Creates the queues the UI uses to control this engine once it has
+    /// been moved to the audio thread. Commands are applied at the start of
+    /// every `render` call.
+    pub fn connect(&mut self) -> EngineHandle {
+        let (handle, controller) = command::channel(self.sampling_rate);
+        self.controller = Some(controller);
+        handle
+    }
+
+    /// Changes tempo and recalculates samples_per_beat
+    pub fn set_bpm(&mut self, bpm: i16) {
+        self.session.bpm = bpm;
+        self.samples_per_beat = self.sampling_rate * 60.0 / bpm as f32;
+    }
+
+    /// Resumes playback from the playhead, partway into any audio clip under it
+    pub fn play(&mut self) {
+        if !self.is_playing {
+            self.resume = true;
+        }
+        self.is_playing = true;
+    }
+
+    /// Stops playback where it is. Held notes are let go, since their note
+    /// offs won't come, and audio clips are cut.
+    pub fn pause(&mut self) {
+        self.is_playing = false;
+        self.voices.release_notes();
+        self.voices.cut_clips();
+    }
+
+    /// Stops playback and rewinds to the start of the song
+    pub fn stop(&mut self) {
+        self.pause();
+        self.seek(0.0);
+    }
+
+    /// Moves the playhead, in beats. Steps exactly on `beat` fire when playback
+    /// starts. Held notes are let go, since their note offs are behind the playhead.
+    pub fn seek(&mut self, beat: f64) {
+        self.playhead_position = beat;
+        self.voices.release_notes();
+        self.voices.cut_clips();
+        self.resume = true;
+    }
+
+    /// Starts a new voice of an instrument. Earlier hits keep ringing up to
+    /// the instrument's polyphony.
+    pub fn trigger(&mut self, instrument_idx: usize) {
+        self.trigger_on(instrument_idx, None, &Step::ON, TRIGGER_GATE);
+    }
+
+    /// Starts a new voice of an instrument playing through a track's bus,
+    /// with the velocity, pan and pitch of `step`. A step has no note off,
+    /// so synths and looping samples hold for `gate` beats.
+    fn trigger_on(&mut self, instrument_idx: usize, track: Option<usize>, step: &Step, gate: f64) -> Option<&mut Voice> {
+        let hold = (gate * self.samples_per_beat as f64) as u32;
+        let instrument = self.session.instruments.get(instrument_idx)?;
+        let voice = self.voices.trigger(
+            Source::Instrument(instrument_idx),
+            step.velocity,
+            step.rate(),
+            instrument.polyphony,
+            instrument.steal,
+        )?;
+        voice.track = track;
+        voice.pan = step.pan;
+        if instrument.sampler.loops() {
+            voice.hold = Some(hold);
+        }
+        start_synth(voice, instrument, Some(hold));
+        Some(voice)
+    }
+
+    /// Starts an instrument playing `key`, pitched from its root key. The
+    /// voice stops at the matching note off.
+    pub fn play_note(&mut self, instrument_idx: usize, track: Option<usize>, key: u8, velocity: f32) {
+        if let Some(instrument) = self.session.instruments.get(instrument_idx)
+            && let Some(voice) = self.voices.trigger(
+                Source::Instrument(instrument_idx),
+                velocity,
+                instrument.key_rate(key),
+                instrument.polyphony,
+                instrument.steal,
+            ) {
+            voice.track = track;
+            voice.key = Some(key);
+            start_synth(voice, instrument, None);
+        }
+    }
+
+    /// Fills `out` with interleaved stereo audio, advancing the playhead
+    /// when playing. Called by the audio callback, the offline renderer and tests.
+    pub fn render(&mut self, out: &mut [f32]) {
+        self.render_channels(out, 2);
+    }
+
+    /// Like `render`, for an output with any number of channels. Mono gets
+    /// the average of left and right; past stereo, the first two channels
+    /// carry left and right and the rest stay silent.
+    pub fn render_channels(&mut self, out: &mut [f32], channels: usize) {
+        self.process_commands();
+
+        // the mixer works on blocks of at most MAX_BLOCK frames
+        let channels = channels.max(1);
+        for chunk in out.chunks_mut(MAX_BLOCK * channels) {
+            self.render_block(chunk, channels);
+        }
+
+        let status = self.controller.as_ref().map(|controller| &controller.status);
+        self.mixer.publish_levels(status.map(|status| &status.meters));
+        if let Some(status) = status {
+            status.publish(self.playhead_position, self.is_playing);
+        }
+    }
+
+    /// Renders up to `MAX_BLOCK` frames
+    fn render_block(&mut self, out: &mut [f32], channels: usize) {
+        let frames = out.len() / channels;
+        let block = Block::new(self.playhead_position, frames, self.samples_per_beat as f64);
+
+        // Work out where every hit in this buffer lands before mixing it
+        if self.is_playing {
+            schedule_block(
+                &self.session.playlist.clips,
+                &self.session.patterns,
+                &self.session.note_patterns,
+                &block,
+                self.session.swing,
+                self.is_metronome.then(|| self.session.time_signature.beat_length()),
+                &mut self.events,
+            );
+            if mem::take(&mut self.resume) {
+                schedule_resume(&self.session.playlist.clips, &block, &mut self.events);
+            }
+        } else {
+            self.events.clear();
+        }
+
+        let mut next_event = 0;
+        let instrument_solo = any_solo(self.session.instruments.iter().map(|i| &i.mix));
+        self.mixer.begin_block(frames, self.session.playlist.tracks.len(), self.session.aux_buses.len());
+
+        for offset in 0..frames {
+            while let Some(&event) = self.events.get(next_event)
+                && event.offset == offset {
+                self.fire(event);
+                next_event += 1;
+            }
+
+            // Mix every playing voice: instruments, metronome and file browser preview
+            let instruments = &self.session.instruments;
+            let metronome = &self.metronome;
+            let preview = &self.preview_sound;
+            let mixer = &mut self.mixer;
+            mixer.begin_frame(instruments.len());
+            self.voices.mix_frame(
+                |source| match source {
+                    Source::Instrument(idx) => instruments.get(idx).map(Instrument::sound),
+                    Source::Metronome => Some(metronome.sound()),
+                    Source::Preview => preview.as_ref().map(Instrument::sound),
+                },
+                |voice, [left, right]| {
+                    // instrument volume and pan apply before the track's
+                    let frame = match voice.source {
+                        Source::Instrument(idx) => {
+                            let instrument = &instruments[idx];
+                            let [gain_left, gain_right] = instrument.mix.gains(instrument_solo);
+                            let frame = [left * gain_left, right * gain_right];
+                            let pre = if instrument.mix.is_audible(instrument_solo) { [left, right] } else { [0.0; 2] };
+                            mixer.send(offset, &instrument.sends, pre, frame);
+                            mixer.meter_instrument(idx, frame);
+                            frame
+                        }
+                        _ => [left, right],
+                    };
+                    mixer.add(offset, voice.track, frame);
+                },
+            );
+            mixer.end_frame();
+        }
+
+        // Effects, faders and the master bus, then one sample per output channel
+        let session = &self.session;
+        let mixed = self.mixer.end_block(&session.playlist.tracks, &session.aux_buses, session.master_volume);
+        for (frame, mix) in out.chunks_exact_mut(channels).zip(mixed) {
+            match frame {
+                [mono] => *mono = (mix[0] + mix[1]) * 0.5,
+                [left, right, rest @ ..] => {
+                    *left = mix[0];
+                    *right = mix[1];
+                    rest.fill(0.0);
+                }
+                [] => {}
+            }
+        }
+
+        if self.is_playing {
+            self.playhead_position = block.end;
+        }
+    }
+
+    /// Applies every queued command and hands replaced data back to the UI thread
+    fn process_commands(&mut self) {
+        let Some(mut controller) = self.controller.take() else {
+            return;
+        };
+
+        while let Ok(command) = controller.commands.pop() {
+            if let Some(returned) = self.apply(command) {
+                // if the UI isn't collecting, dropping here is the only option left
+                let _ = controller.returned.push(returned);
+            }
+        }
+        self.controller = Some(controller);
+    }
+
+    /// Applies one command. Returns it back when it now holds data to free.
+    pub fn apply(&mut self, command: Command) -> Option<Command> {
+        match command {
+            Command::Play => self.play(),
+            Command::Pause => self.pause(),
+            Command::Stop => self.stop(),
+            Command::Seek(beat) => self.seek(beat),
+            Command::SetBpm(bpm) => self.set_bpm(bpm),
+            Command::SetMetronome(enabled) => self.is_metronome = enabled,
+            Command::SetTimeSignature(signature) => self.session.time_signature = signature,
+            Command::SetSwing(swing) => self.session.swing = swing,
+            Command::SetInterpolation(interpolation) => self.voices.interpolation = interpolation,
+            Command::TriggerInstrument(idx) => self.trigger(idx),
+            Command::TriggerNote { instrument, key } => {
+                self.voices.release_note(Source::Instrument(instrument), key, None);
+                self.play_note(instrument, None, key, 1.0);
+            }
+            Command::ReleaseNote { instrument, key } => self.voices.release_note(Source::Instrument(instrument), key, None),
+            Command::Preview(mut sound) => {
+                self.voices.release(Source::Preview);
+                if sound.is_some() {
+                    self.voices.trigger(Source::Preview, 1.0, 1.0, 1, StealPolicy::Oldest);
+                }
+                mem::swap(&mut self.preview_sound, &mut sound);
+                return Some(Command::Preview(sound));
+            }
+            Command::SetStep { pattern, instrument, step, value } => {
+                if let Some(cell) = self.session.patterns.get_mut(pattern)
+                    .and_then(|pattern| pattern.data.get_mut(instrument))
+                    .and_then(|row| row.get_mut(step)) {
+                    *cell = value;
+                }
+            }
+            Command::MoveClip { index, track_index, start_time, length, offset } => {
+                if let Some(clip) = self.session.playlist.clips.get_mut(index) {
+                    clip.track_index = track_index;
+                    clip.start_time = start_time;
+                    clip.length = length;
+                    clip.offset = offset;
+                }
+            }
+            Command::SetTrackMix(index, mix) => {
+                if let Some(track) = self.session.playlist.tracks.get_mut(index) {
+                    track.mix = mix;
+                }
+            }
+            Command::SetInstrumentMix(index, mix) => {
+                if let Some(instrument) = self.session.instruments.get_mut(index) {
+                    instrument.mix = mix;
+                }
+            }
+            Command::SetSynth(index, patch) => {
+                if let Some(instrument) = self.session.instruments.get_mut(index) {
+                    instrument.generator = Generator::Synth(patch);
+                }
+            }
+            Command::SetSampler(index, settings) => {
+                if let Some(instrument) = self.session.instruments.get_mut(index) {
+                    instrument.sampler = settings;
+                }
+            }
+            Command::SetAuxMix(index, mix) => {
+                if let Some(bus) = self.session.aux_buses.get_mut(index) {
+                    bus.mix = mix;
+                }
+            }
+            Command::SetSend { source, index, send } => {
+                if let Some(current) = self.session.sends_mut(source).and_then(|sends| sends.get_mut(index)) {
+                    *current = send;
+                }
+            }
+            Command::SetMasterVolume(volume) => self.session.master_volume = volume,
+            Command::SetEffectParam { target, slot, param, value } => {
+                if let Some(chain) = self.mixer.effects_mut(target) {
+                    chain.set_param(slot, param, value);
+                }
+            }
+            Command::SetEffectBypass { target, slot, bypass } => {
+                if let Some(chain) = self.mixer.effects_mut(target) {
+                    chain.set_bypass(slot, bypass);
+                }
+            }
+            Command::SetInstruments(mut instruments) => {
+                mem::swap(&mut self.session.instruments, &mut instruments);
+                return Some(Command::SetInstruments(instruments));
+            }
+            Command::SetPatterns(mut patterns) => {
+                mem::swap(&mut self.session.patterns, &mut patterns);
+                return Some(Command::SetPatterns(patterns));
+            }
+            // a held note's off may be gone with the data it came from
+            Command::SetNotePatterns(mut patterns) => {
+                self.voices.release_notes();
+                mem::swap(&mut self.session.note_patterns, &mut patterns);
+                return Some(Command::SetNotePatterns(patterns));
+            }
+            Command::SetClips(mut clips) => {
+                self.voices.release_notes();
+                mem::swap(&mut self.session.playlist.clips, &mut clips);
+                return Some(Command::SetClips(clips));
+            }
+            Command::SetTracks(mut tracks) => {
+                mem::swap(&mut self.session.playlist.tracks, &mut tracks);
+                return Some(Command::SetTracks(tracks));
+            }
+            Command::SetAuxBuses(mut buses) => {
+                mem::swap(&mut self.session.aux_buses, &mut buses);
+                return Some(Command::SetAuxBuses(buses));
+            }
+            Command::SetEffects(target, mut chain) => {
+                if let Some(current) = self.mixer.effects_mut(target) {
+                    mem::swap(current, &mut *chain);
+                }
+                return Some(Command::SetEffects(target, chain));
+            }
+            Command::LoadSession(mut session) => {
+                mem::swap(&mut self.session, &mut *session);
+                self.voices.clear();
+                self.set_bpm(self.session.bpm);
+                self.stop();
+                return Some(Command::LoadSession(session));
+            }
+        }
+        None
+    }
+
+    /// Starts the sound a scheduled event points at
+    fn fire(&mut self, event: Event) {
+        match event.kind {
+            EventKind::Step(instrument_idx, step, length) => {
+                // roll every time round, so the pattern varies as it loops
+                if step.probability >= 1.0 || self.rng.next_f32() < step.probability {
+                    self.trigger_on(instrument_idx, event.track, &step, length);
+                }
+            }
+            EventKind::AudioClip { instrument, from, length } => {
+                // beats to frames of the sample, which plays at its own rate
+                let samples_per_beat = self.samples_per_beat as f64;
+                let rate = self.session.instruments.get(instrument)
+                    .map_or(1.0, |instrument| instrument.sample.sample_rate as f64 / self.sampling_rate as f64);
+                // looping samples hold for the rest of the clip
+                if let Some(voice) = self.trigger_on(instrument, event.track, &Step::ON, length) {
+                    voice.position = from * samples_per_beat * rate;
+                    voice.clip_end = Some((length * samples_per_beat).round() as u32);
+                }
+            }
+            EventKind::NoteOn { instrument, key, velocity } => self.play_note(instrument, event.track, key, velocity),
+            EventKind::NoteOff { instrument, key } => {
+                self.voices.release_note(Source::Instrument(instrument), key, event.track);
+            }
+            EventKind::Click => {
+                self.voices.trigger(Source::Metronome, 1.0, 1.0, 1, StealPolicy::Oldest);
+            }
+        }
+    }
+}
+
+/// Gives a new voice of a synth instrument its oscillators and envelopes,
+/// tuned so the root key at a rate of 1.0 sounds the root key
+///
+/// # Arguments
+/// * `hold` - Frames until the note lets go by itself, None to wait for its note off
+fn start_synth(voice: &mut Voice, instrument: &Instrument, hold: Option<u32>) {
+    if let Generator::Synth(_) = instrument.generator {
+        let frequency = key_frequency(instrument.root_key) * voice.pitch;
+        voice.synth = Some(SynthVoice::new(frequency, hold));
+    }
+}
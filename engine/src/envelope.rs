@@ -0,0 +1,104 @@
+//! Attack, decay, sustain and release envelopes shared by the synth and the
+//! sampler.
+
+use serde::{Deserialize, Serialize};
+
+/// Attack, decay, sustain and release. Times are in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32, // level held while the note is down, 0.0 to 1.0
+    pub release: f32,
+}
+
+impl Envelope {
+    /// Full level for as long as the note lasts, with a short release so a
+    /// note off doesn't click
+    pub const FLAT: Envelope = Envelope { attack: 0.0, decay: 0.0, sustain: 1.0, release: 0.005 };
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope { attack: 0.005, decay: 0.2, sustain: 0.7, release: 0.2 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    Done,
+}
+
+/// Where one voice is in an envelope. Segments are linear.
+#[derive(Clone, Copy, Debug)]
+pub struct EnvelopeState {
+    stage: Stage,
+    level: f32,
+    release_step: f32, // per frame, fixed when the release starts so it always takes the release time
+}
+
+impl Default for EnvelopeState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvelopeState {
+    /// At the start of the attack
+    pub fn new() -> Self {
+        EnvelopeState { stage: Stage::Attack, level: 0.0, release_step: 0.0 }
+    }
+
+    /// Advances one frame and returns the level
+    pub fn next(&mut self, envelope: &Envelope, sample_rate: f32) -> f32 {
+        let per_frame = |seconds: f32| 1.0 / (seconds * sample_rate).max(1.0);
+        match self.stage {
+            Stage::Attack => {
+                self.level += per_frame(envelope.attack);
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.level -= (1.0 - envelope.sustain) * per_frame(envelope.decay);
+                if self.level <= envelope.sustain {
+                    self.level = envelope.sustain;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => self.level = envelope.sustain,
+            Stage::Release => {
+                self.level -= self.release_step;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = Stage::Done;
+                }
+            }
+            Stage::Done => {}
+        }
+        self.level
+    }
+
+    /// Starts the release from wherever the level is
+    pub fn release(&mut self, envelope: &Envelope, sample_rate: f32) {
+        if self.stage != Stage::Done {
+            self.stage = Stage::Release;
+            self.release_step = self.level / (envelope.release * sample_rate).max(1.0);
+        }
+    }
+
+    pub fn is_releasing(&self) -> bool {
+        matches!(self.stage, Stage::Release | Stage::Done)
+    }
+
+    /// Whether the release has finished
+    pub fn is_done(&self) -> bool {
+        self.stage == Stage::Done
+    }
+}
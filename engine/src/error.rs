@@ -0,0 +1,86 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Everything that can go wrong outside the audio callback. Each variant
+/// carries enough context to show the user which file or device failed.
+#[derive(Debug)]
+pub enum Error {
+    /// An audio file couldn't be read or decoded
+    Load { path: PathBuf, reason: String },
+    /// No decoder handles this kind of file
+    UnsupportedFile(PathBuf),
+    /// A project file couldn't be read, parsed or written
+    Project { path: PathBuf, reason: String },
+    /// The project was saved by a newer build with a format this one can't read
+    ProjectVersion { path: PathBuf, version: u32, supported: u32 },
+    /// Bouncing to disk failed
+    Export { path: PathBuf, reason: String },
+    /// The audio output couldn't be opened or started
+    Device(String),
+    /// Aux bus sends that would feed back into themselves
+    Routing(String),
+    /// A groove template couldn't be read or parsed
+    Groove { path: PathBuf, reason: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Load { path, reason } => write!(f, "Could not load {}: {}", path.display(), reason),
+            Error::UnsupportedFile(path) => write!(f, "{} is not a supported audio file", path.display()),
+            Error::Project { path, reason } => write!(f, "Project {}: {}", path.display(), reason),
+            Error::ProjectVersion { path, version, supported } => write!(
+                f,
+                "{} was saved with a newer version of remdaw (format {}, supported {})",
+                path.display(), version, supported
+            ),
+            Error::Export { path, reason } => write!(f, "Could not export {}: {}", path.display(), reason),
+            Error::Device(reason) => write!(f, "Audio device: {}", reason),
+            Error::Routing(reason) => write!(f, "Routing: {}", reason),
+            Error::Groove { path, reason } => write!(f, "Groove {}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    pub fn load(path: impl Into<PathBuf>, reason: impl ToString) -> Self {
+        Error::Load { path: path.into(), reason: reason.to_string() }
+    }
+
+    pub fn project(path: impl Into<PathBuf>, reason: impl ToString) -> Self {
+        Error::Project { path: path.into(), reason: reason.to_string() }
+    }
+
+    pub fn export(path: impl Into<PathBuf>, reason: impl ToString) -> Self {
+        Error::Export { path: path.into(), reason: reason.to_string() }
+    }
+
+    pub fn device(reason: impl ToString) -> Self {
+        Error::Device(reason.to_string())
+    }
+
+    pub fn routing(reason: impl ToString) -> Self {
+        Error::Routing(reason.to_string())
+    }
+
+    pub fn groove(path: impl Into<PathBuf>, reason: impl ToString) -> Self {
+        Error::Groove { path: path.into(), reason: reason.to_string() }
+    }
+
+    /// The file the error is about, if any
+    pub fn path(&self) -> Option<&PathBuf> {
+        match self {
+            Error::Load { path, .. }
+            | Error::UnsupportedFile(path)
+            | Error::Project { path, .. }
+            | Error::ProjectVersion { path, .. }
+            | Error::Export { path, .. }
+            | Error::Groove { path, .. } => Some(path),
+            Error::Device(_) | Error::Routing(_) => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
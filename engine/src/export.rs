@@ -0,0 +1,146 @@
+use std::path::Path;
+use crate::engine::Engine;
+use crate::error::{Error, Result};
+use crate::models::Session;
+use crate::resample::Interpolation;
+
+/// Frames rendered per block while bouncing
+const BLOCK_FRAMES: usize = 1024;
+/// Level a block of the tail has to stay under for the song to have rung out (-80 dB)
+const TAIL_THRESHOLD: f32 = 1e-4;
+/// How long the tail has to stay silent before it counts as over, so the
+/// gaps between a long delay's repeats don't end it early
+const TAIL_SILENCE_SECONDS: u32 = 2;
+/// Longest tail rendered after the range, in seconds
+const MAX_TAIL_SECONDS: u32 = 30;
+
+/// Sample formats the exporter can write
+#[derive(Clone, Copy, PartialEq)]
+pub enum BitDepth {
+    Int16,
+    Int24,
+    Float32,
+}
+
+impl BitDepth {
+    pub const ALL: [BitDepth; 3] = [BitDepth::Int16, BitDepth::Int24, BitDepth::Float32];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BitDepth::Int16 => "16-bit",
+            BitDepth::Int24 => "24-bit",
+            BitDepth::Float32 => "32-bit float",
+        }
+    }
+
+    fn spec(&self, sample_rate: u32) -> hound::WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            BitDepth::Int16 => (16, hound::SampleFormat::Int),
+            BitDepth::Int24 => (24, hound::SampleFormat::Int),
+            BitDepth::Float32 => (32, hound::SampleFormat::Float),
+        };
+        hound::WavSpec { channels: 2, sample_rate, bits_per_sample, sample_format }
+    }
+}
+
+/// What to bounce and how to write it
+#[derive(Clone)]
+pub struct ExportSettings {
+    pub sample_rate: u32,
+    pub bit_depth: BitDepth,
+    pub interpolation: Interpolation, // how samples are resampled to `sample_rate`
+    pub start_beat: f64,
+    pub end_beat: f64,
+    pub tail: bool, // keep rendering after the range until reverbs and delays ring out
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            bit_depth: BitDepth::Int16,
+            interpolation: Interpolation::Sinc,
+            start_beat: 0.0,
+            end_beat: 16.0,
+            tail: true,
+        }
+    }
+}
+
+/// Renders the playlist between the selected beats faster than realtime and
+/// writes it to a stereo WAV file. Takes an engine made by `offline_engine` so
+/// the live session is left untouched. With `settings.tail` the transport
+/// stops at the end of the range and rendering carries on until the mix
+/// falls silent, so effects ring out instead of being cut.
+///
+/// # Arguments
+/// * `offline` - Session copy positioned at the start of the range
+/// * `settings` - Sample rate, bit depth and range
+/// * `path` - Destination WAV file
+/// * `progress` - Called after every block with the share of the range rendered so far
+pub fn write_wav(mut offline: Engine, settings: &ExportSettings, path: &Path, mut progress: impl FnMut(f32)) -> Result<()> {
+    if settings.end_beat <= settings.start_beat {
+        return Err(Error::export(path, "the range is empty"));
+    }
+
+    let total_frames = ((settings.end_beat - settings.start_beat) * offline.samples_per_beat as f64).ceil() as usize;
+
+    let mut writer = hound::WavWriter::create(path, settings.bit_depth.spec(settings.sample_rate))
+        .map_err(|err| Error::export(path, err))?;
+    let mut write_block = |block: &[f32]| -> Result<()> {
+        for &sample in block {
+            let sample = sample.clamp(-1.0, 1.0);
+            let result = match settings.bit_depth {
+                BitDepth::Int16 => writer.write_sample((sample * i16::MAX as f32) as i16),
+                BitDepth::Int24 => writer.write_sample((sample * 8_388_607.0) as i32),
+                BitDepth::Float32 => writer.write_sample(sample),
+            };
+            result.map_err(|err| Error::export(path, err))?;
+        }
+        Ok(())
+    };
+
+    let mut buffer = vec![0.0f32; BLOCK_FRAMES * 2];
+    let mut frames_left = total_frames;
+    while frames_left > 0 {
+        let frames = frames_left.min(BLOCK_FRAMES);
+        let block = &mut buffer[..frames * 2];
+        offline.render(block);
+        write_block(block)?;
+        frames_left -= frames;
+        progress(1.0 - frames_left as f32 / total_frames as f32);
+    }
+
+    if settings.tail {
+        // nothing new starts, held notes are let go and everything rings out
+        offline.pause();
+        // quiet blocks are held back until something audible follows them,
+        // so the file ends where the sound does
+        let mut quiet = Vec::new();
+        let mut tail_left = (MAX_TAIL_SECONDS * settings.sample_rate) as usize;
+        while tail_left > 0 && quiet.len() < (TAIL_SILENCE_SECONDS * settings.sample_rate) as usize * 2 {
+            let frames = tail_left.min(BLOCK_FRAMES);
+            let block = &mut buffer[..frames * 2];
+            offline.render(block);
+            quiet.extend_from_slice(block);
+            if block.iter().any(|sample| sample.abs() >= TAIL_THRESHOLD) {
+                write_block(&quiet)?;
+                quiet.clear();
+            }
+            tail_left -= frames;
+        }
+    }
+
+    writer.finalize().map_err(|err| Error::export(path, err))
+}
+
+/// Copies the song into a fresh engine running at the export sample rate,
+/// positioned at the start of the range
+pub fn offline_engine(session: &Session, settings: &ExportSettings) -> Engine {
+    let mut offline = Engine::with_session(session.clone(), settings.sample_rate as f32);
+    offline.voices.interpolation = settings.interpolation;
+
+    offline.seek(settings.start_beat);
+    offline.play();
+    offline
+}
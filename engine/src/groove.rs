@@ -0,0 +1,86 @@
+//! Swing and groove templates: small timing and velocity offsets that take
+//! the mechanical edge off quantized pattern steps.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+
+/// File extension of groove templates
+pub const GROOVE_EXTENSION: &str = "groove";
+
+/// Furthest a groove moves a step either way, as a fraction of a step
+pub const MAX_GROOVE_SHIFT: f32 = 0.5;
+
+/// Timing and velocity feel laid over a pattern. Both lists repeat on their
+/// own, so a two step shuffle works on any pattern length.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Groove {
+    pub name: String,
+    #[serde(default)]
+    pub timing: Vec<f32>, // shift of each step as a fraction of a step, late is positive
+    #[serde(default)]
+    pub velocity: Vec<f32>, // scales the velocity of each step
+}
+
+impl Groove {
+    /// Templates that ship with remdaw
+    pub fn presets() -> Vec<Groove> {
+        vec![
+            Groove {
+                name: "Lazy hip-hop".to_string(),
+                timing: vec![0.0, 0.15, 0.05, 0.2],
+                velocity: vec![1.0, 0.7, 0.85, 0.65],
+            },
+            Groove {
+                name: "House shuffle".to_string(),
+                timing: vec![0.0, 0.1, 0.0, 0.12],
+                velocity: vec![1.0, 0.75, 0.9, 0.8],
+            },
+            Groove {
+                name: "Pushed".to_string(),
+                timing: vec![0.0, -0.08, -0.04, -0.1],
+                velocity: vec![1.0, 0.85, 0.95, 0.8],
+            },
+            Groove {
+                name: "Humanize".to_string(),
+                timing: vec![0.0, 0.03, -0.02, 0.04, -0.03, 0.02, 0.01, -0.04],
+                velocity: vec![1.0, 0.92, 0.96, 0.88, 0.98, 0.9, 0.95, 0.86],
+            },
+        ]
+    }
+
+    /// Reads a groove template saved as JSON
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|err| Error::groove(path, err))?;
+        serde_json::from_str(&content).map_err(|err| Error::groove(path, format!("invalid groove file: {}", err)))
+    }
+
+    /// Shift of `step`, as a fraction of a step
+    pub fn timing(&self, step: usize) -> f32 {
+        cycle(&self.timing, step).map_or(0.0, |shift| shift.clamp(-MAX_GROOVE_SHIFT, MAX_GROOVE_SHIFT))
+    }
+
+    /// What `step`'s velocity is multiplied by
+    pub fn velocity(&self, step: usize) -> f32 {
+        cycle(&self.velocity, step).map_or(1.0, |scale| scale.max(0.0))
+    }
+}
+
+fn cycle(values: &[f32], step: usize) -> Option<f32> {
+    values.get(step % values.len().max(1)).copied()
+}
+
+/// How late a step plays, as a fraction of a step. Swing holds back every
+/// second step: at 0 the off-beat sits halfway through the pair, at 1 it
+/// lands three quarters of the way, a dotted feel.
+///
+/// # Arguments
+/// * `step` - Step within the pattern
+/// * `swing` - 0.0 to 1.0
+/// * `groove` - Template applied on top of the swing, if any
+pub fn step_shift(step: usize, swing: f32, groove: Option<&Groove>) -> f64 {
+    let swing = if step % 2 == 1 { swing.clamp(0.0, 1.0) * 0.5 } else { 0.0 };
+    let groove = groove.map_or(0.0, |groove| groove.timing(step));
+    (swing + groove) as f64
+}
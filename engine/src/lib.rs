@@ -0,0 +1,29 @@
+//! Sequencing, mixing and project handling for remdaw.
+//! Has no GUI or audio device dependencies so it can be driven by the cpal
+//! callback, the offline renderer and tests alike.
+
+pub mod command;
+pub mod decoder;
+pub mod effects;
+pub mod engine;
+pub mod envelope;
+pub mod error;
+pub mod export;
+pub mod groove;
+pub mod meter;
+pub mod mixer;
+pub mod models;
+pub mod peaks;
+pub mod project;
+pub mod resample;
+pub mod routing;
+pub mod sample;
+pub mod sampler;
+pub mod sequencer;
+pub mod synth;
+pub mod voice;
+
+pub use command::{Command, EngineHandle};
+pub use engine::Engine;
+pub use error::Error;
+pub use models::{AuxBus, ClipType, Generator, Instrument, Note, NotePattern, Pattern, PlacedClip, Playlist, Session, Step, StepResolution, TimeSignature, Track};
@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use crate::mixer::MAX_TRACKS;
+use crate::routing::MAX_AUX_BUSES;
+
+/// Instruments that get a meter. Later ones are mixed as usual but not measured.
+pub const MAX_METERED_INSTRUMENTS: usize = 128;
+/// How long the peak hold line stays put, in seconds
+const PEAK_HOLD_TIME: f32 = 1.5;
+/// How fast a displayed peak falls once the signal drops, in dB per second
+const PEAK_FALL_RATE: f32 = 24.0;
+/// Quietest level a meter shows, in dB
+pub const METER_FLOOR_DB: f32 = -60.0;
+
+/// Peak and RMS of one channel strip, left and right, as linear amplitude
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Levels {
+    pub peak: [f32; 2],
+    pub rms: [f32; 2],
+}
+
+/// Collects levels over one block on the audio thread
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LevelAccumulator {
+    peak: [f32; 2],
+    sum_squares: [f32; 2],
+    frames: usize,
+}
+
+impl LevelAccumulator {
+    pub fn add(&mut self, frame: [f32; 2]) {
+        for (channel, sample) in frame.into_iter().enumerate() {
+            self.peak[channel] = self.peak[channel].max(sample.abs());
+            self.sum_squares[channel] += sample * sample;
+        }
+        self.frames += 1;
+    }
+
+    /// Levels since the last call, starting over for the next block
+    pub fn take(&mut self) -> Levels {
+        let frames = self.frames.max(1) as f32;
+        let levels = Levels {
+            peak: self.peak,
+            rms: self.sum_squares.map(|sum| (sum / frames).sqrt()),
+        };
+        *self = Self::default();
+        levels
+    }
+}
+
+/// Levels of one strip shared with the UI without locking. Peaks build up
+/// until the UI takes them so short transients between repaints still show.
+#[derive(Default)]
+pub struct AtomicLevels {
+    peak: [AtomicU32; 2], // f32 bits
+    rms: [AtomicU32; 2],  // f32 bits
+    clipped: AtomicBool,
+}
+
+impl AtomicLevels {
+    /// Publishes a block's levels. Called from the audio thread.
+    pub fn store(&self, levels: Levels) {
+        for channel in 0..2 {
+            // non negative floats order the same as their bits
+            self.peak[channel].fetch_max(levels.peak[channel].to_bits(), Ordering::Relaxed);
+            self.rms[channel].store(levels.rms[channel].to_bits(), Ordering::Relaxed);
+        }
+        if levels.peak.iter().any(|&peak| peak > 1.0) {
+            self.clipped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Peak since the last call and the latest RMS. Called from the UI.
+    pub fn take(&self) -> Levels {
+        Levels {
+            peak: [0, 1].map(|channel| f32::from_bits(self.peak[channel].swap(0, Ordering::Relaxed))),
+            rms: [0, 1].map(|channel| f32::from_bits(self.rms[channel].load(Ordering::Relaxed))),
+        }
+    }
+
+    /// Whether the strip went over full scale since the indicator was reset
+    pub fn clipped(&self) -> bool {
+        self.clipped.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_clip(&self) {
+        self.clipped.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Every meter the engine publishes
+pub struct Meters {
+    pub master: AtomicLevels,
+    pub tracks: Box<[AtomicLevels]>,
+    pub instruments: Box<[AtomicLevels]>,
+    pub aux: Box<[AtomicLevels]>,
+}
+
+impl Default for Meters {
+    fn default() -> Self {
+        Meters {
+            master: AtomicLevels::default(),
+            tracks: (0..MAX_TRACKS).map(|_| AtomicLevels::default()).collect(),
+            instruments: (0..MAX_METERED_INSTRUMENTS).map(|_| AtomicLevels::default()).collect(),
+            aux: (0..MAX_AUX_BUSES).map(|_| AtomicLevels::default()).collect(),
+        }
+    }
+}
+
+/// What a meter shows for one channel: a peak that falls smoothly and a
+/// hold line that stays at the highest recent peak. Lives on the UI side.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PeakHold {
+    pub peak: f32, // linear amplitude
+    pub hold: f32, // linear amplitude
+    hold_left: f32, // seconds until the hold line starts falling
+}
+
+impl PeakHold {
+    /// Takes in the newest peak
+    ///
+    /// # Arguments
+    /// * `peak` - Peak measured since the last update
+    /// * `dt` - Seconds since the last update
+    pub fn update(&mut self, peak: f32, dt: f32) {
+        let fall = db_to_gain(-PEAK_FALL_RATE * dt);
+        self.peak = peak.max(self.peak * fall);
+
+        if peak >= self.hold {
+            self.hold = peak;
+            self.hold_left = PEAK_HOLD_TIME;
+        } else if self.hold_left > 0.0 {
+            self.hold_left -= dt;
+        } else {
+            self.hold = self.peak.max(self.hold * fall);
+        }
+    }
+}
+
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-10).log10()
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
@@ -0,0 +1,336 @@
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+use serde::{Deserialize, Serialize};
+use crate::effects::{EffectChain, EffectTarget};
+use crate::meter::{LevelAccumulator, Meters, MAX_METERED_INSTRUMENTS};
+use crate::models::{AuxBus, Session, Track};
+use crate::routing::{process_order, AuxSend, MAX_AUX_BUSES};
+
+/// Tracks that get their own bus. Voices on tracks past this go straight to master.
+pub const MAX_TRACKS: usize = 64;
+/// Frames mixed at a time. Longer renders are split into blocks this size.
+pub const MAX_BLOCK: usize = 512;
+/// Highest level the limiter lets through
+const LIMITER_CEILING: f32 = 1.0;
+/// Time for the limiter to recover 60 dB of gain reduction, in seconds
+const LIMITER_RELEASE: f32 = 0.5;
+
+/// Volume, pan, mute and solo of one mixer channel, for a track or an instrument
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelMix {
+    pub volume: f32, // linear gain, 1.0 leaves the level unchanged
+    pub pan: f32,    // -1.0 is hard left, 1.0 hard right
+    pub muted: bool,
+    pub solo: bool,
+}
+
+impl Default for ChannelMix {
+    fn default() -> Self {
+        ChannelMix {
+            volume: 1.0,
+            pan: 0.0,
+            muted: false,
+            solo: false,
+        }
+    }
+}
+
+impl ChannelMix {
+    /// Left and right gain of the channel. Silent when muted, or when
+    /// another channel of the same kind is soloed and this one isn't.
+    ///
+    /// # Arguments
+    /// * `any_solo` - Whether any channel of the same kind is soloed
+    pub fn gains(&self, any_solo: bool) -> [f32; 2] {
+        if !self.is_audible(any_solo) {
+            return [0.0; 2];
+        }
+        let [left, right] = pan_gains(self.pan);
+        [left * self.volume, right * self.volume]
+    }
+
+    /// Whether the channel is heard at all, i.e. not muted or soloed out
+    pub fn is_audible(&self, any_solo: bool) -> bool {
+        !self.muted && (self.solo || !any_solo)
+    }
+}
+
+/// Constant power pan law, scaled so the centre is unity gain and leaves
+/// unpanned material at its original level.
+pub fn pan_gains(pan: f32) -> [f32; 2] {
+    // exact, so unpanned channels pass audio through bit for bit
+    if pan == 0.0 {
+        return [1.0; 2];
+    }
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    [angle.cos() * SQRT_2, angle.sin() * SQRT_2]
+}
+
+/// Whether any channel in `mixes` is soloed
+pub fn any_solo<'a>(mixes: impl IntoIterator<Item = &'a ChannelMix>) -> bool {
+    mixes.into_iter().any(|mix| mix.solo)
+}
+
+/// Peak limiter with instant attack. Leaves anything under the ceiling untouched.
+pub struct Limiter {
+    gain: f32,
+    release: f32, // per frame gain multiplier while recovering
+}
+
+impl Limiter {
+    pub fn new(sample_rate: f32) -> Self {
+        Limiter {
+            gain: 1.0,
+            release: (1000f32.ln() / (LIMITER_RELEASE * sample_rate)).exp(),
+        }
+    }
+
+    pub fn process(&mut self, frame: [f32; 2]) -> [f32; 2] {
+        let peak = frame[0].abs().max(frame[1].abs());
+        self.gain = (self.gain * self.release).min(1.0);
+        if peak * self.gain > LIMITER_CEILING {
+            self.gain = LIMITER_CEILING / peak;
+        }
+        [frame[0] * self.gain, frame[1] * self.gain]
+    }
+}
+
+/// Sums voices into one bus per track, runs each track's inserts, then sums
+/// the tracks into the master bus, metering instruments, tracks and master
+/// along the way. Sends feed aux buses, which return to master after the
+/// tracks. Buses hold a block of frames so effects can process them
+/// together; they are allocated up front so mixing never allocates.
+pub struct Mixer {
+    buses: Vec<Vec<[f32; 2]>>,
+    master: Vec<[f32; 2]>, // sources that aren't on a track, e.g. the metronome, then the mix
+    frames: usize,
+    tracks: usize,
+    instruments: Vec<[f32; 2]>, // each instrument's voices this frame, for metering
+    aux: Vec<Vec<[f32; 2]>>,
+    aux_count: usize,
+    aux_order: [usize; MAX_AUX_BUSES],
+    track_effects: Vec<EffectChain>,
+    aux_effects: Vec<EffectChain>,
+    pub master_effects: EffectChain,
+    limiter: Limiter,
+    track_levels: Vec<LevelAccumulator>,
+    instrument_levels: Vec<LevelAccumulator>,
+    aux_levels: Vec<LevelAccumulator>,
+    master_levels: LevelAccumulator,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: f32) -> Self {
+        Mixer {
+            buses: vec![vec![[0.0; 2]; MAX_BLOCK]; MAX_TRACKS],
+            master: vec![[0.0; 2]; MAX_BLOCK],
+            frames: 0,
+            tracks: 0,
+            instruments: Vec::with_capacity(MAX_METERED_INSTRUMENTS),
+            aux: vec![vec![[0.0; 2]; MAX_BLOCK]; MAX_AUX_BUSES],
+            aux_count: 0,
+            aux_order: [0; MAX_AUX_BUSES],
+            track_effects: (0..MAX_TRACKS).map(|_| EffectChain::default()).collect(),
+            aux_effects: (0..MAX_AUX_BUSES).map(|_| EffectChain::default()).collect(),
+            master_effects: EffectChain::default(),
+            limiter: Limiter::new(sample_rate),
+            track_levels: vec![LevelAccumulator::default(); MAX_TRACKS],
+            instrument_levels: vec![LevelAccumulator::default(); MAX_METERED_INSTRUMENTS],
+            aux_levels: vec![LevelAccumulator::default(); MAX_AUX_BUSES],
+            master_levels: LevelAccumulator::default(),
+        }
+    }
+
+    /// Builds the live effects of every track, aux bus and the master from their slots.
+    /// Allocates, so call it before the mixer moves to the audio thread.
+    pub fn build_effects(&mut self, session: &Session, sample_rate: f32) {
+        for (chain, track) in self.track_effects.iter_mut().zip(&session.playlist.tracks) {
+            *chain = EffectChain::build(&track.effects, sample_rate);
+        }
+        for (chain, bus) in self.aux_effects.iter_mut().zip(&session.aux_buses) {
+            *chain = EffectChain::build(&bus.effects, sample_rate);
+        }
+        self.master_effects = EffectChain::build(&session.master_effects, sample_rate);
+    }
+
+    /// The live chain of a track or the master
+    pub fn effects_mut(&mut self, target: EffectTarget) -> Option<&mut EffectChain> {
+        match target {
+            EffectTarget::Master => Some(&mut self.master_effects),
+            EffectTarget::Track(track) => self.track_effects.get_mut(track),
+            EffectTarget::Aux(bus) => self.aux_effects.get_mut(bus),
+        }
+    }
+
+    /// Clears every bus for a new block of up to `MAX_BLOCK` frames
+    pub fn begin_block(&mut self, frames: usize, tracks: usize, aux_buses: usize) {
+        self.frames = frames.min(MAX_BLOCK);
+        self.tracks = tracks.min(MAX_TRACKS);
+        self.aux_count = aux_buses.min(MAX_AUX_BUSES);
+        for bus in self.buses[..self.tracks].iter_mut().chain(&mut self.aux[..self.aux_count]) {
+            bus[..self.frames].fill([0.0; 2]);
+        }
+        self.master[..self.frames].fill([0.0; 2]);
+    }
+
+    /// Clears the instrument sums for a new frame
+    pub fn begin_frame(&mut self, instruments: usize) {
+        self.instruments.clear();
+        self.instruments.resize(instruments.min(MAX_METERED_INSTRUMENTS), [0.0; 2]);
+    }
+
+    /// Counts a voice's output, after the instrument's gain, toward the instrument's meter
+    pub fn meter_instrument(&mut self, instrument: usize, frame: [f32; 2]) {
+        if let Some(sum) = self.instruments.get_mut(instrument) {
+            sum[0] += frame[0];
+            sum[1] += frame[1];
+        }
+    }
+
+    /// Adds a voice's output to the bus of `track`, or to master when it has none
+    ///
+    /// # Arguments
+    /// * `offset` - Frame within the block
+    /// * `track` - Playlist track the voice plays on
+    /// * `frame` - The voice's output
+    pub fn add(&mut self, offset: usize, track: Option<usize>, frame: [f32; 2]) {
+        let bus = match track.filter(|&track| track < self.tracks) {
+            Some(track) => &mut self.buses[track],
+            None => &mut self.master,
+        };
+        if let Some(sample) = bus.get_mut(offset) {
+            sample[0] += frame[0];
+            sample[1] += frame[1];
+        }
+    }
+
+    /// Adds a channel's output to the aux buses it sends to
+    ///
+    /// # Arguments
+    /// * `offset` - Frame within the block
+    /// * `sends` - The channel's sends
+    /// * `pre` - The channel's output before its volume and pan, silent if it's muted
+    /// * `post` - The channel's output after them
+    pub fn send(&mut self, offset: usize, sends: &[AuxSend], pre: [f32; 2], post: [f32; 2]) {
+        add_sends(&mut self.aux[..self.aux_count], offset, sends, pre, post);
+    }
+
+    /// Ends a frame, counting the instrument sums toward their meters
+    pub fn end_frame(&mut self) {
+        for (sum, levels) in self.instruments.iter().zip(&mut self.instrument_levels) {
+            levels.add(*sum);
+        }
+    }
+
+    /// Runs each track's inserts, then applies its volume, pan, mute and
+    /// solo, feeds its sends and sums it into master. Aux buses then do the
+    /// same, in an order where every bus has all its input before it runs.
+    /// Finally the master runs its own inserts and is limited.
+    ///
+    /// # Returns
+    /// * `&[[f32; 2]]` - The finished block
+    pub fn end_block(&mut self, tracks: &[Track], aux_buses: &[AuxBus], master_volume: f32) -> &[[f32; 2]] {
+        let frames = self.frames;
+        let track_solo = any_solo(tracks.iter().map(|track| &track.mix));
+
+        for (index, track) in tracks.iter().enumerate().take(self.tracks) {
+            let bus = &mut self.buses[index][..frames];
+            self.track_effects[index].process(bus);
+
+            let gains = track.mix.gains(track_solo);
+            let audible = track.mix.is_audible(track_solo);
+            for (offset, (sample, mix)) in bus.iter().zip(&mut self.master[..frames]).enumerate() {
+                let post = [sample[0] * gains[0], sample[1] * gains[1]];
+                let pre = if audible { *sample } else { [0.0; 2] };
+                add_sends(&mut self.aux[..self.aux_count], offset, &track.sends, pre, post);
+                self.track_levels[index].add(post);
+                mix[0] += post[0];
+                mix[1] += post[1];
+            }
+        }
+
+        let aux_buses = &aux_buses[..self.aux_count.min(aux_buses.len())];
+        let aux_solo = any_solo(aux_buses.iter().map(|bus| &bus.mix));
+        let count = process_order(aux_buses, &mut self.aux_order);
+        for position in 0..count {
+            let index = self.aux_order[position];
+            let aux = &aux_buses[index];
+
+            // taken out of the list while it feeds the others, which doesn't
+            // allocate and leaves nothing for it to send to itself
+            let mut bus = std::mem::take(&mut self.aux[index]);
+            self.aux_effects[index].process(&mut bus[..frames]);
+
+            // buses that already ran can't take any more input
+            let waiting = &self.aux_order[position + 1..count];
+            let gains = aux.mix.gains(aux_solo);
+            let audible = aux.mix.is_audible(aux_solo);
+            for (offset, (sample, mix)) in bus[..frames].iter().zip(&mut self.master[..frames]).enumerate() {
+                let post = [sample[0] * gains[0], sample[1] * gains[1]];
+                let pre = if audible { *sample } else { [0.0; 2] };
+                let sends = aux.sends.iter().filter(|send| waiting.contains(&send.bus));
+                add_sends(&mut self.aux[..self.aux_count], offset, sends, pre, post);
+                self.aux_levels[index].add(post);
+                mix[0] += post[0];
+                mix[1] += post[1];
+            }
+            self.aux[index] = bus;
+        }
+
+        let master = &mut self.master[..frames];
+        self.master_effects.process(master);
+        for frame in master.iter_mut() {
+            *frame = [frame[0] * master_volume, frame[1] * master_volume];
+            // metered before the limiter so overs still show
+            self.master_levels.add(*frame);
+            *frame = self.limiter.process(*frame);
+        }
+        master
+    }
+
+    /// Publishes the levels collected since the last call, if anyone is
+    /// listening, and starts collecting again
+    pub fn publish_levels(&mut self, meters: Option<&Meters>) {
+        let master = self.master_levels.take();
+        if let Some(meters) = meters {
+            meters.master.store(master);
+        }
+
+        for (i, levels) in self.track_levels.iter_mut().enumerate().take(self.tracks) {
+            let levels = levels.take();
+            if let Some(meters) = meters {
+                meters.tracks[i].store(levels);
+            }
+        }
+        for (i, levels) in self.instrument_levels.iter_mut().enumerate().take(self.instruments.len()) {
+            let levels = levels.take();
+            if let Some(meters) = meters {
+                meters.instruments[i].store(levels);
+            }
+        }
+        for (i, levels) in self.aux_levels.iter_mut().enumerate().take(self.aux_count) {
+            let levels = levels.take();
+            if let Some(meters) = meters {
+                meters.aux[i].store(levels);
+            }
+        }
+    }
+}
+
+/// Adds one frame of a channel to every aux bus it sends to
+fn add_sends<'a>(
+    aux: &mut [Vec<[f32; 2]>],
+    offset: usize,
+    sends: impl IntoIterator<Item = &'a AuxSend>,
+    pre: [f32; 2],
+    post: [f32; 2],
+) {
+    for send in sends {
+        let Some(sample) = aux.get_mut(send.bus).and_then(|bus| bus.get_mut(offset)) else {
+            continue;
+        };
+        let source = if send.pre_fader { pre } else { post };
+        sample[0] += source[0] * send.amount;
+        sample[1] += source[1] * send.amount;
+    }
+}
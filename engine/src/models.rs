@@ -0,0 +1,581 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::effects::{EffectSlot, EffectTarget};
+use crate::groove::Groove;
+use crate::mixer::ChannelMix;
+use crate::routing::{AuxSend, SendSource};
+use crate::sample::Sample;
+use crate::sampler::SamplerSettings;
+use crate::synth::SynthPatch;
+use crate::voice::{Sound, StealPolicy, DEFAULT_POLYPHONY};
+
+/// Steps in a new pattern: one bar of 16th notes
+pub const DEFAULT_PATTERN_STEPS: usize = 16;
+/// Longest a pattern can be, in steps
+pub const MAX_PATTERN_STEPS: usize = 64;
+/// Slowest tempo a session can play at
+pub const MIN_BPM: i16 = 40;
+/// Fastest tempo a session can play at
+pub const MAX_BPM: i16 = 300;
+
+/// Meter of the song. Beats everywhere else are quarter notes, so a bar of
+/// 7/8 is three and a half beats long.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeSignature {
+    pub numerator: u8,   // beats in a bar
+    pub denominator: u8, // note value of a beat, 4 for quarter notes
+}
+
+impl TimeSignature {
+    /// Signatures offered in the toolbar
+    pub const COMMON: [TimeSignature; 6] = [
+        TimeSignature::new(4, 4),
+        TimeSignature::new(3, 4),
+        TimeSignature::new(5, 4),
+        TimeSignature::new(6, 8),
+        TimeSignature::new(7, 8),
+        TimeSignature::new(12, 8),
+    ];
+
+    pub const fn new(numerator: u8, denominator: u8) -> Self {
+        TimeSignature { numerator, denominator }
+    }
+
+    /// Length of one beat of the signature, in quarter note beats
+    pub fn beat_length(&self) -> f64 {
+        4.0 / self.denominator.max(1) as f64
+    }
+
+    /// Length of one bar, in quarter note beats
+    pub fn bar_length(&self) -> f64 {
+        self.numerator as f64 * self.beat_length()
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.numerator, self.denominator)
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        TimeSignature::new(4, 4)
+    }
+}
+
+/// Note value of one pattern step
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepResolution {
+    Quarter,
+    Eighth,
+    EighthTriplet,
+    #[default]
+    Sixteenth,
+    SixteenthTriplet,
+    ThirtySecond,
+}
+
+impl StepResolution {
+    pub const ALL: [StepResolution; 6] = [
+        StepResolution::Quarter,
+        StepResolution::Eighth,
+        StepResolution::EighthTriplet,
+        StepResolution::Sixteenth,
+        StepResolution::SixteenthTriplet,
+        StepResolution::ThirtySecond,
+    ];
+
+    /// Steps in one quarter note beat
+    pub fn steps_per_beat(self) -> f64 {
+        match self {
+            StepResolution::Quarter => 1.0,
+            StepResolution::Eighth => 2.0,
+            StepResolution::EighthTriplet => 3.0,
+            StepResolution::Sixteenth => 4.0,
+            StepResolution::SixteenthTriplet => 6.0,
+            StepResolution::ThirtySecond => 8.0,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StepResolution::Quarter => "1/4",
+            StepResolution::Eighth => "1/8",
+            StepResolution::EighthTriplet => "1/8 triplet",
+            StepResolution::Sixteenth => "1/16",
+            StepResolution::SixteenthTriplet => "1/16 triplet",
+            StepResolution::ThirtySecond => "1/32",
+        }
+    }
+}
+
+// Where all music positions are stored for playback and export
+#[derive(Clone)]
+pub struct Playlist {
+    pub tracks: Vec<Track>,
+    pub clips: Vec<PlacedClip>,
+}
+
+// one group of patterns of drums from channel rack
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Pattern {
+    pub name: String,
+    pub data: Vec<Vec<Step>>,  // one row of `steps` steps for each instrument
+    #[serde(default = "default_steps")]
+    pub steps: usize,
+    #[serde(default)]
+    pub resolution: StepResolution,
+    #[serde(default)]
+    pub swing: Option<f32>, // None follows the session's swing
+    #[serde(default)]
+    pub groove: Option<Groove>,
+}
+
+fn default_steps() -> usize {
+    DEFAULT_PATTERN_STEPS
+}
+
+/// Highest fine pitch of a step either way, in cents
+pub const STEP_PITCH_RANGE: f32 = 100.0;
+
+/// One step of a pattern row. Turning a step off keeps its settings, so
+/// they come back when it's turned on again.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StepFormat", into = "StepFormat")]
+pub struct Step {
+    pub active: bool,
+    pub velocity: f32,    // 0.0 to 1.0, scales the gain of the hit
+    pub pan: f32,         // -1.0 to 1.0, on top of the instrument's pan
+    pub pitch: f32,       // fine tune in cents
+    pub probability: f32, // chance of the step playing each time round, 0.0 to 1.0
+}
+
+impl Step {
+    pub const OFF: Step = Step { active: false, velocity: 1.0, pan: 0.0, pitch: 0.0, probability: 1.0 };
+    pub const ON: Step = Step { active: true, ..Step::OFF };
+
+    /// Playback rate that gives the step's pitch
+    pub fn rate(&self) -> f32 {
+        2f32.powf(self.pitch / 1200.0)
+    }
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Step::OFF
+    }
+}
+
+/// How a step is saved. Steps left at the default settings are plain
+/// booleans, which is also how version 1 projects stored every step.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StepFormat {
+    Active(bool),
+    Step(StepFields),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct StepFields {
+    active: bool,
+    velocity: f32,
+    pan: f32,
+    pitch: f32,
+    probability: f32,
+}
+
+impl Default for StepFields {
+    fn default() -> Self {
+        Step::OFF.into()
+    }
+}
+
+impl From<Step> for StepFields {
+    fn from(Step { active, velocity, pan, pitch, probability }: Step) -> Self {
+        StepFields { active, velocity, pan, pitch, probability }
+    }
+}
+
+impl From<StepFormat> for Step {
+    fn from(format: StepFormat) -> Self {
+        match format {
+            StepFormat::Active(active) => Step { active, ..Step::OFF },
+            StepFormat::Step(StepFields { active, velocity, pan, pitch, probability }) => {
+                Step { active, velocity, pan, pitch, probability }
+            }
+        }
+    }
+}
+
+impl From<Step> for StepFormat {
+    fn from(step: Step) -> Self {
+        if step == Step::ON || step == Step::OFF {
+            StepFormat::Active(step.active)
+        } else {
+            StepFormat::Step(step.into())
+        }
+    }
+}
+
+/// Key an instrument plays its sample at unless set otherwise: middle C
+pub const DEFAULT_ROOT_KEY: u8 = 60;
+/// Length of a new note pattern, in beats
+pub const DEFAULT_NOTE_PATTERN_LENGTH: f64 = 4.0;
+
+/// One note of a note pattern
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub key: u8,     // MIDI key number, 60 is middle C
+    pub start: f64, // in beats from the start of the pattern
+    pub length: f64, // in beats
+    pub velocity: f32, // 0.0 to 1.0
+}
+
+/// Pitched notes played by a single instrument, edited in the piano roll
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NotePattern {
+    pub name: String,
+    pub instrument: usize, // index into instruments vec
+    pub notes: Vec<Note>,
+    pub length: f64, // in beats, the pattern loops after this
+}
+
+impl NotePattern {
+    /// A bar with no notes
+    pub fn new(name: String, instrument: usize) -> Self {
+        NotePattern {
+            name,
+            instrument,
+            notes: Vec::new(),
+            length: DEFAULT_NOTE_PATTERN_LENGTH,
+        }
+    }
+}
+
+/// Name of a MIDI key, like C4 for middle C
+pub fn key_name(key: u8) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[key as usize % 12], key as i32 / 12 - 1)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ClipType {
+    Pattern(usize), // Index into patterns vec
+    AudioFile(usize), // Index into instruments vec
+    Notes(usize), // Index into note patterns vec
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlacedClip {
+    pub clip_type: ClipType,
+    pub name: String,
+    pub track_index: usize,
+    pub start_time: f64, // in beats
+    pub length: f64,
+    pub color: [u8; 4], // RGBA, only used for drawing
+    #[serde(default)]
+    pub offset: f64, // beats into the sample an audio clip starts playing from
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Track {
+    pub name: String,
+    pub height: f32,
+    #[serde(flatten)]
+    pub mix: ChannelMix, // volume, pan, mute and solo of the track's bus
+    #[serde(default)]
+    pub effects: Vec<EffectSlot>, // inserts, run before the fader
+    #[serde(default)]
+    pub sends: Vec<AuxSend>,
+}
+
+/// A return bus: sums what channels send to it, runs its effects and
+/// returns to master
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuxBus {
+    pub name: String,
+    #[serde(flatten)]
+    pub mix: ChannelMix,
+    #[serde(default)]
+    pub effects: Vec<EffectSlot>,
+    #[serde(default)]
+    pub sends: Vec<AuxSend>, // to other aux buses, never in a loop
+}
+
+impl AuxBus {
+    pub fn new(name: String) -> Self {
+        AuxBus {
+            name,
+            mix: ChannelMix::default(),
+            effects: Vec::new(),
+            sends: Vec::new(),
+        }
+    }
+}
+
+// loaded sounds
+#[derive(Clone)]
+pub struct Instrument {
+    pub sample: Sample, // the decoded audio, shared with the UI's copy of the session
+    pub name: String,
+    pub file_path: PathBuf,
+    pub polyphony: usize, // most hits that can ring at once
+    pub steal: StealPolicy, // which hit makes room for a new one past the limit
+    pub mix: ChannelMix, // applied to every voice before it reaches the track
+    pub sends: Vec<AuxSend>,
+    pub root_key: u8, // key the sample plays at its own pitch
+    pub generator: Generator,
+    pub sampler: SamplerSettings, // how the sample plays, unused by synths
+}
+
+/// Where an instrument's sound comes from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Generator {
+    /// Plays the instrument's sample
+    #[default]
+    Sampler,
+    /// Synthesizes every note, the sample is left empty
+    Synth(SynthPatch),
+}
+
+impl Instrument {
+    /// Wraps already decoded audio, named after the file it came from
+    pub fn new(file_path: PathBuf, sample: Sample) -> Self {
+        Instrument {
+            name: file_name(&file_path),
+            file_path,
+            sample,
+            polyphony: DEFAULT_POLYPHONY,
+            steal: StealPolicy::default(),
+            mix: ChannelMix::default(),
+            sends: Vec::new(),
+            root_key: DEFAULT_ROOT_KEY,
+            generator: Generator::Sampler,
+            sampler: SamplerSettings::default(),
+        }
+    }
+
+    /// A synth with the default patch and no sample
+    pub fn synth(name: String) -> Self {
+        Instrument {
+            name,
+            generator: Generator::Synth(SynthPatch::default()),
+            ..Instrument::new(PathBuf::new(), Sample::default())
+        }
+    }
+
+    pub fn is_synth(&self) -> bool {
+        matches!(self.generator, Generator::Synth(_))
+    }
+
+    /// What the instrument's voices play
+    pub fn sound(&self) -> Sound<'_> {
+        match &self.generator {
+            Generator::Sampler => Sound::Sample(&self.sample, &self.sampler),
+            Generator::Synth(patch) => Sound::Synth(patch),
+        }
+    }
+
+    /// Playback rate that sounds `key`, shifted from the root key
+    pub fn key_rate(&self, key: u8) -> f32 {
+        2f32.powf((key as f32 - self.root_key as f32) / 12.0)
+    }
+
+    /// How long the trimmed sample plays for at `bpm`, in beats
+    pub fn length_in_beats(&self, bpm: i16) -> f64 {
+        let frames = self.sampler.region(self.sample.len()).len();
+        frames / self.sample.sample_rate.max(1) as f64 * bpm as f64 / 60.0
+    }
+}
+
+/// The song being edited: everything a project file stores.
+/// The UI owns one copy and the engine plays another, kept in sync with commands.
+#[derive(Clone)]
+pub struct Session {
+    pub instruments: Vec<Instrument>,
+    pub patterns: Vec<Pattern>,
+    pub note_patterns: Vec<NotePattern>,
+    pub playlist: Playlist,
+    pub bpm: i16,
+    pub master_volume: f32,
+    pub master_effects: Vec<EffectSlot>,
+    pub aux_buses: Vec<AuxBus>,
+    pub time_signature: TimeSignature,
+    pub swing: f32, // 0.0 to 1.0, for patterns without their own
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// No instruments or patterns and the default tracks
+    pub fn new() -> Self {
+        Session {
+            instruments: Vec::new(),
+            patterns: Vec::new(),
+            note_patterns: Vec::new(),
+            playlist: Playlist::new(),
+            bpm: 130,
+            master_volume: 1.0,
+            master_effects: Vec::new(),
+            aux_buses: Vec::new(),
+            time_signature: TimeSignature::default(),
+            swing: 0.0,
+        }
+    }
+
+    /// Adds an instrument along with an empty row in every pattern
+    pub fn add_instrument(&mut self, instrument: Instrument) -> usize {
+        self.instruments.push(instrument);
+        for pattern in &mut self.patterns {
+            pattern.data.push(vec![Step::OFF; pattern.steps]);
+        }
+        self.instruments.len() - 1
+    }
+
+    /// The saved effects of a track or the master
+    pub fn effects(&self, target: EffectTarget) -> Option<&Vec<EffectSlot>> {
+        match target {
+            EffectTarget::Master => Some(&self.master_effects),
+            EffectTarget::Track(track) => self.playlist.tracks.get(track).map(|track| &track.effects),
+            EffectTarget::Aux(bus) => self.aux_buses.get(bus).map(|bus| &bus.effects),
+        }
+    }
+
+    pub fn effects_mut(&mut self, target: EffectTarget) -> Option<&mut Vec<EffectSlot>> {
+        match target {
+            EffectTarget::Master => Some(&mut self.master_effects),
+            EffectTarget::Track(track) => self.playlist.tracks.get_mut(track).map(|track| &mut track.effects),
+            EffectTarget::Aux(bus) => self.aux_buses.get_mut(bus).map(|bus| &mut bus.effects),
+        }
+    }
+
+    /// The sends of a track, instrument or aux bus
+    pub fn sends(&self, source: SendSource) -> Option<&Vec<AuxSend>> {
+        match source {
+            SendSource::Track(track) => self.playlist.tracks.get(track).map(|track| &track.sends),
+            SendSource::Instrument(instrument) => self.instruments.get(instrument).map(|instrument| &instrument.sends),
+            SendSource::Aux(bus) => self.aux_buses.get(bus).map(|bus| &bus.sends),
+        }
+    }
+
+    pub fn sends_mut(&mut self, source: SendSource) -> Option<&mut Vec<AuxSend>> {
+        match source {
+            SendSource::Track(track) => self.playlist.tracks.get_mut(track).map(|track| &mut track.sends),
+            SendSource::Instrument(instrument) => self.instruments.get_mut(instrument).map(|instrument| &mut instrument.sends),
+            SendSource::Aux(bus) => self.aux_buses.get_mut(bus).map(|bus| &mut bus.sends),
+        }
+    }
+
+    /// Removes a note pattern along with its clips. Clips of later note
+    /// patterns are renumbered.
+    pub fn remove_note_pattern(&mut self, index: usize) -> NotePattern {
+        let pattern = self.note_patterns.remove(index);
+        self.playlist.clips.retain(|clip| !matches!(clip.clip_type, ClipType::Notes(notes) if notes == index));
+        for clip in &mut self.playlist.clips {
+            if let ClipType::Notes(notes) = &mut clip.clip_type
+                && *notes > index {
+                *notes -= 1;
+            }
+        }
+        pattern
+    }
+
+    /// Removes an aux bus along with every send to it. Sends to later buses
+    /// are renumbered.
+    pub fn remove_aux_bus(&mut self, index: usize) -> AuxBus {
+        let bus = self.aux_buses.remove(index);
+        let sends = self.playlist.tracks.iter_mut().map(|track| &mut track.sends)
+            .chain(self.instruments.iter_mut().map(|instrument| &mut instrument.sends))
+            .chain(self.aux_buses.iter_mut().map(|bus| &mut bus.sends));
+        for sends in sends {
+            sends.retain(|send| send.bus != index);
+            for send in sends.iter_mut().filter(|send| send.bus > index) {
+                send.bus -= 1;
+            }
+        }
+        bus
+    }
+}
+
+impl Pattern {
+    /// A bar of 16th notes with no active steps
+    pub fn empty(name: String, num_instruments: usize) -> Self {
+        Pattern {
+            name,
+            data: vec![vec![Step::OFF; DEFAULT_PATTERN_STEPS]; num_instruments],
+            steps: DEFAULT_PATTERN_STEPS,
+            resolution: StepResolution::default(),
+            swing: None,
+            groove: None,
+        }
+    }
+
+    /// Changes the step count, keeping the steps that still fit. Steps past
+    /// the end are dropped, new ones start off.
+    pub fn set_steps(&mut self, steps: usize) {
+        self.steps = steps.clamp(1, MAX_PATTERN_STEPS);
+        for row in &mut self.data {
+            row.resize(self.steps, Step::OFF);
+        }
+    }
+
+    /// Length of one time through the pattern, in beats
+    pub fn length(&self) -> f64 {
+        self.steps as f64 / self.resolution.steps_per_beat()
+    }
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Playlist {
+    pub fn new() -> Self {
+        Playlist {
+            tracks: vec![
+                Track {
+                    name: "Track 1".to_string(),
+                    height: 60.0,
+                    mix: ChannelMix::default(),
+                    effects: Vec::new(),
+                    sends: Vec::new(),
+                },
+                Track {
+                    name: "Track 2".to_string(),
+                    height: 50.0,
+                    mix: ChannelMix::default(),
+                    effects: Vec::new(),
+                    sends: Vec::new(),
+                },
+                Track {
+                    name: "Track 3".to_string(),
+                    height: 50.0,
+                    mix: ChannelMix::default(),
+                    effects: Vec::new(),
+                    sends: Vec::new(),
+                },
+            ],
+            clips: Vec::new(),  // Empty - user will add clips
+        }
+    }
+
+    /// Beat where the last clip ends
+    pub fn length(&self) -> f64 {
+        self.clips.iter()
+            .map(|clip| clip.start_time + clip.length)
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Shortens file path down to the file name.
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Unknown").to_owned()
+}
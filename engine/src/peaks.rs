@@ -0,0 +1,97 @@
+//! Min/max summaries of a sample at several resolutions, so a waveform can
+//! be drawn at any zoom without reading every frame it covers.
+
+/// Frames summarized by one peak of the finest level
+pub const BASE_BUCKET: usize = 64;
+/// Buckets of one level that make up one bucket of the next
+const LEVEL_FACTOR: usize = 4;
+
+/// Lowest and highest value over some frames, both channels together
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peak {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Peak {
+    /// Covers nothing yet, so any value widens it
+    pub const EMPTY: Peak = Peak { min: f32::INFINITY, max: f32::NEG_INFINITY };
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+
+    fn add_frame(&mut self, frame: [f32; 2]) {
+        self.min = self.min.min(frame[0]).min(frame[1]);
+        self.max = self.max.max(frame[0]).max(frame[1]);
+    }
+
+    fn merge(&mut self, other: Peak) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// One level of the cache: a peak for every `bucket` frames
+struct Level {
+    bucket: usize,
+    peaks: Vec<Peak>,
+}
+
+/// Peaks of a sample from `BASE_BUCKET` frames each up to a handful for the
+/// whole file. Each level is a quarter the size of the one before.
+pub struct PeakCache {
+    levels: Vec<Level>,
+}
+
+impl PeakCache {
+    pub fn new(frames: &[[f32; 2]]) -> Self {
+        let finest = frames.chunks(BASE_BUCKET)
+            .map(|chunk| {
+                let mut peak = Peak::EMPTY;
+                chunk.iter().for_each(|frame| peak.add_frame(*frame));
+                peak
+            })
+            .collect();
+        let mut levels = vec![Level { bucket: BASE_BUCKET, peaks: finest }];
+
+        while let Some(last) = levels.last()
+            && last.peaks.len() > 1 {
+            let peaks = last.peaks.chunks(LEVEL_FACTOR)
+                .map(|chunk| {
+                    let mut peak = Peak::EMPTY;
+                    chunk.iter().for_each(|other| peak.merge(*other));
+                    peak
+                })
+                .collect();
+            let bucket = last.bucket * LEVEL_FACTOR;
+            levels.push(Level { bucket, peaks });
+        }
+
+        PeakCache { levels }
+    }
+
+    /// Peak of `frames[start..end]`. Reads the coarsest level whose buckets
+    /// fit in the range, so the cost stays the same at any zoom. Buckets
+    /// overlapping the ends are read whole, widening the range by less than
+    /// a bucket on each side.
+    ///
+    /// # Arguments
+    /// * `frames` - The frames the cache was built from, read directly for ranges shorter than a bucket
+    pub fn range(&self, frames: &[[f32; 2]], start: usize, end: usize) -> Peak {
+        let end = end.min(frames.len());
+        let mut peak = Peak::EMPTY;
+        if start >= end {
+            return peak;
+        }
+
+        match self.levels.iter().rev().find(|level| level.bucket <= end - start) {
+            Some(level) => {
+                let last = end.div_ceil(level.bucket).min(level.peaks.len());
+                level.peaks[start / level.bucket..last].iter().for_each(|other| peak.merge(*other));
+            }
+            None => frames[start..end].iter().for_each(|frame| peak.add_frame(*frame)),
+        }
+        peak
+    }
+}
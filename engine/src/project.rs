@@ -0,0 +1,285 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::models::{file_name, AuxBus, ClipType, Generator, Instrument, NotePattern, Pattern, PlacedClip, Session, TimeSignature, Track, DEFAULT_ROOT_KEY, MAX_BPM, MIN_BPM};
+use crate::decoder::load_sample;
+use crate::effects::EffectSlot;
+use crate::error::{Error, Result};
+use crate::mixer::ChannelMix;
+use crate::routing::{self, AuxSend};
+use crate::sample::Sample;
+use crate::sampler::SamplerSettings;
+use crate::voice::{StealPolicy, DEFAULT_POLYPHONY};
+
+/// Version written into every saved project. Bump when the format changes
+/// in a way older builds can't read.
+pub const PROJECT_VERSION: u32 = 3;
+
+/// File extension used by the save/open dialogs
+pub const PROJECT_EXTENSION: &str = "remdaw";
+
+/// A reference to a sample on disk. The path is stored relative to the
+/// project file whenever the sample lives next to (or below) it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InstrumentRef {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default = "default_polyphony")]
+    pub polyphony: usize,
+    #[serde(default)]
+    pub steal: StealPolicy,
+    #[serde(flatten)]
+    pub mix: ChannelMix,
+    #[serde(default)]
+    pub sends: Vec<AuxSend>,
+    #[serde(default = "default_root_key")]
+    pub root_key: u8,
+    #[serde(default)]
+    pub generator: Generator,
+    #[serde(default)]
+    pub sampler: SamplerSettings,
+}
+
+fn default_root_key() -> u8 {
+    DEFAULT_ROOT_KEY
+}
+
+fn default_polyphony() -> usize {
+    DEFAULT_POLYPHONY
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+/// Everything needed to restore a session
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+    pub bpm: i16,
+    #[serde(default)]
+    pub time_signature: TimeSignature,
+    #[serde(default)]
+    pub swing: f32,
+    #[serde(default = "default_volume")]
+    pub master_volume: f32,
+    #[serde(default)]
+    pub master_effects: Vec<EffectSlot>,
+    #[serde(default)]
+    pub instruments: Vec<InstrumentRef>,
+    #[serde(default)]
+    pub patterns: Vec<Pattern>,
+    #[serde(default)]
+    pub note_patterns: Vec<NotePattern>,
+    #[serde(default)]
+    pub tracks: Vec<Track>,
+    #[serde(default)]
+    pub aux_buses: Vec<AuxBus>,
+    #[serde(default)]
+    pub clips: Vec<PlacedClip>,
+}
+
+impl Project {
+    /// Snapshots the current session.
+    ///
+    /// # Arguments
+    /// * `session` - Session to read patterns, clips, tracks and instruments from
+    /// * `project_dir` - Directory the project will be saved in, used to relativize sample paths
+    pub fn from_session(session: &Session, project_dir: &Path) -> Self {
+        Project {
+            version: PROJECT_VERSION,
+            bpm: session.bpm,
+            time_signature: session.time_signature,
+            swing: session.swing,
+            master_volume: session.master_volume,
+            master_effects: session.master_effects.clone(),
+            instruments: session.instruments.iter()
+                .map(|instrument| InstrumentRef {
+                    name: instrument.name.clone(),
+                    path: relative_to(&instrument.file_path, project_dir),
+                    polyphony: instrument.polyphony,
+                    steal: instrument.steal,
+                    mix: instrument.mix,
+                    sends: instrument.sends.clone(),
+                    root_key: instrument.root_key,
+                    generator: instrument.generator,
+                    sampler: instrument.sampler,
+                })
+                .collect(),
+            patterns: session.patterns.clone(),
+            note_patterns: session.note_patterns.clone(),
+            tracks: session.playlist.tracks.clone(),
+            aux_buses: session.aux_buses.clone(),
+            clips: session.playlist.clips.clone(),
+        }
+    }
+
+    /// Reads and parses a project file from disk
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|err| Error::project(path, err))?;
+        let project: Project = serde_json::from_str(&content)
+            .map_err(|err| Error::project(path, format!("invalid project file: {}", err)))?;
+
+        if project.version > PROJECT_VERSION {
+            return Err(Error::ProjectVersion {
+                path: path.to_path_buf(),
+                version: project.version,
+                supported: PROJECT_VERSION,
+            });
+        }
+        project.validate().map_err(|reason| Error::project(path, reason))?;
+        Ok(project)
+    }
+
+    /// Checks what a hand edited file could get wrong and the engine can't
+    /// recover from: a tempo out of range, or an index past the end of the
+    /// list it points into.
+    fn validate(&self) -> std::result::Result<(), String> {
+        if !(MIN_BPM..=MAX_BPM).contains(&self.bpm) {
+            return Err(format!("tempo {} is outside {}..={}", self.bpm, MIN_BPM, MAX_BPM));
+        }
+        for (i, note_pattern) in self.note_patterns.iter().enumerate() {
+            if note_pattern.instrument >= self.instruments.len() {
+                return Err(format!("note pattern {} plays missing instrument {}", i, note_pattern.instrument));
+            }
+        }
+        for (i, clip) in self.clips.iter().enumerate() {
+            let (index, count, kind) = match clip.clip_type {
+                ClipType::Pattern(index) => (index, self.patterns.len(), "pattern"),
+                ClipType::AudioFile(index) => (index, self.instruments.len(), "instrument"),
+                ClipType::Notes(index) => (index, self.note_patterns.len(), "note pattern"),
+            };
+            if index >= count {
+                return Err(format!("clip {} refers to missing {} {}", i, kind, index));
+            }
+            if clip.track_index >= self.tracks.len() {
+                return Err(format!("clip {} is on missing track {}", i, clip.track_index));
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the project to disk as pretty printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|err| Error::project(path, err))?;
+        fs::write(path, json).map_err(|err| Error::project(path, err))
+    }
+
+    /// Replaces `session` with this project.
+    /// Samples that can't be found or decoded are loaded as silence so clip
+    /// and pattern indices stay valid. Aux sends that loop back on themselves
+    /// are dropped.
+    ///
+    /// # Returns
+    /// * `Vec<Error>` - One error per sample that could not be loaded, and one
+    ///   if any sends had to be dropped
+    pub fn apply(self, session: &mut Session, project_dir: &Path) -> Vec<Error> {
+        let mut errors = Vec::new();
+
+        session.instruments = self.instruments.iter()
+            .map(|instrument| {
+                // synths have no sample to load
+                let (path, sample) = match instrument.generator {
+                    Generator::Synth(_) => (PathBuf::new(), Sample::default()),
+                    Generator::Sampler => {
+                        let path = resolve_path(&instrument.path, project_dir);
+                        match load_sample(&path) {
+                            Ok(sample) => (path, sample),
+                            Err(err) => {
+                                errors.push(err);
+                                (path, Sample::default())
+                            }
+                        }
+                    }
+                };
+                Instrument {
+                    name: instrument.name.clone(),
+                    polyphony: instrument.polyphony,
+                    steal: instrument.steal,
+                    mix: instrument.mix,
+                    sends: instrument.sends.clone(),
+                    root_key: instrument.root_key,
+                    generator: instrument.generator,
+                    sampler: instrument.sampler,
+                    ..Instrument::new(path, sample)
+                }
+            })
+            .collect();
+
+        // every pattern needs one row per instrument, each as long as the pattern
+        let mut patterns = self.patterns;
+        for pattern in &mut patterns {
+            pattern.data.resize(session.instruments.len(), Vec::new());
+            pattern.set_steps(pattern.steps);
+        }
+        if patterns.is_empty() {
+            patterns.push(Pattern::empty("Pattern 1".to_string(), session.instruments.len()));
+        }
+
+        session.patterns = patterns;
+        session.note_patterns = self.note_patterns;
+        session.playlist.tracks = self.tracks;
+        session.playlist.clips = self.clips;
+        session.bpm = self.bpm;
+        session.time_signature = self.time_signature;
+        session.swing = self.swing;
+        session.master_volume = self.master_volume;
+        session.master_effects = self.master_effects;
+
+        // only possible in a project edited by hand
+        let mut aux_buses = self.aux_buses;
+        if let Err(err) = routing::validate(&aux_buses) {
+            routing::break_loops(&mut aux_buses);
+            errors.push(err);
+        }
+        session.aux_buses = aux_buses;
+
+        errors
+    }
+}
+
+/// Directory a project's relative sample paths are resolved against
+pub fn project_dir(project_path: &Path) -> PathBuf {
+    project_path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Makes sure a chosen save path ends with the project extension
+pub fn with_extension(path: PathBuf) -> PathBuf {
+    if path.extension().and_then(|e| e.to_str()) == Some(PROJECT_EXTENSION) {
+        path
+    } else {
+        path.with_extension(PROJECT_EXTENSION)
+    }
+}
+
+/// Stores `path` relative to `base` if it lives inside it, otherwise absolute.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let base = fs::canonicalize(base).unwrap_or_else(|_| base.to_path_buf());
+
+    match absolute.strip_prefix(&base) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => absolute,
+    }
+}
+
+/// Resolves a stored sample path against the project directory.
+/// Falls back to a file with the same name next to the project, which
+/// covers projects that were moved together with their samples.
+fn resolve_path(path: &Path, base: &Path) -> PathBuf {
+    let resolved = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        // normalize separators so projects saved on windows open elsewhere
+        let relative: PathBuf = path.to_string_lossy().split(['/', '\\']).collect();
+        base.join(relative)
+    };
+
+    if !resolved.is_file() {
+        let sibling = base.join(file_name(&resolved));
+        if sibling.is_file() {
+            return sibling;
+        }
+    }
+    resolved
+}
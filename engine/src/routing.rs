@@ -0,0 +1,130 @@
+//! Send/return routing. Tracks, instruments and aux buses can send part of
+//! their signal to aux buses, which run their own effects and return to
+//! master. Aux buses may feed each other, as long as nothing feeds back
+//! into itself.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{Error, Result};
+use crate::models::AuxBus;
+
+/// Aux buses the mixer has room for. Later ones are silent.
+pub const MAX_AUX_BUSES: usize = 16;
+
+/// Part of a channel's signal sent to an aux bus
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuxSend {
+    pub bus: usize,  // index into the session's aux buses
+    pub amount: f32, // linear gain
+    #[serde(default)]
+    pub pre_fader: bool, // taps the signal before the channel's volume and pan
+}
+
+impl AuxSend {
+    pub fn new(bus: usize) -> Self {
+        AuxSend { bus, amount: 1.0, pre_fader: false }
+    }
+}
+
+/// A channel that can send to aux buses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendSource {
+    Track(usize),
+    Instrument(usize),
+    Aux(usize),
+}
+
+/// Whether `from` sending to `to` would make a loop, because `to` already
+/// reaches `from` through its own sends, or they're the same bus.
+pub fn creates_loop(buses: &[AuxBus], from: usize, to: usize) -> bool {
+    let mut visited = vec![false; buses.len()];
+    let mut stack = vec![to];
+
+    while let Some(bus) = stack.pop() {
+        if bus == from {
+            return true;
+        }
+        if bus >= buses.len() || visited[bus] {
+            continue;
+        }
+        visited[bus] = true;
+        stack.extend(buses[bus].sends.iter().map(|send| send.bus));
+    }
+    false
+}
+
+/// Checks that no aux bus feeds back into itself
+pub fn validate(buses: &[AuxBus]) -> Result<()> {
+    for (index, bus) in buses.iter().enumerate() {
+        // a send is on a loop exactly when its destination leads back here
+        for send in &bus.sends {
+            if creates_loop(buses, index, send.bus) {
+                return Err(Error::routing(format!(
+                    "{} sends to {}, which feeds back into itself",
+                    bus.name,
+                    buses.get(send.bus).map_or("a missing bus", |bus| &bus.name),
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Drops every aux to aux send that makes a loop, keeping the earliest ones.
+/// Used on projects that were edited by hand.
+///
+/// # Returns
+/// * `usize` - How many sends were dropped
+pub fn break_loops(buses: &mut [AuxBus]) -> usize {
+    // add the sends back one by one, skipping any that close a loop
+    let sends: Vec<Vec<AuxSend>> = buses.iter_mut().map(|bus| std::mem::take(&mut bus.sends)).collect();
+    let mut dropped = 0;
+    for (index, sends) in sends.into_iter().enumerate() {
+        for send in sends {
+            if creates_loop(buses, index, send.bus) {
+                dropped += 1;
+            } else {
+                buses[index].sends.push(send);
+            }
+        }
+    }
+    dropped
+}
+
+/// Works out an order to process aux buses in so each one has every send
+/// into it before it runs. Doesn't allocate, so the audio thread can call it.
+/// Buses caught in a loop go last in index order; the mixer then drops
+/// sends into buses that already ran.
+///
+/// # Returns
+/// * `usize` - How many entries of `order` were filled
+pub fn process_order(buses: &[AuxBus], order: &mut [usize; MAX_AUX_BUSES]) -> usize {
+    let count = buses.len().min(MAX_AUX_BUSES);
+    let mut inputs = [0usize; MAX_AUX_BUSES];
+    for bus in &buses[..count] {
+        for send in &bus.sends {
+            if send.bus < count {
+                inputs[send.bus] += 1;
+            }
+        }
+    }
+
+    let mut placed = [false; MAX_AUX_BUSES];
+    let mut filled = 0;
+    while filled < count {
+        // the lowest bus with nothing left feeding it, or any bus on a loop
+        let next = (0..count)
+            .find(|&bus| !placed[bus] && inputs[bus] == 0)
+            .or_else(|| (0..count).find(|&bus| !placed[bus]));
+        let Some(next) = next else { break };
+
+        placed[next] = true;
+        order[filled] = next;
+        filled += 1;
+        for send in &buses[next].sends {
+            if send.bus < count && inputs[send.bus] > 0 {
+                inputs[send.bus] -= 1;
+            }
+        }
+    }
+    filled
+}
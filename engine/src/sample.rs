@@ -0,0 +1,124 @@
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use hound::SampleFormat;
+use crate::error::{Error, Result};
+use crate::peaks::{Peak, PeakCache};
+
+/// Decoded audio, always held as stereo frames so the mixer can treat every
+/// source the same way. Cheap to clone: the frames are shared.
+#[derive(Clone)]
+pub struct Sample {
+    pub frames: Arc<[[f32; 2]]>, // [left, right], normalized to [-1.0, 1.0]
+    pub sample_rate: u32,
+    peaks: Arc<OnceLock<PeakCache>>, // built when a file is loaded, or else by the first draw, shared by every clone
+}
+
+impl Default for Sample {
+    fn default() -> Self {
+        Sample::stereo(Vec::new(), 44100)
+    }
+}
+
+impl Sample {
+    /// Places a single channel in the centre
+    pub fn mono(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Sample::stereo(samples.into_iter().map(|s| [s, s]).collect(), sample_rate)
+    }
+
+    pub fn stereo(frames: Vec<[f32; 2]>, sample_rate: u32) -> Self {
+        Sample {
+            frames: frames.into(),
+            sample_rate,
+            peaks: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Converts interleaved audio with any number of channels. Mono is
+    /// centred, stereo keeps its channels, and channels past the first two
+    /// are folded into both sides at half gain. `None` when there are no channels.
+    pub fn from_interleaved(interleaved: &[f32], channels: usize, sample_rate: u32) -> Option<Self> {
+        if channels == 0 {
+            return None;
+        }
+
+        let frames = interleaved.chunks_exact(channels)
+            .map(|frame| match frame {
+                [mono] => [*mono, *mono],
+                [left, right] => [*left, *right],
+                [left, right, rest @ ..] => {
+                    let extra: f32 = rest.iter().sum::<f32>() * 0.5;
+                    [left + extra, right + extra]
+                }
+                [] => [0.0, 0.0],
+            })
+            .collect();
+
+        Some(Sample::stereo(frames, sample_rate))
+    }
+
+    /// Length in frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Lowest and highest value of frames `start..end`, for drawing. The
+    /// first call builds the peak cache if `build_peaks` hasn't, so keep it
+    /// off the audio thread.
+    pub fn peak(&self, start: usize, end: usize) -> Peak {
+        self.peaks().range(&self.frames, start, end)
+    }
+
+    /// Builds the peak cache now, so the first frame that draws the sample
+    /// doesn't stall on a long file
+    pub fn build_peaks(&self) {
+        self.peaks();
+    }
+
+    /// Whether the peak cache has been built
+    pub fn has_peaks(&self) -> bool {
+        self.peaks.get().is_some()
+    }
+
+    fn peaks(&self) -> &PeakCache {
+        self.peaks.get_or_init(|| PeakCache::new(&self.frames))
+    }
+}
+
+/// Loads a WAV file from disk and converts it to stereo f32 frames
+/// normalized to the range [-1.0, 1.0].
+///
+/// Reads 8, 16, 24 and 32 bit integer and 32 bit float files.
+///
+/// # Arguments
+/// * `path` - File path to the WAV file
+///
+/// # Returns
+/// * `Result<Sample>` - The decoded audio, or why it couldn't be read
+pub fn load_wav(path: &Path) -> Result<Sample> {
+    let fail = |err: hound::Error| Error::load(path, err);
+
+    let mut reader = hound::WavReader::open(path).map_err(fail)?;
+    let spec = reader.spec();
+
+    // Normalize every format to f32 first
+    let interleaved: Vec<f32> = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, 32) => reader.samples::<f32>().collect::<std::result::Result<_, _>>().map_err(fail)?,
+        (SampleFormat::Int, bits @ (8 | 16 | 24 | 32)) => {
+            let scale = 1.0 / (1u64 << (bits - 1)) as f32;
+            reader.samples::<i32>()
+                .map(|sample| sample.map(|value| value as f32 * scale))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(fail)?
+        }
+        (format, bits) => {
+            return Err(Error::load(path, format!("unsupported format ({} bit {:?})", bits, format)));
+        }
+    };
+
+    Sample::from_interleaved(&interleaved, spec.channels as usize, spec.sample_rate)
+        .ok_or_else(|| Error::load(path, "file has no channels"))
+}
@@ -0,0 +1,151 @@
+//! Sampler settings: which part of a sample plays, in which direction, how
+//! it loops and how its level moves over a note.
+
+use serde::{Deserialize, Serialize};
+use crate::envelope::Envelope;
+
+/// Longest loop crossfade, as a fraction of the loop
+pub const MAX_CROSSFADE: f32 = 0.5;
+
+/// How a sample answers note offs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayMode {
+    /// A note off releases the envelope, and the loop, if any, holds the note
+    #[default]
+    Gated,
+    /// Plays the sample through whatever the note length, ignoring the loop
+    OneShot,
+}
+
+impl PlayMode {
+    pub const ALL: [PlayMode; 2] = [PlayMode::Gated, PlayMode::OneShot];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlayMode::Gated => "Gated",
+            PlayMode::OneShot => "One-shot",
+        }
+    }
+}
+
+/// How an instrument plays its sample. Points are fractions of the sample's
+/// length so they stay put if the file is resampled.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplerSettings {
+    pub start: f32,
+    pub end: f32,
+    pub reverse: bool,
+    pub looping: bool,
+    pub loop_start: f32,
+    pub loop_end: f32,
+    pub crossfade: f32, // share of the loop blended across the loop point, 0.0 to MAX_CROSSFADE
+    pub gain: f32,      // linear
+    pub envelope: Envelope,
+    pub mode: PlayMode,
+}
+
+impl SamplerSettings {
+    /// The whole sample, forwards, once, at full level
+    pub const DEFAULT: SamplerSettings = SamplerSettings {
+        start: 0.0,
+        end: 1.0,
+        reverse: false,
+        looping: false,
+        loop_start: 0.0,
+        loop_end: 1.0,
+        crossfade: 0.0,
+        gain: 1.0,
+        envelope: Envelope::FLAT,
+        mode: PlayMode::Gated,
+    };
+
+    /// Whether notes hold on the loop
+    pub fn loops(&self) -> bool {
+        self.looping && self.mode == PlayMode::Gated
+    }
+
+    /// Where voices read in a sample of `frames` frames
+    pub fn region(&self, frames: usize) -> Region {
+        let frames = frames as f64;
+        // on whole frames, so a loop repeats exactly
+        let point = |fraction: f32| (fraction.clamp(0.0, 1.0) as f64 * frames).round();
+        let (start, end) = (point(self.start), point(self.end).max(point(self.start)));
+
+        // the loop in playback order, inside the trimmed region
+        let (loop_start, loop_end) = (point(self.loop_start).max(start), point(self.loop_end).min(end));
+        let looped = if self.reverse { (end - loop_end, end - loop_start) } else { (loop_start - start, loop_end - start) };
+        let repeat = (self.loops() && looped.1 - looped.0 >= 1.0).then(|| {
+            let length = looped.1 - looped.0;
+            // the blend reads from before the loop start, so it can't be longer than that
+            let crossfade = (self.crossfade.clamp(0.0, MAX_CROSSFADE) as f64 * length).min(looped.0);
+            Loop { start: looped.0, end: looped.1, crossfade }
+        });
+
+        Region { start, end, reverse: self.reverse, repeat }
+    }
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Loop {
+    start: f64,
+    end: f64,
+    crossfade: f64,
+}
+
+/// The playable part of a sample. Positions count frames from where
+/// playback starts, in the direction it plays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    start: f64, // in frames of the sample
+    end: f64,
+    reverse: bool,
+    repeat: Option<Loop>,
+}
+
+impl Region {
+    /// Frames from the start to the end point
+    pub fn len(&self) -> f64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() < 1.0
+    }
+
+    /// Frame of the sample to read for `position`
+    pub fn source(&self, position: f64) -> f64 {
+        if self.reverse {
+            // the last, fractional frame would read from before the sample
+            (self.end - 1.0 - position).max(0.0)
+        } else {
+            self.start + position
+        }
+    }
+
+    /// Whether `position` is in the loop crossfade, with the position it
+    /// blends towards and that position's share of the mix
+    pub fn crossfade(&self, position: f64) -> Option<(f64, f32)> {
+        let repeat = self.repeat?;
+        let fade_start = repeat.end - repeat.crossfade;
+        (repeat.crossfade > 0.0 && position >= fade_start && position < repeat.end).then(|| {
+            let mix = (position - fade_start) / repeat.crossfade;
+            (position - (repeat.end - repeat.start), mix as f32)
+        })
+    }
+
+    /// Moves `position` on by `step`, back round the loop if it runs past it
+    pub fn advance(&self, position: f64, step: f64) -> f64 {
+        let position = position + step;
+        match self.repeat {
+            Some(repeat) if position >= repeat.end => repeat.start + (position - repeat.start) % (repeat.end - repeat.start),
+            _ => position,
+        }
+    }
+}
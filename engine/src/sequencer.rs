@@ -0,0 +1,281 @@
+use crate::groove::step_shift;
+use crate::models::{ClipType, NotePattern, Pattern, PlacedClip, Step};
+
+/// Slack for float error when a beat lands exactly on a block or frame boundary
+const EPSILON: f64 = 1e-9;
+
+/// Room for events in a single block. Event buffers are made with this
+/// capacity and never grow, so events past it are dropped.
+pub const MAX_EVENTS: usize = 1024;
+
+/// The stretch of the song covered by one audio callback buffer.
+///
+/// Blocks are half open, `[start, end)` in beats, so consecutive blocks share
+/// no beats and leave no gaps. An event belongs to the frame whose span
+/// contains it.
+#[derive(Clone, Copy, Debug)]
+pub struct Block {
+    pub start: f64, // in beats
+    pub end: f64,   // in beats, exclusive
+    pub frames: usize,
+    pub samples_per_beat: f64,
+}
+
+impl Block {
+    pub fn new(start: f64, frames: usize, samples_per_beat: f64) -> Self {
+        Block {
+            start,
+            end: start + frames as f64 / samples_per_beat,
+            frames,
+            samples_per_beat,
+        }
+    }
+
+    pub fn contains(&self, beat: f64) -> bool {
+        beat + EPSILON >= self.start && beat + EPSILON < self.end
+    }
+
+    /// Frame within the block that `beat` falls on
+    pub fn offset_of(&self, beat: f64) -> usize {
+        let offset = ((beat - self.start + EPSILON) * self.samples_per_beat).floor().max(0.0) as usize;
+        offset.min(self.frames.saturating_sub(1))
+    }
+}
+
+/// Small xorshift generator that rolls step probabilities. Doesn't allocate
+/// or lock, and starts from the same seed so renders can be repeated.
+#[derive(Clone, Copy, Debug)]
+pub struct Rng(u32);
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng(0x9e37_79b9)
+    }
+}
+
+impl Rng {
+    /// Uniform in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        (x >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    Step(usize, Step, f64), // instrument hit by a pattern step, the step's settings and its length in beats
+    /// An audio clip starting: its instrument, how far into the sample it
+    /// starts and how long until the clip ends, both in beats
+    AudioClip { instrument: usize, from: f64, length: f64 },
+    NoteOn { instrument: usize, key: u8, velocity: f32 },
+    NoteOff { instrument: usize, key: u8 },
+    Click,            // metronome
+}
+
+/// Something that starts sounding `offset` frames into the block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Event {
+    pub offset: usize,
+    pub kind: EventKind,
+    pub track: Option<usize>, // track of the clip that scheduled it, None for the metronome
+}
+
+/// Finds every event the playlist schedules inside `block`, sorted by offset.
+///
+/// # Arguments
+/// * `clips` - Clips placed in the playlist
+/// * `patterns` - Step data the pattern clips point into
+/// * `note_patterns` - Notes the note clips point into
+/// * `block` - Beats the current buffer covers
+/// * `swing` - Swing of patterns that don't set their own
+/// * `metronome` - Beats between clicks, None when the metronome is off
+/// * `events` - Cleared, then filled with the events in the block, up to its capacity
+pub fn schedule_block(
+    clips: &[PlacedClip],
+    patterns: &[Pattern],
+    note_patterns: &[NotePattern],
+    block: &Block,
+    swing: f32,
+    metronome: Option<f64>,
+    events: &mut Vec<Event>,
+) {
+    events.clear();
+
+    for clip in clips {
+        let clip_end = clip.start_time + clip.length;
+        if clip_end <= block.start || clip.start_time >= block.end {
+            continue;
+        }
+
+        match &clip.clip_type {
+            ClipType::Pattern(pattern_idx) => {
+                let Some(pattern) = patterns.get(*pattern_idx) else {
+                    continue;
+                };
+
+                let steps_per_beat = pattern.resolution.steps_per_beat();
+                let swing = pattern.swing.unwrap_or(swing);
+                let groove = pattern.groove.as_ref();
+
+                // Swing and grooves move steps by up to a step either way, so
+                // start a step early and stop a step late
+                let first = ((block.start - clip.start_time - EPSILON) * steps_per_beat - 1.0)
+                    .ceil()
+                    .max(0.0) as usize;
+
+                for step in first.. {
+                    let grid = step as f64 / steps_per_beat;
+                    if grid >= clip.length || clip.start_time + grid - 1.0 / steps_per_beat + EPSILON >= block.end {
+                        break;
+                    }
+
+                    // Patterns repeat when the clip is longer than they are
+                    let step_in_pattern = step % pattern.steps.max(1);
+                    // a step pulled early never plays before its clip starts
+                    // and one pushed late past its clip's end doesn't play at all
+                    let position_in_clip = (grid + step_shift(step_in_pattern, swing, groove) / steps_per_beat).max(0.0);
+                    let beat = clip.start_time + position_in_clip;
+                    if position_in_clip >= clip.length || !block.contains(beat) {
+                        continue;
+                    }
+
+                    let offset = block.offset_of(beat);
+                    let accent = groove.map_or(1.0, |groove| groove.velocity(step_in_pattern));
+                    for (i, row) in pattern.data.iter().enumerate() {
+                        if let Some(&hit) = row.get(step_in_pattern)
+                            && hit.active {
+                            let hit = Step { velocity: (hit.velocity * accent).min(1.0), ..hit };
+                            push_event(events, Event {
+                                offset,
+                                kind: EventKind::Step(i, hit, 1.0 / steps_per_beat),
+                                track: Some(clip.track_index),
+                            });
+                        }
+                    }
+                }
+            }
+
+            ClipType::Notes(pattern_idx) => {
+                if let Some(pattern) = note_patterns.get(*pattern_idx) {
+                    schedule_notes(clip, pattern, block, events);
+                }
+            }
+
+            ClipType::AudioFile(instrument) => {
+                if block.contains(clip.start_time) {
+                    push_event(events, Event {
+                        offset: block.offset_of(clip.start_time),
+                        kind: EventKind::AudioClip { instrument: *instrument, from: clip.offset, length: clip.length },
+                        track: Some(clip.track_index),
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(interval) = metronome {
+        let mut click = (block.start / interval - EPSILON).ceil().max(0.0);
+        while block.contains(click * interval) {
+            let beat = click * interval;
+            push_event(events, Event { offset: block.offset_of(beat), kind: EventKind::Click, track: None });
+            click += 1.0;
+        }
+    }
+
+    sort_events(events);
+}
+
+/// Adds the audio clips already playing at the start of `block`, so starting
+/// or seeking into the middle of a clip plays it from there. Clips starting
+/// exactly at the block's start are left to `schedule_block`.
+///
+/// # Arguments
+/// * `clips` - Clips placed in the playlist
+/// * `block` - First block after playback starts or the playhead moves
+/// * `events` - Events of the block, which the clips are added to while there is room
+pub fn schedule_resume(clips: &[PlacedClip], block: &Block, events: &mut Vec<Event>) {
+    for clip in clips {
+        let into_clip = block.start - clip.start_time;
+        if let ClipType::AudioFile(instrument) = clip.clip_type
+            && into_clip > EPSILON
+            && into_clip < clip.length {
+            push_event(events, Event {
+                offset: 0,
+                kind: EventKind::AudioClip { instrument, from: clip.offset + into_clip, length: clip.length - into_clip },
+                track: Some(clip.track_index),
+            });
+        }
+    }
+    sort_events(events);
+}
+
+/// Adds an event if the buffer has room. Callers size it with
+/// `MAX_EVENTS` up front, so a crowded block drops events rather than
+/// growing the buffer on the audio thread. Note offs take the place of
+/// other events when it is full, since a lost note off leaves a note
+/// hanging for good.
+fn push_event(events: &mut Vec<Event>, event: Event) {
+    let is_note_off = |event: &Event| matches!(event.kind, EventKind::NoteOff { .. });
+    if events.len() < events.capacity() {
+        events.push(event);
+    } else if is_note_off(&event)
+        && let Some(dropped) = events.iter().rposition(|event| !is_note_off(event)) {
+        events[dropped] = event;
+    }
+}
+
+/// Unstable sort doesn't allocate. Note offs go first on a frame, so a
+/// note ending where the same key starts again doesn't cut the new one.
+fn sort_events(events: &mut [Event]) {
+    events.sort_unstable_by_key(|event| (event.offset, !matches!(event.kind, EventKind::NoteOff { .. })));
+}
+
+/// Adds the note ons and offs of a note clip that fall inside `block`. The
+/// pattern loops every `pattern.length` beats and notes stop at the end of
+/// the clip. Notes shorter than a frame are skipped.
+fn schedule_notes(clip: &PlacedClip, pattern: &NotePattern, block: &Block, events: &mut Vec<Event>) {
+    if pattern.length <= 0.0 {
+        return;
+    }
+    let clip_end = clip.start_time + clip.length;
+    let longest = pattern.notes.iter().map(|note| note.start + note.length).fold(0.0, f64::max);
+
+    // every time round the loop that can have a note start or end in the block
+    let first = ((block.start - clip.start_time - longest) / pattern.length).floor().max(0.0) as usize;
+    let last = ((block.end - clip.start_time) / pattern.length).floor().max(0.0) as usize;
+
+    for repeat in first..=last {
+        let loop_start = clip.start_time + repeat as f64 * pattern.length;
+        for note in &pattern.notes {
+            let on = loop_start + note.start;
+            if note.start >= pattern.length || note.length <= 0.0 || on >= clip_end {
+                continue;
+            }
+            let off = (on + note.length).min(clip_end);
+            // an off on the same frame as its on would go first and leave the note hanging
+            if (off - on) * block.samples_per_beat < 1.0 {
+                continue;
+            }
+            let instrument = pattern.instrument;
+
+            if block.contains(on) {
+                push_event(events, Event {
+                    offset: block.offset_of(on),
+                    kind: EventKind::NoteOn { instrument, key: note.key, velocity: note.velocity },
+                    track: Some(clip.track_index),
+                });
+            }
+            if block.contains(off) {
+                push_event(events, Event {
+                    offset: block.offset_of(off),
+                    kind: EventKind::NoteOff { instrument, key: note.key },
+                    track: Some(clip.track_index),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Subtractive synthesizer generator. A patch holds the settings shared by
+//! every note of an instrument; each voice carries its own oscillator,
+//! envelope and filter state so notes can overlap up to the polyphony.
+
+use std::f32::consts::{PI, TAU};
+use serde::{Deserialize, Serialize};
+use crate::envelope::{Envelope, EnvelopeState};
+
+/// Lowest and highest cutoff, the highest as a fraction of the sample rate
+const MIN_CUTOFF: f32 = 20.0;
+const MAX_CUTOFF_RATIO: f32 = 0.45;
+
+/// Frequency of a MIDI key in equal temperament, A4 = 440 Hz
+pub fn key_frequency(key: u8) -> f32 {
+    440.0 * 2f32.powf((key as f32 - 69.0) / 12.0)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Waveform {
+    Sine,
+    #[default]
+    Saw,
+    Square,
+    Triangle,
+    Noise,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 5] = [Waveform::Sine, Waveform::Saw, Waveform::Square, Waveform::Triangle, Waveform::Noise];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Waveform::Sine => "Sine",
+            Waveform::Saw => "Saw",
+            Waveform::Square => "Square",
+            Waveform::Triangle => "Triangle",
+            Waveform::Noise => "Noise",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterMode {
+    #[default]
+    LowPass,
+    HighPass,
+    BandPass,
+}
+
+impl FilterMode {
+    pub const ALL: [FilterMode; 3] = [FilterMode::LowPass, FilterMode::HighPass, FilterMode::BandPass];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterMode::LowPass => "Low Pass",
+            FilterMode::HighPass => "High Pass",
+            FilterMode::BandPass => "Band Pass",
+        }
+    }
+}
+
+/// What the LFO moves
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LfoTarget {
+    Pitch,
+    #[default]
+    Cutoff,
+    Amplitude,
+}
+
+impl LfoTarget {
+    pub const ALL: [LfoTarget; 3] = [LfoTarget::Pitch, LfoTarget::Cutoff, LfoTarget::Amplitude];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LfoTarget::Pitch => "Pitch",
+            LfoTarget::Cutoff => "Cutoff",
+            LfoTarget::Amplitude => "Amplitude",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Oscillator {
+    pub waveform: Waveform,
+    pub level: f32,  // 0.0 to 1.0, 0 turns the oscillator off
+    pub octave: i8,  // shift from the note, -3 to 3
+    pub detune: f32, // in cents
+}
+
+impl Default for Oscillator {
+    fn default() -> Self {
+        Oscillator { waveform: Waveform::Saw, level: 0.5, octave: 0, detune: 0.0 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lfo {
+    pub waveform: Waveform,
+    pub rate: f32,  // in Hz
+    pub depth: f32, // semitones for pitch, octaves for cutoff, 0.0 to 1.0 for amplitude
+    pub target: LfoTarget,
+}
+
+impl Default for Lfo {
+    fn default() -> Self {
+        Lfo { waveform: Waveform::Sine, rate: 4.0, depth: 0.0, target: LfoTarget::Cutoff }
+    }
+}
+
+/// Settings of a synth instrument
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SynthPatch {
+    pub oscillators: [Oscillator; 2],
+    pub filter: FilterMode,
+    pub cutoff: f32,    // in Hz
+    pub resonance: f32, // filter Q
+    pub filter_envelope: Envelope,
+    pub envelope_amount: f32, // how far the filter envelope opens the cutoff, in octaves
+    pub amp_envelope: Envelope,
+    pub lfo: Lfo,
+}
+
+impl Default for SynthPatch {
+    fn default() -> Self {
+        SynthPatch {
+            oscillators: [
+                Oscillator::default(),
+                Oscillator { waveform: Waveform::Square, level: 0.3, octave: -1, detune: 7.0 },
+            ],
+            filter: FilterMode::LowPass,
+            cutoff: 1200.0,
+            resonance: 1.0,
+            filter_envelope: Envelope { attack: 0.005, decay: 0.3, sustain: 0.2, release: 0.3 },
+            envelope_amount: 2.0,
+            amp_envelope: Envelope::default(),
+            lfo: Lfo::default(),
+        }
+    }
+}
+
+/// Topology preserving state variable filter, stable while the cutoff moves
+#[derive(Clone, Copy, Debug, Default)]
+struct SvfState {
+    ic1: f32,
+    ic2: f32,
+}
+
+impl SvfState {
+    fn process(&mut self, input: f32, mode: FilterMode, cutoff: f32, resonance: f32, sample_rate: f32) -> f32 {
+        let cutoff = cutoff.clamp(MIN_CUTOFF, sample_rate * MAX_CUTOFF_RATIO);
+        let g = (PI * cutoff / sample_rate).tan();
+        let k = 1.0 / resonance.max(0.1);
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        let v3 = input - self.ic2;
+        let v1 = a1 * self.ic1 + a2 * v3;
+        let v2 = self.ic2 + a2 * self.ic1 + a3 * v3;
+        self.ic1 = 2.0 * v1 - self.ic1;
+        self.ic2 = 2.0 * v2 - self.ic2;
+
+        match mode {
+            FilterMode::LowPass => v2,
+            FilterMode::BandPass => v1,
+            FilterMode::HighPass => input - k * v1 - v2,
+        }
+    }
+}
+
+/// Smooths the jump of a saw or square so it doesn't alias as badly
+fn poly_blep(phase: f32, step: f32) -> f32 {
+    if phase < step {
+        let t = phase / step;
+        2.0 * t - t * t - 1.0
+    } else if phase > 1.0 - step {
+        let t = (phase - 1.0) / step;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// One sample of `waveform` at `phase`, in `[0, 1)`
+fn wave(waveform: Waveform, phase: f32, step: f32, noise: &mut u32) -> f32 {
+    match waveform {
+        Waveform::Sine => (TAU * phase).sin(),
+        Waveform::Saw => 2.0 * phase - 1.0 - poly_blep(phase, step),
+        Waveform::Square => {
+            let square = if phase < 0.5 { 1.0 } else { -1.0 };
+            square + poly_blep(phase, step) - poly_blep((phase + 0.5).fract(), step)
+        }
+        Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        Waveform::Noise => {
+            *noise ^= *noise << 13;
+            *noise ^= *noise >> 17;
+            *noise ^= *noise << 5;
+            *noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+        }
+    }
+}
+
+/// Playing state of one synth note
+#[derive(Clone, Copy, Debug)]
+pub struct SynthVoice {
+    frequency: f32,
+    phases: [f32; 2],
+    lfo_phase: f32,
+    noise: u32,
+    amp: EnvelopeState,
+    filter_envelope: EnvelopeState,
+    filter: SvfState,
+    hold: Option<u32>, // frames left before the note lets go by itself, None waits for a release
+}
+
+impl SynthVoice {
+    /// # Arguments
+    /// * `frequency` - Pitch of the note in Hz
+    /// * `hold` - Frames to hold the note for, None to hold it until `release`
+    pub fn new(frequency: f32, hold: Option<u32>) -> Self {
+        SynthVoice {
+            frequency,
+            phases: [0.0; 2],
+            lfo_phase: 0.0,
+            noise: 0x2545_f491,
+            amp: EnvelopeState::new(),
+            filter_envelope: EnvelopeState::new(),
+            filter: SvfState::default(),
+            hold,
+        }
+    }
+
+    /// Lets go of the note. The envelopes move to their release on the next frame.
+    pub fn release(&mut self) {
+        if !self.amp.is_releasing() {
+            self.hold = Some(0);
+        }
+    }
+
+    /// Renders the next sample. None once the release has finished.
+    pub fn next(&mut self, patch: &SynthPatch, sample_rate: f32) -> Option<f32> {
+        match &mut self.hold {
+            Some(0) => {
+                self.hold = None;
+                self.amp.release(&patch.amp_envelope, sample_rate);
+                self.filter_envelope.release(&patch.filter_envelope, sample_rate);
+            }
+            Some(hold) => *hold -= 1,
+            None => {}
+        }
+
+        let lfo = wave(patch.lfo.waveform, self.lfo_phase, 0.0, &mut self.noise);
+        self.lfo_phase = (self.lfo_phase + patch.lfo.rate / sample_rate).fract();
+        let depth = patch.lfo.depth;
+        let (pitch_lfo, cutoff_lfo, amp_lfo) = match patch.lfo.target {
+            LfoTarget::Pitch => (2f32.powf(lfo * depth / 12.0), 0.0, 1.0),
+            LfoTarget::Cutoff => (1.0, lfo * depth, 1.0),
+            // tremolo dips from full level, never above it
+            LfoTarget::Amplitude => (1.0, 0.0, 1.0 - depth.clamp(0.0, 1.0) * 0.5 * (1.0 - lfo)),
+        };
+
+        let mut mix = 0.0;
+        for (oscillator, phase) in patch.oscillators.iter().zip(&mut self.phases) {
+            if oscillator.level <= 0.0 {
+                continue;
+            }
+            let ratio = 2f32.powf(oscillator.octave as f32 + oscillator.detune / 1200.0);
+            let step = (self.frequency * ratio * pitch_lfo / sample_rate).min(0.5);
+            mix += wave(oscillator.waveform, *phase, step, &mut self.noise) * oscillator.level;
+            *phase = (*phase + step).fract();
+        }
+
+        let opening = self.filter_envelope.next(&patch.filter_envelope, sample_rate) * patch.envelope_amount;
+        let cutoff = patch.cutoff * 2f32.powf(opening + cutoff_lfo);
+        let filtered = self.filter.process(mix, patch.filter, cutoff, patch.resonance, sample_rate);
+
+        let level = self.amp.next(&patch.amp_envelope, sample_rate);
+        if self.amp.is_done() {
+            return None;
+        }
+        Some(filtered * level * amp_lfo)
+    }
+}
@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+use crate::mixer::pan_gains;
+use crate::resample::{read_linear, Interpolation, SincTable};
+use crate::envelope::EnvelopeState;
+use crate::sample::Sample;
+use crate::sampler::{PlayMode, SamplerSettings};
+use crate::synth::{SynthPatch, SynthVoice};
+
+/// Voices the engine can play at once across every sound source
+pub const MAX_VOICES: usize = 128;
+/// Overlapping hits an instrument allows unless configured otherwise
+pub const DEFAULT_POLYPHONY: usize = 8;
+/// Length of the fade out on a stolen voice, in frames (about 1.5 ms at 44.1 kHz)
+const STEAL_FADE_FRAMES: usize = 64;
+
+/// What a voice reads its samples from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Instrument(usize),
+    Metronome,
+    Preview,
+}
+
+/// What a voice plays: a sample it reads through, or a synth patch it renders
+#[derive(Clone, Copy)]
+pub enum Sound<'a> {
+    Sample(&'a Sample, &'a SamplerSettings),
+    Synth(&'a SynthPatch),
+}
+
+/// A sample played whole, as the metronome and previews are
+impl<'a> From<&'a Sample> for Sound<'a> {
+    fn from(sample: &'a Sample) -> Self {
+        Sound::Sample(sample, &SamplerSettings::DEFAULT)
+    }
+}
+
+/// Which voice makes room when an instrument is already at its polyphony limit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StealPolicy {
+    /// Fade out the voice that started first
+    #[default]
+    Oldest,
+    /// Fade out the voice with the lowest gain, the oldest of those on a tie
+    Quietest,
+    /// Ignore the new trigger
+    None,
+}
+
+/// One playing hit of a sound source
+#[derive(Clone, Copy, Debug)]
+pub struct Voice {
+    pub source: Source,
+    pub position: f64, // frames played into the sample's region, fractional when pitched or resampled
+    pub gain: f32,
+    pub pitch: f32,    // playback rate, 1.0 plays at the original speed and key
+    pub pan: f32,      // -1.0 is hard left, 1.0 hard right
+    pub track: Option<usize>, // playlist track whose bus it plays through, None goes to master
+    pub key: Option<u8>, // note it plays, None for hits that ring until the sample ends
+    pub synth: Option<SynthVoice>, // oscillator and envelope state when it plays a synth
+    pub hold: Option<u32>, // frames before a sample voice lets go by itself, Some(0) lets go now
+    pub clip_end: Option<u32>, // frames before an audio clip's voice is cut at the end of its clip
+    envelope: EnvelopeState, // of a sample voice
+    started: u64,
+    fade: Option<usize>, // frames left before a stolen voice goes silent
+}
+
+impl Voice {
+    fn is_stolen(&self) -> bool {
+        self.fade.is_some()
+    }
+}
+
+/// Fixed size set of voices. Nothing is allocated after creation, so it is
+/// safe to use on the audio thread.
+///
+/// Sources play at their own sample rate whatever the output rate is: each
+/// voice steps through its source by `pitch * source rate / output rate`.
+pub struct VoicePool {
+    voices: Vec<Voice>,
+    triggered: u64,
+    output_rate: f64,
+    pub interpolation: Interpolation,
+    sinc: SincTable,
+}
+
+impl VoicePool {
+    pub fn new(output_rate: f32) -> Self {
+        VoicePool {
+            voices: Vec::with_capacity(MAX_VOICES),
+            triggered: 0,
+            output_rate: output_rate as f64,
+            interpolation: Interpolation::default(),
+            sinc: SincTable::new(),
+        }
+    }
+
+    /// Voices currently sounding, stolen ones included
+    pub fn voices(&self) -> &[Voice] {
+        &self.voices
+    }
+
+    /// Whether `source` has a voice that isn't fading out
+    pub fn is_sounding(&self, source: Source) -> bool {
+        self.voices.iter().any(|voice| voice.source == source && !voice.is_stolen())
+    }
+
+    /// Starts a new voice from the beginning of `source`.
+    ///
+    /// # Arguments
+    /// * `source` - Sound to play
+    /// * `gain` - Linear gain of the voice
+    /// * `pitch` - Playback rate
+    /// * `polyphony` - Most voices `source` may have at once
+    /// * `policy` - How to make room once `source` is at `polyphony`
+    ///
+    /// # Returns
+    /// * `Option<&mut Voice>` - The new voice, or None when the policy ignored the trigger
+    pub fn trigger(&mut self, source: Source, gain: f32, pitch: f32, polyphony: usize, policy: StealPolicy) -> Option<&mut Voice> {
+        let active = self.voices.iter()
+            .filter(|voice| voice.source == source && !voice.is_stolen())
+            .count();
+
+        if active >= polyphony.max(1) {
+            let victim = match policy {
+                StealPolicy::Oldest => self.find_victim(source, |voice| (0.0, voice.started)),
+                StealPolicy::Quietest => self.find_victim(source, |voice| (voice.gain, voice.started)),
+                StealPolicy::None => return None,
+            };
+            if let Some(victim) = victim {
+                self.voices[victim].fade = Some(STEAL_FADE_FRAMES);
+            }
+        }
+
+        // The pool is full: cut the voice closest to silence to make room
+        if self.voices.len() == MAX_VOICES
+            && let Some(index) = self.voices.iter()
+                .enumerate()
+                .min_by_key(|(_, voice)| (voice.fade.unwrap_or(usize::MAX), voice.started))
+                .map(|(index, _)| index) {
+            self.voices.swap_remove(index);
+        }
+
+        self.triggered += 1;
+        self.voices.push(Voice {
+            source,
+            position: 0.0,
+            gain,
+            pitch,
+            pan: 0.0,
+            track: None,
+            key: None,
+            synth: None,
+            hold: None,
+            clip_end: None,
+            envelope: EnvelopeState::new(),
+            started: self.triggered,
+            fade: None,
+        });
+        self.voices.last_mut()
+    }
+
+    /// The unstolen voice of `source` that sorts lowest by `key`
+    fn find_victim<K: PartialOrd>(&self, source: Source, key: impl Fn(&Voice) -> K) -> Option<usize> {
+        self.voices.iter()
+            .enumerate()
+            .filter(|(_, voice)| voice.source == source && !voice.is_stolen())
+            .min_by(|(_, a), (_, b)| key(a).partial_cmp(&key(b)).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+
+    /// Fades out every voice of `source`
+    pub fn release(&mut self, source: Source) {
+        for voice in &mut self.voices {
+            if voice.source == source && !voice.is_stolen() {
+                voice.fade = Some(STEAL_FADE_FRAMES);
+            }
+        }
+    }
+
+    /// Lets go of the voices of `source` playing `key` through `track`.
+    /// Gated samples and synths move to their release, one-shots play on.
+    pub fn release_note(&mut self, source: Source, key: u8, track: Option<usize>) {
+        for voice in &mut self.voices {
+            if voice.source == source && voice.key == Some(key) && voice.track == track {
+                Self::let_go(voice);
+            }
+        }
+    }
+
+    /// Lets go of every voice playing a note
+    pub fn release_notes(&mut self) {
+        for voice in &mut self.voices {
+            if voice.key.is_some() {
+                Self::let_go(voice);
+            }
+        }
+    }
+
+    fn let_go(voice: &mut Voice) {
+        match &mut voice.synth {
+            Some(synth) => synth.release(),
+            None => voice.hold = Some(0),
+        }
+    }
+
+    /// Fades out every voice started by an audio clip
+    pub fn cut_clips(&mut self) {
+        for voice in &mut self.voices {
+            if voice.clip_end.take().is_some() && !voice.is_stolen() {
+                voice.fade = Some(STEAL_FADE_FRAMES);
+            }
+        }
+    }
+
+    /// Cuts every voice immediately
+    pub fn clear(&mut self) {
+        self.voices.clear();
+    }
+
+    /// Sums one frame of every voice and advances them. Voices that run past
+    /// the end of their sample, finish their release or fade, or whose source
+    /// is gone are freed.
+    ///
+    /// # Arguments
+    /// * `sounds` - Looks up what a source plays
+    pub fn next_frame<'a, S: Into<Sound<'a>>>(&mut self, sounds: impl Fn(Source) -> Option<S>) -> [f32; 2] {
+        let mut mix = [0.0; 2];
+        self.mix_frame(sounds, |_, frame| {
+            mix[0] += frame[0];
+            mix[1] += frame[1];
+        });
+        mix
+    }
+
+    /// Like `next_frame`, but hands each voice's frame to `out` instead of
+    /// summing them, so the caller can route voices to different buses.
+    ///
+    /// # Arguments
+    /// * `sounds` - Looks up what a source plays
+    /// * `out` - Receives every sounding voice and its frame, gain, pan and fade applied
+    pub fn mix_frame<'a, S: Into<Sound<'a>>>(
+        &mut self,
+        sounds: impl Fn(Source) -> Option<S>,
+        mut out: impl FnMut(&Voice, [f32; 2]),
+    ) {
+        let mut i = 0;
+
+        while i < self.voices.len() {
+            let voice = &mut self.voices[i];
+            match &mut voice.clip_end {
+                Some(0) => {
+                    voice.clip_end = None;
+                    if !voice.is_stolen() {
+                        voice.fade = Some(STEAL_FADE_FRAMES);
+                    }
+                }
+                Some(frames) => *frames -= 1,
+                None => {}
+            }
+            let mut gain = voice.gain;
+            if let Some(fade) = &mut voice.fade {
+                gain *= *fade as f32 / STEAL_FADE_FRAMES as f32;
+                *fade = fade.saturating_sub(1);
+            }
+            let [pan_left, pan_right] = pan_gains(voice.pan);
+
+            let finished = match sounds(voice.source).map(Into::into) {
+                Some(Sound::Sample(sample, settings)) if voice.synth.is_none() => {
+                    let data = &sample.frames[..];
+                    let region = settings.region(data.len());
+                    let step = voice.pitch as f64 * sample.sample_rate as f64 / self.output_rate;
+
+                    match &mut voice.hold {
+                        Some(0) => {
+                            voice.hold = None;
+                            if settings.mode == PlayMode::Gated {
+                                voice.envelope.release(&settings.envelope, self.output_rate as f32);
+                            }
+                        }
+                        Some(hold) => *hold -= 1,
+                        None => {}
+                    }
+
+                    if voice.position < region.len() {
+                        let read = |position: f64| {
+                            let position = region.source(position);
+                            match self.interpolation {
+                                Interpolation::Linear => read_linear(data, position),
+                                Interpolation::Sinc => self.sinc.read(data, position, step),
+                            }
+                        };
+                        let [mut left, mut right] = read(voice.position);
+                        if let Some((from, mix)) = region.crossfade(voice.position) {
+                            let [from_left, from_right] = read(from);
+                            left = left * (1.0 - mix) + from_left * mix;
+                            right = right * (1.0 - mix) + from_right * mix;
+                        }
+                        let gain = gain * settings.gain * voice.envelope.next(&settings.envelope, self.output_rate as f32);
+                        out(voice, [left * gain * pan_left, right * gain * pan_right]);
+                        voice.position = region.advance(voice.position, step);
+                    }
+                    voice.fade == Some(0) || voice.envelope.is_done() || voice.position >= region.len()
+                }
+                Some(Sound::Synth(patch)) => match voice.synth.as_mut().and_then(|synth| synth.next(patch, self.output_rate as f32)) {
+                    Some(sample) => {
+                        out(voice, [sample * gain * pan_left, sample * gain * pan_right]);
+                        voice.fade == Some(0)
+                    }
+                    None => true,
+                },
+                _ => true,
+            };
+
+            if finished {
+                self.voices.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+use remdaw_engine::project::Project;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::sampler::SamplerSettings;
+use remdaw_engine::{ClipType, Engine, Instrument, PlacedClip, Session};
+
+// 120 bpm at 8 kHz gives 4000 frames per beat
+const SAMPLE_RATE: f32 = 8000.0;
+const FRAMES_PER_BEAT: usize = 4000;
+// a stolen or cut voice takes this long to fade out
+const FADE_FRAMES: usize = 64;
+
+/// Four beats of a slow ramp, so every frame is different
+fn ramp() -> Vec<f32> {
+    (0..FRAMES_PER_BEAT * 4).map(|i| i as f32 / (FRAMES_PER_BEAT * 4) as f32).collect()
+}
+
+fn audio_clip(start_time: f64, length: f64, offset: f64) -> PlacedClip {
+    PlacedClip {
+        clip_type: ClipType::AudioFile(0),
+        name: "test.wav".to_string(),
+        track_index: 0,
+        start_time,
+        length,
+        color: [0, 0, 0, 255],
+        offset,
+    }
+}
+
+/// An engine playing `clip` of the ramp from the start of the song
+fn engine_with(clip: PlacedClip) -> Engine {
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.set_bpm(120);
+    engine.session.add_instrument(Instrument::new(PathBuf::from("test.wav"), Sample::mono(ramp(), 8000)));
+    engine.session.playlist.clips.push(clip);
+    engine
+}
+
+/// Renders `frames` stereo frames and returns the left channel
+fn render(engine: &mut Engine, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0; frames * 2];
+    engine.render(&mut out);
+    out.chunks(2).map(|frame| frame[0]).collect()
+}
+
+#[test]
+fn offset_clips_start_partway_into_the_sample() {
+    let mut whole = engine_with(audio_clip(0.0, 4.0, 0.0));
+    whole.play();
+    let whole = render(&mut whole, FRAMES_PER_BEAT * 3);
+
+    let mut trimmed = engine_with(audio_clip(0.0, 2.0, 1.0));
+    trimmed.play();
+    let trimmed = render(&mut trimmed, FRAMES_PER_BEAT);
+
+    assert!(whole[FRAMES_PER_BEAT] > 0.0);
+    assert_eq!(trimmed[..], whole[FRAMES_PER_BEAT..FRAMES_PER_BEAT * 2]);
+}
+
+#[test]
+fn clips_are_cut_at_their_end() {
+    let mut engine = engine_with(audio_clip(0.0, 1.0, 0.0));
+    engine.play();
+    let out = render(&mut engine, FRAMES_PER_BEAT * 2);
+
+    assert!(out[FRAMES_PER_BEAT - 1] > 0.0);
+    assert!(out[FRAMES_PER_BEAT + FADE_FRAMES..].iter().all(|&s| s == 0.0));
+    assert!(engine.voices.voices().is_empty());
+}
+
+#[test]
+fn looping_samples_play_until_the_clip_ends() {
+    let mut engine = engine_with(audio_clip(0.0, 2.0, 0.0));
+    engine.session.instruments[0].sample = Sample::mono(vec![0.5; 100], 8000);
+    engine.session.instruments[0].sampler = SamplerSettings { looping: true, ..SamplerSettings::default() };
+    engine.play();
+    let out = render(&mut engine, FRAMES_PER_BEAT * 3);
+
+    // far past a 16th note and the end of the sample, still at full level
+    assert!(out[..FRAMES_PER_BEAT * 2 - 1].iter().all(|&s| s == 0.5));
+    assert!(out[FRAMES_PER_BEAT * 2 + FADE_FRAMES..].iter().all(|&s| s == 0.0));
+    assert!(engine.voices.voices().is_empty());
+}
+
+#[test]
+fn starting_mid_clip_plays_from_the_playhead() {
+    let mut whole = engine_with(audio_clip(0.0, 4.0, 0.0));
+    whole.play();
+    let whole = render(&mut whole, FRAMES_PER_BEAT * 3);
+
+    let mut engine = engine_with(audio_clip(0.0, 4.0, 0.0));
+    engine.seek(1.5);
+    engine.play();
+    let resumed = render(&mut engine, FRAMES_PER_BEAT);
+    assert_eq!(resumed[..], whole[FRAMES_PER_BEAT * 3 / 2..FRAMES_PER_BEAT * 5 / 2]);
+
+    // pausing cuts the clip, and playing again picks it up where it left off
+    engine.pause();
+    render(&mut engine, FADE_FRAMES);
+    assert!(engine.voices.voices().is_empty());
+    engine.play();
+    let again = render(&mut engine, 100);
+    let at = FRAMES_PER_BEAT * 5 / 2;
+    assert_eq!(again[..], whole[at..at + 100]);
+}
+
+#[test]
+fn clip_length_follows_the_trimmed_sample() {
+    // four beats of the ramp at 120 bpm, two at 60
+    let mut instrument = Instrument::new(PathBuf::from("test.wav"), Sample::mono(ramp(), 8000));
+    assert_eq!(instrument.length_in_beats(120), 4.0);
+    assert_eq!(instrument.length_in_beats(60), 2.0);
+
+    instrument.sampler = SamplerSettings { start: 0.25, reverse: true, ..SamplerSettings::default() };
+    assert_eq!(instrument.length_in_beats(120), 3.0);
+}
+
+#[test]
+fn projects_without_offsets_play_clips_from_the_start() {
+    let mut session = Session::new();
+    session.playlist.clips.push(audio_clip(2.0, 3.0, 0.5));
+    let json = serde_json::to_string(&Project::from_session(&session, &std::env::temp_dir())).unwrap();
+
+    let mut loaded = Session::new();
+    serde_json::from_str::<Project>(&json).unwrap().apply(&mut loaded, &std::env::temp_dir());
+    assert_eq!(loaded.playlist.clips[0].offset, 0.5);
+
+    let json = json.replace("\"offset\"", "\"ignored\"");
+    serde_json::from_str::<Project>(&json).unwrap().apply(&mut loaded, &std::env::temp_dir());
+    assert_eq!(loaded.playlist.clips[0].offset, 0.0);
+}
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use remdaw_engine::decoder::{decoder_for, is_supported, load_sample, probe, supported_extensions};
+use remdaw_engine::Error;
+
+/// A path in the temp directory that no other test uses
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("remdaw-decoder-{}-{}", std::process::id(), name))
+}
+
+/// Writes a 16 bit big endian AIFF file from interleaved samples
+fn write_aiff(path: &Path, channels: u16, sample_rate: u32, samples: &[i16]) {
+    let frames = (samples.len() / channels as usize) as u32;
+    let data: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+
+    // sample rate as an 80 bit extended float
+    let exponent = 31 - sample_rate.leading_zeros();
+    let mantissa = (sample_rate as u64) << (63 - exponent);
+    let mut rate = Vec::from((16383 + exponent as u16).to_be_bytes());
+    rate.extend_from_slice(&mantissa.to_be_bytes());
+
+    let mut comm = Vec::new();
+    comm.extend_from_slice(&channels.to_be_bytes());
+    comm.extend_from_slice(&frames.to_be_bytes());
+    comm.extend_from_slice(&16u16.to_be_bytes());
+    comm.extend_from_slice(&rate);
+
+    let mut ssnd = vec![0; 8]; // offset and block size
+    ssnd.extend_from_slice(&data);
+
+    let mut form = b"AIFF".to_vec();
+    for (id, chunk) in [(b"COMM", comm), (b"SSND", ssnd)] {
+        form.extend_from_slice(id);
+        form.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        form.extend_from_slice(&chunk);
+    }
+
+    let mut file = b"FORM".to_vec();
+    file.extend_from_slice(&(form.len() as u32).to_be_bytes());
+    file.extend_from_slice(&form);
+    std::fs::write(path, file).unwrap();
+}
+
+#[test]
+fn extensions_pick_a_decoder_regardless_of_case() {
+    for name in ["kick.wav", "kick.WAV", "loop.flac", "pad.ogg", "vox.mp3", "snare.aif", "snare.AIFF"] {
+        assert!(is_supported(Path::new(name)), "{name}");
+    }
+    for name in ["notes.txt", "song.remdaw", "no_extension"] {
+        assert!(!is_supported(Path::new(name)), "{name}");
+    }
+
+    let extensions = supported_extensions();
+    for extension in ["wav", "flac", "ogg", "mp3", "aiff"] {
+        assert!(extensions.contains(&extension));
+    }
+    assert!(decoder_for(Path::new("a.wav")).is_some());
+}
+
+#[test]
+fn aiff_loads_as_stereo() {
+    let path = temp_file("stereo.aiff");
+    write_aiff(&path, 2, 48000, &[16384, -16384, 0, 8192, -8192, 0]);
+
+    let info = probe(&path).unwrap();
+    let sample = load_sample(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(info.format, "AIFF");
+    assert_eq!((info.sample_rate, info.channels), (48000, 2));
+    assert!(info.duration().is_some());
+
+    let sample = sample.unwrap();
+    assert_eq!(sample.sample_rate, 48000);
+    assert_eq!(&sample.frames[..], &[[0.5, -0.5], [0.0, 0.25], [-0.25, 0.0]]);
+    // ready to draw without building peaks on the first frame
+    assert!(sample.has_peaks());
+}
+
+#[test]
+fn mono_aiff_is_centred() {
+    let path = temp_file("mono.aif");
+    write_aiff(&path, 1, 22050, &[16384, -8192]);
+
+    let sample = load_sample(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(&sample.unwrap().frames[..], &[[0.5, 0.5], [-0.25, -0.25]]);
+}
+
+#[test]
+fn wav_probe_reports_the_header() {
+    let info = probe(Path::new("../test_instruments/cowbell.wav")).unwrap();
+    assert_eq!(info.format, "WAV");
+    assert!(info.sample_rate > 0);
+    assert!(info.duration().unwrap() > 0.0);
+}
+
+#[test]
+fn bad_files_are_errors() {
+    assert!(matches!(load_sample(Path::new("notes.txt")), Err(Error::UnsupportedFile(_))));
+    assert!(matches!(load_sample(&temp_file("missing.flac")), Err(Error::Load { .. })));
+
+    let path = temp_file("garbage.flac");
+    std::fs::write(&path, b"not a flac file").unwrap();
+    let result = load_sample(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(Error::Load { .. })));
+}
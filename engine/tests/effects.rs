@@ -0,0 +1,208 @@
+use std::f32::consts::PI;
+use std::path::PathBuf;
+use remdaw_engine::effects::{
+    Compressor, Delay, Effect, EffectChain, EffectKind, EffectSlot, EffectTarget, Eq, Filter, Gain, Reverb,
+};
+use remdaw_engine::meter::gain_to_db;
+use remdaw_engine::mixer::MAX_BLOCK;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{ClipType, Command, Engine, Instrument, Pattern, PlacedClip, Step};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+fn sine(frequency: f32, frames: usize) -> Vec<[f32; 2]> {
+    (0..frames)
+        .map(|i| {
+            let sample = (2.0 * PI * frequency * i as f32 / SAMPLE_RATE).sin();
+            [sample, sample]
+        })
+        .collect()
+}
+
+/// Peak of the left channel once the filter has settled
+fn settled_peak(buffer: &[[f32; 2]]) -> f32 {
+    buffer[buffer.len() / 2..].iter().fold(0.0, |peak, frame| peak.max(frame[0].abs()))
+}
+
+/// Gain in dB an effect applies to a sine
+fn response(effect: &mut dyn Effect, frequency: f32) -> f32 {
+    let mut buffer = sine(frequency, SAMPLE_RATE as usize / 4);
+    effect.process(&mut buffer);
+    gain_to_db(settled_peak(&buffer))
+}
+
+#[test]
+fn gain_scales_by_decibels() {
+    let mut gain = Gain::new();
+    gain.set_param(0, 6.0);
+    let mut buffer = vec![[0.5, -0.25]; 4];
+    gain.process(&mut buffer);
+    let expected = 10f32.powf(6.0 / 20.0);
+    assert!((buffer[3][0] - 0.5 * expected).abs() < 1e-5);
+    assert!((buffer[3][1] + 0.25 * expected).abs() < 1e-5);
+
+    // parameters are clamped to their range
+    gain.set_param(0, 1000.0);
+    assert_eq!(gain.param(0), gain.params()[0].max);
+}
+
+#[test]
+fn filters_pass_and_stop_the_right_bands() {
+    let mut low_pass = Filter::new(SAMPLE_RATE);
+    low_pass.set_param(1, 1000.0);
+    assert!(response(&mut low_pass, 100.0).abs() < 0.5);
+    low_pass.reset();
+    assert!(response(&mut low_pass, 10000.0) < -24.0);
+
+    let mut high_pass = Filter::new(SAMPLE_RATE);
+    high_pass.set_param(0, 1.0);
+    high_pass.set_param(1, 1000.0);
+    assert!(response(&mut high_pass, 100.0) < -24.0);
+    high_pass.reset();
+    assert!(response(&mut high_pass, 10000.0).abs() < 0.5);
+
+    // a peak boosts its centre by its gain
+    let mut peak = Filter::new(SAMPLE_RATE);
+    peak.set_param(0, 4.0);
+    peak.set_param(1, 2000.0);
+    peak.set_param(3, 9.0);
+    assert!((response(&mut peak, 2000.0) - 9.0).abs() < 0.2);
+}
+
+#[test]
+fn flat_eq_passes_audio_through() {
+    let mut eq = Eq::new(SAMPLE_RATE);
+    for frequency in [50.0, 1000.0, 12000.0] {
+        eq.reset();
+        assert!(response(&mut eq, frequency).abs() < 0.1, "{frequency} Hz");
+    }
+
+    // cutting the lows leaves the highs alone
+    eq.set_param(1, -12.0);
+    eq.reset();
+    assert!(response(&mut eq, 30.0) < -10.0);
+    eq.reset();
+    assert!(response(&mut eq, 12000.0).abs() < 0.2);
+}
+
+#[test]
+fn compressor_reduces_level_above_the_threshold() {
+    let mut compressor = Compressor::new(SAMPLE_RATE);
+    compressor.set_param(0, -20.0);
+    compressor.set_param(1, 4.0);
+
+    // 0 dB in is 20 dB over, which a 4:1 ratio brings down to 5 dB over
+    let mut buffer = vec![[1.0, 1.0]; SAMPLE_RATE as usize / 2];
+    compressor.process(&mut buffer);
+    let level = gain_to_db(buffer.last().unwrap()[0]);
+    assert!((level + 15.0).abs() < 0.1, "{level} dB");
+    assert!((compressor.reduction() - 15.0).abs() < 0.1);
+
+    // quiet signals are left alone
+    compressor.reset();
+    let mut quiet = vec![[0.01, 0.01]; 1000];
+    compressor.process(&mut quiet);
+    assert!((quiet[999][0] - 0.01).abs() < 1e-6);
+}
+
+#[test]
+fn delay_echoes_after_its_time() {
+    let mut delay = Delay::new(SAMPLE_RATE);
+    delay.set_param(0, 10.0); // 480 frames
+    delay.set_param(1, 0.5);
+    delay.set_param(2, 1.0); // only the echoes
+
+    let mut buffer = vec![[0.0; 2]; 2000];
+    buffer[0] = [1.0, 1.0];
+    delay.process(&mut buffer);
+
+    assert_eq!(buffer[0], [0.0, 0.0]);
+    assert_eq!(buffer[480], [1.0, 1.0]);
+    assert_eq!(buffer[960], [0.5, 0.5]);
+    assert_eq!(buffer[1440], [0.25, 0.25]);
+    let silent = buffer.iter().enumerate().filter(|(i, _)| i % 480 != 0).all(|(_, frame)| *frame == [0.0, 0.0]);
+    assert!(silent);
+}
+
+#[test]
+fn reverb_rings_on_until_reset() {
+    let mut reverb = Reverb::new(SAMPLE_RATE);
+    let mut buffer = vec![[0.0; 2]; SAMPLE_RATE as usize / 2];
+    buffer[0] = [1.0, 1.0];
+    reverb.process(&mut buffer);
+
+    // the tail is still going a quarter second later
+    let tail = &buffer[SAMPLE_RATE as usize / 4..];
+    assert!(tail.iter().any(|frame| frame[0].abs() > 1e-4));
+    assert!(buffer.iter().all(|frame| frame[0].is_finite() && frame[0].abs() < 2.0));
+
+    reverb.reset();
+    let mut silence = vec![[0.0; 2]; 1000];
+    reverb.process(&mut silence);
+    assert!(silence.iter().all(|frame| *frame == [0.0, 0.0]));
+}
+
+#[test]
+fn bypassed_effects_pass_audio_through() {
+    let mut slot = EffectSlot::new(EffectKind::Gain);
+    slot.params[0] = -6.0;
+    let mut chain = EffectChain::build(&[slot.clone(), EffectSlot::new(EffectKind::Delay)], SAMPLE_RATE);
+    assert_eq!(chain.len(), 2);
+    assert_eq!(chain.latency(), 0);
+
+    chain.set_bypass(0, true);
+    chain.set_bypass(1, true);
+    let mut buffer = vec![[0.5, 0.5]; 8];
+    chain.process(&mut buffer);
+    assert!(buffer.iter().all(|frame| *frame == [0.5, 0.5]));
+
+    chain.set_bypass(0, false);
+    chain.process(&mut buffer);
+    assert!((gain_to_db(buffer[0][0] / 0.5) + 6.0).abs() < 1e-3);
+
+    // slots round trip through a project file
+    let json = serde_json::to_string(&slot).unwrap();
+    assert_eq!(serde_json::from_str::<EffectSlot>(&json).unwrap(), slot);
+}
+
+#[test]
+fn engine_runs_track_and_master_chains() {
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.session.add_instrument(Instrument::new(PathBuf::from("test.wav"), Sample::mono(vec![0.5; 8000], 48000)));
+    engine.session.patterns.push(Pattern::empty("Pattern 1".to_string(), 1));
+    engine.session.patterns[0].data[0][0] = Step::ON;
+    engine.session.playlist.clips.push(PlacedClip {
+        clip_type: ClipType::Pattern(0),
+        name: "Pattern 1".to_string(),
+        track_index: 0,
+        start_time: 0.0,
+        length: 4.0,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    });
+
+    let mut gain = EffectSlot::new(EffectKind::Gain);
+    gain.params[0] = -6.0;
+    engine.session.master_effects.push(gain.clone());
+    engine.session.playlist.tracks[0].effects.push(gain);
+    engine.mixer.build_effects(&engine.session, SAMPLE_RATE);
+    engine.play();
+
+    // longer than one mixer block, so the chains run on every sub-block
+    let frames = MAX_BLOCK * 2 + 10;
+    let mut out = vec![0.0; frames * 2];
+    engine.render(&mut out);
+    let level = |out: &[f32]| gain_to_db(out[(frames - 1) * 2] / 0.5);
+    assert!((level(&out) + 12.0).abs() < 1e-3);
+
+    // the hit keeps ringing while the chains change. Replacing a chain
+    // hands the old one back to be freed.
+    let empty = EffectChain::build(&[], SAMPLE_RATE);
+    assert!(engine.apply(Command::SetEffects(EffectTarget::Master, Box::new(empty))).is_some());
+    engine.render(&mut out);
+    assert!((level(&out) + 6.0).abs() < 1e-3);
+
+    engine.apply(Command::SetEffectBypass { target: EffectTarget::Track(0), slot: 0, bypass: true });
+    engine.render(&mut out);
+    assert!(level(&out).abs() < 1e-3);
+}
@@ -0,0 +1,212 @@
+use std::path::PathBuf;
+use remdaw_engine::resample::Interpolation;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{ClipType, Command, Engine, Instrument, Pattern, PlacedClip, Step};
+
+// 120 bpm at 8 kHz gives a whole number of frames per beat and per step
+const SAMPLE_RATE: f32 = 8000.0;
+const FRAMES_PER_BEAT: usize = 4000;
+const FRAMES_PER_STEP: usize = FRAMES_PER_BEAT / 4;
+
+/// An engine with one instrument per sample, one empty pattern and a
+/// four beat pattern clip at the start of the song
+fn engine_with(samples: &[Vec<f32>]) -> Engine {
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.set_bpm(120);
+    engine.session.patterns.push(Pattern::empty("Pattern 1".to_string(), 0));
+    for sample in samples {
+        engine.session.add_instrument(Instrument::new(PathBuf::from("test.wav"), Sample::mono(sample.clone(), 8000)));
+    }
+    engine.session.playlist.clips.push(PlacedClip {
+        clip_type: ClipType::Pattern(0),
+        name: "Pattern 1".to_string(),
+        track_index: 0,
+        start_time: 0.0,
+        length: 4.0,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    });
+    engine
+}
+
+/// Renders `frames` stereo frames and returns the left channel
+fn render(engine: &mut Engine, frames: usize) -> Vec<f32> {
+    let mut out = vec![0.0; frames * 2];
+    engine.render(&mut out);
+    assert!(out.chunks(2).all(|frame| frame[0] == frame[1]), "channels should match");
+    out.chunks(2).map(|frame| frame[0]).collect()
+}
+
+#[test]
+fn stopped_engine_is_silent() {
+    let mut engine = engine_with(&[vec![1.0; 16]]);
+    engine.session.patterns[0].data[0][0] = Step::ON;
+
+    let out = render(&mut engine, FRAMES_PER_BEAT);
+    assert!(out.iter().all(|&s| s == 0.0));
+    assert_eq!(engine.playhead_position, 0.0);
+}
+
+#[test]
+fn step_triggers_on_its_first_frame() {
+    let mut engine = engine_with(&[vec![0.5; 8]]);
+    engine.session.patterns[0].data[0][1] = Step::ON; // second 16th
+    engine.play();
+
+    let out = render(&mut engine, FRAMES_PER_BEAT);
+    let first_hit = out.iter().position(|&s| s != 0.0);
+    assert_eq!(first_hit, Some(FRAMES_PER_STEP));
+    assert_eq!(&out[FRAMES_PER_STEP..FRAMES_PER_STEP + 8], &[0.5; 8]);
+    assert!(out[FRAMES_PER_STEP + 8..].iter().all(|&s| s == 0.0));
+}
+
+#[test]
+fn active_steps_mix_together() {
+    let mut engine = engine_with(&[vec![0.25; 4], vec![0.5; 2]]);
+    engine.session.patterns[0].data[0][0] = Step::ON;
+    engine.session.patterns[0].data[1][0] = Step::ON;
+    engine.play();
+
+    let out = render(&mut engine, 8);
+    assert_eq!(out, vec![0.75, 0.75, 0.25, 0.25, 0.0, 0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn playhead_advances_one_beat_per_beat_of_frames() {
+    let mut engine = engine_with(&[]);
+    engine.play();
+
+    render(&mut engine, FRAMES_PER_BEAT * 2);
+    assert!((engine.playhead_position - 2.0).abs() < 1e-9);
+
+    engine.stop();
+    assert_eq!(engine.playhead_position, 0.0);
+}
+
+#[test]
+fn manual_trigger_plays_without_transport() {
+    let mut engine = engine_with(&[vec![1.0, -1.0]]);
+    engine.trigger(0);
+
+    let out = render(&mut engine, 4);
+    assert_eq!(out, vec![1.0, -1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn commands_apply_on_next_render() {
+    let mut engine = engine_with(&[vec![1.0; 4]]);
+    let mut handle = engine.connect();
+
+    assert!(handle.send(Command::SetStep { pattern: 0, instrument: 0, step: 0, value: Step::ON }));
+    assert!(handle.send(Command::Play));
+    assert!(!handle.is_playing(), "nothing applies until the engine renders");
+
+    let out = render(&mut engine, FRAMES_PER_BEAT);
+    assert_eq!(out[0], 1.0);
+    assert!(handle.is_playing());
+    assert!((handle.playhead_position() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn replaced_data_is_returned_to_the_ui() {
+    let mut engine = engine_with(&[vec![1.0; 4]]);
+    let mut handle = engine.connect();
+    let frames = engine.session.instruments[0].sample.frames.clone();
+
+    assert!(handle.send(Command::SetInstruments(Vec::new())));
+    render(&mut engine, 1);
+    assert!(engine.session.instruments.is_empty());
+
+    // the old instrument list is waiting on the return queue, not freed by the engine
+    assert_eq!(std::sync::Arc::strong_count(&frames), 2);
+    handle.collect_garbage();
+    assert_eq!(std::sync::Arc::strong_count(&frames), 1);
+}
+
+#[test]
+fn full_queues_refuse_commands() {
+    let mut engine = engine_with(&[]);
+    let mut handle = engine.connect();
+
+    let mut sent = 0;
+    while handle.send(Command::SetBpm(100 + sent % 50)) {
+        sent += 1;
+        assert!(sent <= 4096, "the queue never filled up");
+    }
+    assert!(!handle.send(Command::SetBpm(90)));
+
+    // once the engine catches up there is room again
+    render(&mut engine, 1);
+    assert!(handle.send(Command::SetBpm(90)));
+}
+
+#[test]
+fn playback_interpolation_is_chosen_by_the_ui() {
+    let mut engine = engine_with(&[]);
+    let mut handle = engine.connect();
+    assert_eq!(engine.voices.interpolation, Interpolation::Sinc);
+
+    assert!(handle.send(Command::SetInterpolation(Interpolation::Linear)));
+    render(&mut engine, 1);
+    assert_eq!(engine.voices.interpolation, Interpolation::Linear);
+}
+
+#[test]
+fn mono_and_multichannel_outputs() {
+    let stereo = Sample::stereo(vec![[0.5, 0.25]; 4], 8000);
+
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.session.add_instrument(Instrument::new(PathBuf::from("test.wav"), stereo.clone()));
+    engine.trigger(0);
+    let mut mono = vec![0.0; 4];
+    engine.render_channels(&mut mono, 1);
+    assert_eq!(mono, vec![0.375; 4]);
+
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.session.add_instrument(Instrument::new(PathBuf::from("test.wav"), stereo));
+    engine.trigger(0);
+    let mut surround = vec![1.0; 4 * 6];
+    engine.render_channels(&mut surround, 6);
+    for frame in surround.chunks(6) {
+        assert_eq!(frame, &[0.5, 0.25, 0.0, 0.0, 0.0, 0.0]);
+    }
+}
+
+#[test]
+fn steps_set_the_velocity_pan_and_pitch_of_their_hit() {
+    let mut engine = engine_with(&[vec![0.5; 8]]);
+    engine.session.patterns[0].data[0][0] = Step { velocity: 0.5, pan: -1.0, ..Step::ON };
+    engine.play();
+
+    let mut out = vec![0.0; 8];
+    engine.render(&mut out);
+    let expected = 0.5 * 0.5 * 2f32.sqrt();
+    assert!((out[0] - expected).abs() < 1e-6);
+    assert!(out[1].abs() < 1e-6);
+
+    // an octave up plays the sample twice as fast
+    let mut engine = engine_with(&[vec![0.5; 8]]);
+    engine.session.patterns[0].data[0][0] = Step { pitch: 1200.0, ..Step::ON };
+    engine.play();
+    let out = render(&mut engine, 8);
+    assert_eq!(out.iter().filter(|&&s| s != 0.0).count(), 4);
+}
+
+#[test]
+fn step_probability_skips_some_loops() {
+    let mut engine = engine_with(&[vec![1.0; 1]]);
+    engine.session.playlist.clips[0].length = 400.0;
+    engine.session.patterns[0].data[0][0] = Step { probability: 0.5, ..Step::ON };
+    engine.set_bpm(960); // 500 frames per beat keeps the render short
+    engine.play();
+
+    // 100 loops of the pattern
+    let out = render(&mut engine, 500 * 400);
+    let hits = out.iter().filter(|&&s| s != 0.0).count();
+    assert!((30..70).contains(&hits), "{hits} hits");
+
+    let mut engine = engine_with(&[vec![1.0; 1]]);
+    engine.session.patterns[0].data[0][0] = Step { probability: 0.0, ..Step::ON };
+    engine.play();
+    assert!(render(&mut engine, FRAMES_PER_BEAT).iter().all(|&s| s == 0.0));
+}
@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+use remdaw_engine::effects::{EffectKind, EffectSlot};
+use remdaw_engine::export::{self, BitDepth, ExportSettings};
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{ClipType, Instrument, PlacedClip, Session};
+
+// 120 bpm at 8 kHz gives 4000 frames per beat
+const SAMPLE_RATE: u32 = 8000;
+const FRAMES_PER_BEAT: usize = 4000;
+
+/// A path in the temp directory that no other test uses
+fn temp_wav(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("remdaw-export-{}-{}.wav", name, std::process::id()))
+}
+
+/// Four beats of a ramp from -0.25 up to 0.75, one audio clip at the start of the song
+fn ramp_session() -> (Session, Vec<f32>) {
+    let ramp: Vec<f32> = (0..FRAMES_PER_BEAT * 4).map(|i| i as f32 / (FRAMES_PER_BEAT * 4) as f32 - 0.25).collect();
+    let mut session = Session::new();
+    session.bpm = 120;
+    session.add_instrument(Instrument::new(PathBuf::from("ramp.wav"), Sample::mono(ramp.clone(), SAMPLE_RATE)));
+    session.playlist.clips.push(PlacedClip {
+        clip_type: ClipType::AudioFile(0),
+        name: "ramp.wav".to_string(),
+        track_index: 0,
+        start_time: 0.0,
+        length: 4.0,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    });
+    (session, ramp)
+}
+
+/// Bounces `session` and reads back the spec and the left channel
+fn export_and_read<T: hound::Sample>(session: &Session, settings: &ExportSettings, path: &Path) -> (hound::WavSpec, Vec<T>) {
+    export::write_wav(export::offline_engine(session, settings), settings, path, |_| {}).unwrap();
+    let mut reader = hound::WavReader::open(path).unwrap();
+    let spec = reader.spec();
+    let left = reader.samples::<T>().step_by(2).map(Result::unwrap).collect();
+    std::fs::remove_file(path).unwrap();
+    (spec, left)
+}
+
+fn settings(bit_depth: BitDepth, start_beat: f64, end_beat: f64) -> ExportSettings {
+    ExportSettings { sample_rate: SAMPLE_RATE, bit_depth, start_beat, end_beat, tail: false, ..ExportSettings::default() }
+}
+
+#[test]
+fn exports_cover_exactly_the_selected_beats() {
+    let (session, ramp) = ramp_session();
+    let path = temp_wav("range");
+    let (spec, left) = export_and_read::<f32>(&session, &settings(BitDepth::Float32, 1.0, 3.0), &path);
+
+    assert_eq!((spec.channels, spec.sample_rate), (2, SAMPLE_RATE));
+    assert_eq!(left.len(), FRAMES_PER_BEAT * 2);
+    // the first frame is the first of beat 1 and the last is the one before beat 3
+    assert_eq!(left[0], ramp[FRAMES_PER_BEAT]);
+    assert_eq!(left[left.len() - 1], ramp[FRAMES_PER_BEAT * 3 - 1]);
+}
+
+#[test]
+fn every_bit_depth_scales_the_mix_to_its_range() {
+    let (session, ramp) = ramp_session();
+    let expected = &ramp[..FRAMES_PER_BEAT];
+
+    let (spec, left) = export_and_read::<i16>(&session, &settings(BitDepth::Int16, 0.0, 1.0), &temp_wav("16"));
+    assert_eq!((spec.bits_per_sample, spec.sample_format), (16, hound::SampleFormat::Int));
+    assert_eq!(left, expected.iter().map(|&s| (s * i16::MAX as f32) as i16).collect::<Vec<_>>());
+
+    let (spec, left) = export_and_read::<i32>(&session, &settings(BitDepth::Int24, 0.0, 1.0), &temp_wav("24"));
+    assert_eq!((spec.bits_per_sample, spec.sample_format), (24, hound::SampleFormat::Int));
+    assert_eq!(left, expected.iter().map(|&s| (s * 8_388_607.0) as i32).collect::<Vec<_>>());
+
+    let (spec, left) = export_and_read::<f32>(&session, &settings(BitDepth::Float32, 0.0, 1.0), &temp_wav("32f"));
+    assert_eq!((spec.bits_per_sample, spec.sample_format), (32, hound::SampleFormat::Float));
+    assert_eq!(left, expected);
+}
+
+#[test]
+fn loud_mixes_are_clipped_and_empty_ranges_refused() {
+    let (mut session, _) = ramp_session();
+    session.instruments[0].mix.volume = 4.0;
+    let (_, left) = export_and_read::<i16>(&session, &settings(BitDepth::Int16, 3.0, 4.0), &temp_wav("loud"));
+    assert_eq!(left[left.len() - 1], i16::MAX);
+
+    let path = temp_wav("empty");
+    let empty = settings(BitDepth::Int16, 2.0, 2.0);
+    assert!(export::write_wav(export::offline_engine(&session, &empty), &empty, &path, |_| {}).is_err());
+    assert!(!path.exists());
+}
+
+#[test]
+fn tails_ring_out_past_the_range() {
+    let (mut session, _) = ramp_session();
+    session.master_effects.push(EffectSlot::new(EffectKind::Delay));
+    let range = settings(BitDepth::Float32, 0.0, 1.0);
+    let (_, cut) = export_and_read::<f32>(&session, &range, &temp_wav("cut"));
+    assert_eq!(cut.len(), FRAMES_PER_BEAT);
+
+    // the delay keeps repeating after the range, until it dies away
+    let mut progress = Vec::new();
+    let path = temp_wav("tail");
+    let tail = ExportSettings { tail: true, ..range };
+    export::write_wav(export::offline_engine(&session, &tail), &tail, &path, |done| progress.push(done)).unwrap();
+    let frames = hound::WavReader::open(&path).unwrap().duration() as usize;
+    std::fs::remove_file(&path).unwrap();
+    assert!(frames > FRAMES_PER_BEAT * 2, "{frames}");
+    assert!(frames < SAMPLE_RATE as usize * 30, "{frames}");
+
+    // progress covers the range only, finishing at 1
+    assert!(progress.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(progress.last(), Some(&1.0));
+}
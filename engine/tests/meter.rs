@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+use remdaw_engine::meter::{AtomicLevels, LevelAccumulator, Levels, PeakHold};
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{Engine, Instrument};
+
+#[test]
+fn accumulator_measures_peak_and_rms_per_block() {
+    let mut levels = LevelAccumulator::default();
+    for frame in [[0.5, -1.0], [-0.5, 0.0], [0.5, 0.0], [-0.5, 0.0]] {
+        levels.add(frame);
+    }
+    assert_eq!(levels.take(), Levels { peak: [0.5, 1.0], rms: [0.5, 0.5] });
+
+    // taking starts a new block
+    assert_eq!(levels.take(), Levels::default());
+}
+
+#[test]
+fn peaks_build_up_until_the_ui_takes_them() {
+    let meter = AtomicLevels::default();
+    meter.store(Levels { peak: [0.8, 0.2], rms: [0.1, 0.1] });
+    meter.store(Levels { peak: [0.3, 0.6], rms: [0.2, 0.2] });
+
+    assert_eq!(meter.take(), Levels { peak: [0.8, 0.6], rms: [0.2, 0.2] });
+    assert_eq!(meter.take().peak, [0.0, 0.0]);
+    assert!(!meter.clipped());
+
+    meter.store(Levels { peak: [1.2, 0.0], rms: [0.0, 0.0] });
+    meter.take();
+    assert!(meter.clipped(), "the clip light stays on after the peak is taken");
+    meter.reset_clip();
+    assert!(!meter.clipped());
+}
+
+#[test]
+fn peak_hold_waits_then_falls() {
+    let mut hold = PeakHold::default();
+    hold.update(0.9, 0.1);
+    assert_eq!((hold.peak, hold.hold), (0.9, 0.9));
+
+    // the bar falls straight away, the hold line only after a while
+    hold.update(0.0, 0.5);
+    assert!(hold.peak < 0.9);
+    assert_eq!(hold.hold, 0.9);
+
+    for _ in 0..40 {
+        hold.update(0.0, 0.1);
+    }
+    assert!(hold.hold < 0.1);
+    assert!(hold.hold >= hold.peak);
+}
+
+#[test]
+fn engine_publishes_instrument_and_master_levels() {
+    let mut engine = Engine::new(8000.0);
+    engine.session.add_instrument(Instrument::new(PathBuf::from("loud.wav"), Sample::mono(vec![1.5; 4], 8000)));
+    engine.session.add_instrument(Instrument::new(PathBuf::from("quiet.wav"), Sample::mono(vec![0.25; 4], 8000)));
+    let handle = engine.connect();
+    let meters = handle.meters();
+
+    engine.trigger(1);
+    engine.render(&mut [0.0; 8]);
+    assert_eq!(meters.instruments[1].take(), Levels { peak: [0.25; 2], rms: [0.25; 2] });
+    assert_eq!(meters.instruments[0].take().peak, [0.0; 2]);
+    assert_eq!(meters.master.take().peak, [0.25; 2]);
+
+    // the master is measured before the limiter, so overs light the clip indicator
+    engine.trigger(0);
+    let mut out = [0.0; 8];
+    engine.render(&mut out);
+    assert!(out.iter().all(|&s| s.abs() <= 1.0));
+    assert_eq!(meters.master.take().peak, [1.5; 2]);
+    assert!(meters.master.clipped());
+    assert!(meters.instruments[0].clipped());
+    assert!(!meters.instruments[1].clipped());
+}
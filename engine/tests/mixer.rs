@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use remdaw_engine::mixer::{pan_gains, ChannelMix, Limiter, Mixer};
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{ClipType, Engine, Instrument, Pattern, PlacedClip, Playlist, Step};
+
+const SAMPLE_RATE: f32 = 8000.0;
+
+fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+    (a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5
+}
+
+#[test]
+fn pan_keeps_constant_power_and_unity_centre() {
+    assert!(close(pan_gains(0.0), [1.0, 1.0]));
+    assert!(close(pan_gains(-1.0), [2f32.sqrt(), 0.0]));
+    assert!(close(pan_gains(1.0), [0.0, 2f32.sqrt()]));
+
+    for pan in [-0.7, -0.2, 0.3, 0.9] {
+        let [left, right] = pan_gains(pan);
+        assert!((left * left + right * right - 2.0).abs() < 1e-5, "pan {pan}");
+    }
+}
+
+#[test]
+fn mute_and_solo_silence_channels() {
+    let plain = ChannelMix { volume: 0.5, ..Default::default() };
+    let soloed = ChannelMix { solo: true, ..Default::default() };
+    let muted = ChannelMix { muted: true, solo: true, ..Default::default() };
+
+    assert_eq!(plain.gains(false), [0.5, 0.5]);
+    assert_eq!(plain.gains(true), [0.0, 0.0]);
+    assert_eq!(soloed.gains(true), [1.0, 1.0]);
+    assert_eq!(muted.gains(true), [0.0, 0.0]);
+}
+
+#[test]
+fn limiter_holds_peaks_at_the_ceiling_and_recovers() {
+    let mut limiter = Limiter::new(SAMPLE_RATE);
+    assert_eq!(limiter.process([0.5, -0.5]), [0.5, -0.5]);
+
+    let limited = limiter.process([4.0, -2.0]);
+    assert!(close(limited, [1.0, -0.5]));
+
+    // two seconds later the gain is back at unity
+    let mut last = [0.0; 2];
+    for _ in 0..2 * SAMPLE_RATE as usize {
+        last = limiter.process([0.5, 0.5]);
+    }
+    assert!(close(last, [0.5, 0.5]));
+}
+
+#[test]
+fn tracks_apply_their_mix_and_untracked_sources_skip_it() {
+    let mut playlist = Playlist::new();
+    playlist.tracks[0].mix.volume = 0.5;
+    playlist.tracks[1].mix.muted = true;
+
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.begin_block(1, playlist.tracks.len(), 0);
+    mixer.add(0, Some(0), [0.4, 0.4]);
+    mixer.add(0, Some(1), [0.3, 0.3]);
+    mixer.add(0, None, [0.1, 0.1]);
+    assert!(close(mixer.end_block(&playlist.tracks, &[], 1.0)[0], [0.3, 0.3]));
+
+    // soloing a track silences the others, but not the master bus
+    playlist.tracks[2].mix.solo = true;
+    mixer.begin_block(1, playlist.tracks.len(), 0);
+    mixer.add(0, Some(0), [0.4, 0.4]);
+    mixer.add(0, Some(2), [0.2, 0.2]);
+    mixer.add(0, None, [0.1, 0.1]);
+    assert!(close(mixer.end_block(&playlist.tracks, &[], 0.5)[0], [0.15, 0.15]));
+}
+
+#[test]
+fn engine_routes_pattern_hits_through_their_track() {
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.set_bpm(120);
+    let mut instrument = Instrument::new(PathBuf::from("test.wav"), Sample::mono(vec![0.5; 8], 8000));
+    instrument.mix.pan = -1.0;
+    engine.session.add_instrument(instrument);
+    engine.session.patterns.push(Pattern::empty("Pattern 1".to_string(), 1));
+    engine.session.patterns[0].data[0][0] = Step::ON;
+    engine.session.playlist.clips.push(PlacedClip {
+        clip_type: ClipType::Pattern(0),
+        name: "Pattern 1".to_string(),
+        track_index: 1,
+        start_time: 0.0,
+        length: 4.0,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    });
+    engine.session.playlist.tracks[1].mix.volume = 0.5;
+    engine.play();
+
+    let mut out = vec![0.0; 16];
+    engine.render(&mut out);
+    let expected = 0.5 * 2f32.sqrt() * 0.5;
+    assert!(close([out[0], out[1]], [expected, 0.0]));
+
+    // muting the track silences the pattern
+    engine.session.playlist.tracks[1].mix.muted = true;
+    engine.seek(0.0);
+    engine.render(&mut out);
+    assert!(out.iter().all(|&s| s == 0.0));
+}
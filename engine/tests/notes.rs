@@ -0,0 +1,217 @@
+use std::path::PathBuf;
+use remdaw_engine::models::key_name;
+use remdaw_engine::project::Project;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::sequencer::{schedule_block, Block, EventKind, MAX_EVENTS};
+use remdaw_engine::{ClipType, Command, Engine, Instrument, Note, NotePattern, Pattern, PlacedClip, Session, Step};
+
+// 120 bpm at 8 kHz: 4000 frames per beat
+const SAMPLE_RATE: f32 = 8000.0;
+const SAMPLES_PER_BEAT: f64 = 4000.0;
+
+fn notes_clip(pattern: usize, start_time: f64, length: f64) -> PlacedClip {
+    PlacedClip {
+        clip_type: ClipType::Notes(pattern),
+        name: "Notes".to_string(),
+        track_index: 0,
+        start_time,
+        length,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    }
+}
+
+fn note(key: u8, start: f64, length: f64) -> Note {
+    Note { key, start, length, velocity: 0.8 }
+}
+
+/// Every note on and off of `clips` over `total` frames, by absolute frame
+fn schedule(clips: &[PlacedClip], patterns: &[NotePattern], total: usize, block_size: usize) -> Vec<(usize, EventKind)> {
+    let mut events = Vec::with_capacity(MAX_EVENTS);
+    let mut hits = Vec::new();
+    let mut frame = 0;
+    while frame < total {
+        let frames = block_size.min(total - frame);
+        let block = Block::new(frame as f64 / SAMPLES_PER_BEAT, frames, SAMPLES_PER_BEAT);
+        schedule_block(clips, &[], patterns, &block, 0.0, None, &mut events);
+        hits.extend(events.iter().map(|event| (frame + event.offset, event.kind)));
+        frame += frames;
+    }
+    hits
+}
+
+#[test]
+fn notes_start_and_stop_and_loop_with_the_pattern() {
+    let mut pattern = NotePattern::new("Bass".to_string(), 2);
+    pattern.length = 2.0;
+    pattern.notes = vec![note(48, 0.0, 0.5), note(55, 1.5, 1.0)];
+
+    // the clip plays the pattern one and a half times
+    let clips = [notes_clip(0, 1.0, 3.0)];
+    let on = |key| EventKind::NoteOn { instrument: 2, key, velocity: 0.8 };
+    let off = |key| EventKind::NoteOff { instrument: 2, key };
+    let expected = vec![
+        (4000, on(48)),
+        (6000, off(48)),
+        (10000, on(55)),
+        // the second note rings into the next loop, where the first starts again
+        (12000, on(48)),
+        (14000, off(48)),
+        (14000, off(55)),
+    ];
+    let mut whole = schedule(&clips, &[pattern.clone()], 20000, 20000);
+    // offs on the same frame can come in any order
+    whole.sort_by_key(|(frame, kind)| (*frame, format!("{kind:?}")));
+    assert_eq!(whole.len(), expected.len());
+    for event in &expected {
+        assert!(whole.contains(event), "{event:?} in {whole:?}");
+    }
+
+    for block_size in [1, 300, 4000] {
+        let mut hits = schedule(&clips, &[pattern.clone()], 20000, block_size);
+        hits.sort_by_key(|(frame, kind)| (*frame, format!("{kind:?}")));
+        assert_eq!(hits, whole, "block size {block_size}");
+    }
+}
+
+#[test]
+fn a_note_off_goes_before_a_note_on_of_the_same_frame() {
+    let mut pattern = NotePattern::new("Repeat".to_string(), 0);
+    pattern.length = 1.0;
+    pattern.notes = vec![note(60, 0.0, 1.0)];
+
+    let hits = schedule(&[notes_clip(0, 0.0, 2.0)], &[pattern], 8000, 8000);
+    let kinds: Vec<bool> = hits.iter().map(|(_, kind)| matches!(kind, EventKind::NoteOn { .. })).collect();
+    assert_eq!(hits.iter().map(|(frame, _)| *frame).collect::<Vec<_>>(), vec![0, 4000, 4000]);
+    assert_eq!(kinds, vec![true, false, true]);
+}
+
+#[test]
+fn notes_shorter_than_a_frame_are_skipped() {
+    let mut pattern = NotePattern::new("Blip".to_string(), 0);
+    pattern.notes = vec![note(60, 0.0, 0.1 / SAMPLES_PER_BEAT), note(62, 0.5, 1.0), note(64, 1.0, 1.0)];
+
+    // the clip cuts the second note off a fraction of a frame in and ends before the third
+    let clips = [notes_clip(0, 0.0, 0.5 + 0.5 / SAMPLES_PER_BEAT)];
+    for block_size in [1, 4000] {
+        assert!(schedule(&clips, &[pattern.clone()], 8000, block_size).is_empty());
+    }
+}
+
+#[test]
+fn notes_are_pitched_from_the_root_key_and_stop_on_release() {
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.set_bpm(120);
+    let mut instrument = Instrument::new(PathBuf::from("test.wav"), Sample::mono(vec![0.5; 80000], 8000));
+    instrument.root_key = 57;
+    engine.session.add_instrument(instrument);
+
+    let mut pattern = NotePattern::new("Lead".to_string(), 0);
+    pattern.notes = vec![note(69, 0.0, 0.25)];
+    engine.session.note_patterns.push(pattern);
+    engine.session.playlist.clips.push(notes_clip(0, 0.0, 4.0));
+    engine.play();
+
+    // an octave over the root plays twice as fast
+    let mut out = vec![0.0; 200 * 2];
+    engine.render(&mut out);
+    assert_eq!(engine.voices.voices().len(), 1);
+    assert_eq!(engine.voices.voices()[0].pitch, 2.0);
+    assert_eq!(engine.voices.voices()[0].key, Some(69));
+
+    // a quarter beat in the note off fades it out
+    let mut out = vec![0.0; 1000 * 2];
+    engine.render(&mut out);
+    assert!(engine.voices.voices().is_empty());
+    assert_eq!(out[999 * 2], 0.0);
+}
+
+#[test]
+fn seeking_or_replacing_notes_lets_held_notes_go() {
+    let mut engine = Engine::new(SAMPLE_RATE);
+    engine.set_bpm(120);
+    engine.session.add_instrument(Instrument::new(PathBuf::from("test.wav"), Sample::mono(vec![0.5; 80000], 8000)));
+    let mut pattern = NotePattern::new("Pad".to_string(), 0);
+    pattern.notes = vec![note(60, 0.0, 4.0)];
+    engine.session.note_patterns.push(pattern);
+    engine.session.playlist.clips.push(notes_clip(0, 0.0, 4.0));
+    engine.play();
+
+    let mut out = vec![0.0; 200 * 2];
+    engine.render(&mut out);
+    assert_eq!(engine.voices.voices().len(), 1);
+
+    // the note off is behind the new playhead
+    engine.seek(2.0);
+    let mut out = vec![0.0; 1000 * 2];
+    engine.render(&mut out);
+    assert!(engine.voices.voices().is_empty());
+
+    // the note that is sounding may have been removed
+    engine.seek(0.0);
+    engine.render(&mut out);
+    assert_eq!(engine.voices.voices().len(), 1);
+    let mut handle = engine.connect();
+    assert!(handle.send(Command::SetNotePatterns(Vec::new())));
+    engine.render(&mut out);
+    assert!(engine.voices.voices().is_empty());
+}
+
+#[test]
+fn removing_a_note_pattern_removes_its_clips() {
+    let mut session = Session::new();
+    session.note_patterns = vec![NotePattern::new("A".to_string(), 0), NotePattern::new("B".to_string(), 0)];
+    session.playlist.clips = vec![notes_clip(0, 0.0, 4.0), notes_clip(1, 4.0, 4.0)];
+
+    session.remove_note_pattern(0);
+    assert_eq!(session.note_patterns.len(), 1);
+    assert_eq!(session.playlist.clips.len(), 1);
+    assert!(matches!(session.playlist.clips[0].clip_type, ClipType::Notes(0)));
+
+    assert_eq!(key_name(60), "C4");
+    assert_eq!(key_name(61), "C#4");
+    assert_eq!(key_name(21), "A0");
+}
+
+#[test]
+fn note_patterns_and_root_keys_round_trip_through_projects() {
+    let mut session = Session::new();
+    let mut instrument = Instrument::new(PathBuf::from("missing.wav"), Sample::default());
+    instrument.root_key = 45;
+    session.add_instrument(instrument);
+    let mut pattern = NotePattern::new("Chords".to_string(), 0);
+    pattern.notes = vec![note(60, 0.0, 1.0), note(64, 0.0, 1.0)];
+    session.note_patterns.push(pattern);
+    session.playlist.clips.push(notes_clip(0, 2.0, 4.0));
+
+    let json = serde_json::to_string(&Project::from_session(&session, &std::env::temp_dir())).unwrap();
+    let mut loaded = Session::new();
+    serde_json::from_str::<Project>(&json).unwrap().apply(&mut loaded, &std::env::temp_dir());
+
+    assert_eq!(loaded.instruments[0].root_key, 45);
+    assert_eq!(loaded.note_patterns[0].notes, session.note_patterns[0].notes);
+    assert!(matches!(loaded.playlist.clips[0].clip_type, ClipType::Notes(0)));
+}
+
+#[test]
+fn note_offs_arrive_in_a_full_block() {
+    // a pattern clip with more hits on its first step than the buffer holds
+    let mut crowded = Pattern::empty("Pattern 1".to_string(), MAX_EVENTS + 10);
+    for row in &mut crowded.data {
+        row[0] = Step::ON;
+    }
+    let mut notes = NotePattern::new("Bass".to_string(), 0);
+    notes.notes = vec![note(48, 0.0, 0.5), note(50, 0.25, 0.5)];
+    let clips = [
+        PlacedClip { clip_type: ClipType::Pattern(0), ..notes_clip(0, 0.0, 1.0) },
+        notes_clip(0, 0.0, 1.0),
+    ];
+
+    let block = Block::new(0.0, 4000, SAMPLES_PER_BEAT);
+    let mut events = Vec::with_capacity(MAX_EVENTS);
+    schedule_block(&clips, &[crowded], &[notes], &block, 0.0, None, &mut events);
+
+    assert_eq!(events.len(), events.capacity());
+    let offs: Vec<_> = events.iter().filter(|event| matches!(event.kind, EventKind::NoteOff { .. })).map(|event| event.offset).collect();
+    assert_eq!(offs, vec![2000, 3000]);
+}
@@ -0,0 +1,68 @@
+use remdaw_engine::peaks::{Peak, BASE_BUCKET};
+use remdaw_engine::sample::Sample;
+
+/// Noise with a slow swell, so every stretch of it has a different peak
+fn noisy_sample(frames: usize) -> Sample {
+    let mut state = 0x1234_5678u32;
+    Sample::stereo(
+        (0..frames)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let swell = (i as f32 / frames as f32 * 7.0).sin();
+                let noise = state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+                [noise * swell, noise * 0.5]
+            })
+            .collect(),
+        44100,
+    )
+}
+
+/// Peak found by reading every frame
+fn scan(sample: &Sample, start: usize, end: usize) -> Peak {
+    let frames = &sample.frames[start..end.min(sample.len())];
+    Peak {
+        min: frames.iter().flatten().copied().fold(f32::INFINITY, f32::min),
+        max: frames.iter().flatten().copied().fold(f32::NEG_INFINITY, f32::max),
+    }
+}
+
+#[test]
+fn bucket_aligned_ranges_match_a_full_scan_at_every_level() {
+    let sample = noisy_sample(300_000);
+    for bucket in [BASE_BUCKET, BASE_BUCKET * 4, BASE_BUCKET * 16, BASE_BUCKET * 256] {
+        for start in [0, bucket, bucket * 7] {
+            let end = start + bucket * 3;
+            assert_eq!(sample.peak(start, end), scan(&sample, start, end), "{bucket} frame buckets from {start}");
+        }
+    }
+
+    // the whole file, including the short last bucket
+    assert_eq!(sample.peak(0, sample.len()), scan(&sample, 0, sample.len()));
+}
+
+#[test]
+fn any_range_covers_its_frames_and_little_more() {
+    let sample = noisy_sample(100_000);
+    for (start, end) in [(5, 20), (100, 1000), (12_345, 67_890), (99_000, 200_000)] {
+        let peak = sample.peak(start, end);
+        let exact = scan(&sample, start, end);
+        assert!(peak.min <= exact.min && peak.max >= exact.max, "{start}..{end}");
+
+        // widened by at most a bucket of the level it read on each side
+        let span = end.min(sample.len()) - start;
+        let bucket = (0..).map(|level| BASE_BUCKET << (2 * level)).take_while(|bucket| *bucket <= span).last().unwrap_or(1);
+        let wide = scan(&sample, start.saturating_sub(bucket), end + bucket);
+        assert!(peak.min >= wide.min && peak.max <= wide.max, "{start}..{end}");
+    }
+}
+
+#[test]
+fn empty_ranges_and_samples_have_no_peak() {
+    let sample = noisy_sample(1000);
+    assert!(sample.peak(500, 500).is_empty());
+    assert!(sample.peak(2000, 3000).is_empty());
+    assert!(Sample::default().peak(0, 100).is_empty());
+    assert!(!sample.peak(999, 1000).is_empty());
+}
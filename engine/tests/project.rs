@@ -0,0 +1,234 @@
+use std::path::{Path, PathBuf};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use remdaw_engine::project::{Project, PROJECT_VERSION};
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{ClipType, Error, Instrument, Pattern, PlacedClip, Session, Step, StepResolution, TimeSignature};
+
+/// A path in the temp directory that no other test uses
+fn temp_project(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("remdaw-project-{}-{}.remdaw", std::process::id(), name))
+}
+
+/// An empty directory in the temp directory that no other test uses
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("remdaw-project-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes a short mono WAV
+fn write_wav(path: &Path) {
+    let spec = WavSpec { channels: 1, sample_rate: 22050, bits_per_sample: 16, sample_format: SampleFormat::Int };
+    let mut writer = WavWriter::create(path, spec).unwrap();
+    for sample in [0i16, 1000, -1000, 0] {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// A session with one instrument playing `sample`, one step on and a clip of the pattern
+fn session_with(sample: &Path) -> Session {
+    let mut session = Session::new();
+    session.bpm = 97;
+    session.swing = 0.25;
+    session.add_instrument(Instrument::new(sample.to_path_buf(), Sample::default()));
+    session.patterns.push(Pattern::empty("Pattern 1".to_string(), 1));
+    session.patterns[0].data[0][4] = Step { velocity: 0.5, ..Step::ON };
+    session.playlist.clips.push(PlacedClip {
+        clip_type: ClipType::Pattern(0),
+        name: "Pattern 1".to_string(),
+        track_index: 2,
+        start_time: 8.0,
+        length: 4.0,
+        color: [10, 20, 30, 255],
+        offset: 0.0,
+    });
+    session
+}
+
+#[test]
+fn saved_projects_load_back_as_the_same_song() {
+    let dir = temp_dir("round-trip");
+    let sample = dir.join("kick.wav");
+    write_wav(&sample);
+    let session = session_with(&sample);
+
+    let path = dir.join("song.remdaw");
+    Project::from_session(&session, &dir).save(&path).unwrap();
+    let mut loaded = Session::new();
+    let errors = Project::load(&path).unwrap().apply(&mut loaded, &dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(errors.is_empty());
+    assert_eq!(loaded.bpm, 97);
+    assert_eq!(loaded.swing, 0.25);
+    assert_eq!(loaded.instruments[0].file_path, sample);
+    assert_eq!(loaded.instruments[0].sample.len(), 4);
+    assert_eq!(loaded.patterns[0].data, session.patterns[0].data);
+
+    let clip = &loaded.playlist.clips[0];
+    assert!(matches!(clip.clip_type, ClipType::Pattern(0)));
+    assert_eq!((clip.track_index, clip.start_time, clip.length), (2, 8.0, 4.0));
+    assert_eq!(clip.color, [10, 20, 30, 255]);
+}
+
+#[test]
+fn sample_paths_are_stored_relative_to_the_project() {
+    let dir = temp_dir("relative");
+    std::fs::create_dir(dir.join("samples")).unwrap();
+    let sample = dir.join("samples").join("kick.wav");
+    write_wav(&sample);
+    let session = session_with(&sample);
+
+    // samples below the project are stored relative to it, others stay absolute
+    let project = Project::from_session(&session, &dir);
+    assert_eq!(project.instruments[0].path, Path::new("samples").join("kick.wav"));
+    let elsewhere = temp_dir("relative-elsewhere");
+    let outside = Project::from_session(&session, &elsewhere);
+    assert!(outside.instruments[0].path.is_absolute());
+
+    // relative paths resolve against the project, whatever separator they were saved with
+    let mut loaded = Session::new();
+    let mut project = Project::from_session(&session, &dir);
+    project.instruments[0].path = PathBuf::from("samples\\kick.wav");
+    assert!(project.apply(&mut loaded, &dir).is_empty());
+    assert_eq!(loaded.instruments[0].file_path, sample);
+
+    // a project moved together with its sample finds it next to itself
+    let moved = elsewhere.join("kick.wav");
+    std::fs::copy(&sample, &moved).unwrap();
+    let errors = Project::from_session(&session, &dir).apply(&mut loaded, &elsewhere);
+    std::fs::remove_dir_all(&dir).unwrap();
+    std::fs::remove_dir_all(&elsewhere).unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(loaded.instruments[0].file_path, moved);
+    assert_eq!(loaded.instruments[0].sample.len(), 4);
+}
+
+#[test]
+fn newer_projects_are_rejected_with_their_version() {
+    let path = temp_project("newer");
+    std::fs::write(&path, format!(r#"{{ "version": {}, "bpm": 120 }}"#, PROJECT_VERSION + 1)).unwrap();
+
+    let result = Project::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    match result {
+        Err(Error::ProjectVersion { version, supported, .. }) => {
+            assert_eq!(version, PROJECT_VERSION + 1);
+            assert_eq!(supported, PROJECT_VERSION);
+        }
+        _ => panic!("expected a version error"),
+    }
+}
+
+#[test]
+fn unreadable_projects_are_errors() {
+    assert!(matches!(Project::load(&temp_project("missing")), Err(Error::Project { .. })));
+
+    let path = temp_project("garbage");
+    std::fs::write(&path, "{ not json").unwrap();
+    let result = Project::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(Error::Project { .. })));
+}
+
+#[test]
+fn malformed_projects_are_rejected() {
+    let mut session = Session::new();
+    session.patterns.push(Pattern::empty("Pattern 1".to_string(), 0));
+    session.playlist.clips.push(PlacedClip {
+        clip_type: ClipType::Pattern(0),
+        name: "Pattern 1".to_string(),
+        track_index: 0,
+        start_time: 0.0,
+        length: 4.0,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    });
+    let json = serde_json::to_string(&Project::from_session(&session, &std::env::temp_dir())).unwrap();
+
+    let load = |name: &str, json: String| {
+        let path = temp_project(name);
+        std::fs::write(&path, json).unwrap();
+        let result = Project::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    };
+    assert!(load("valid", json.clone()).is_ok());
+    // a tempo of zero would make every beat infinitely long
+    assert!(matches!(load("bpm", json.replace("\"bpm\":130", "\"bpm\":0")), Err(Error::Project { .. })));
+    assert!(matches!(load("pattern", json.replace("{\"Pattern\":0}", "{\"Pattern\":3}")), Err(Error::Project { .. })));
+    assert!(matches!(load("track", json.replace("\"track_index\":0", "\"track_index\":999")), Err(Error::Project { .. })));
+}
+
+#[test]
+fn missing_samples_load_as_silence_and_are_reported() {
+    let path = temp_project("missing-sample");
+    std::fs::write(&path, r#"{
+        "version": 1,
+        "bpm": 120,
+        "instruments": [{ "name": "Kick", "path": "does-not-exist.wav" }]
+    }"#).unwrap();
+
+    let project = Project::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let mut session = Session::new();
+    let errors = project.unwrap().apply(&mut session, &std::env::temp_dir());
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0], Error::Load { .. }));
+    assert_eq!(session.instruments.len(), 1);
+    assert!(session.instruments[0].sample.is_empty());
+    assert_eq!(session.patterns[0].data.len(), 1);
+}
+
+#[test]
+fn version_1_steps_load_and_edited_steps_round_trip() {
+    let project: Project = serde_json::from_str(r#"{
+        "version": 1,
+        "bpm": 120,
+        "patterns": [{ "name": "Pattern 1", "data": [[true, false]] }]
+    }"#).unwrap();
+    assert_eq!(project.patterns[0].data[0], vec![Step::ON, Step::OFF]);
+
+    // untouched steps are still saved as plain booleans
+    let mut pattern = Pattern::empty("Pattern 1".to_string(), 1);
+    pattern.data[0][0] = Step::ON;
+    pattern.data[0][1] = Step { velocity: 0.5, pitch: -20.0, ..Step::OFF };
+    let json = serde_json::to_string(&pattern).unwrap();
+    assert!(json.contains("[[true,{") && json.contains("false]],"), "{json}");
+
+    let loaded: Pattern = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.data, pattern.data);
+}
+
+#[test]
+fn patterns_and_meter_default_to_sixteen_steps_of_four_four() {
+    let project: Project = serde_json::from_str(r#"{
+        "version": 2,
+        "bpm": 120,
+        "instruments": [{ "name": "Kick", "path": "does-not-exist.wav" }],
+        "patterns": [{ "name": "Pattern 1", "data": [[true, false]] }]
+    }"#).unwrap();
+    let mut session = Session::new();
+    session.time_signature = TimeSignature::new(7, 8);
+    project.apply(&mut session, &std::env::temp_dir());
+
+    // short rows from hand edited files are padded out to the pattern
+    assert_eq!(session.time_signature, TimeSignature::default());
+    assert_eq!(session.patterns[0].steps, 16);
+    assert_eq!(session.patterns[0].resolution, StepResolution::Sixteenth);
+    assert_eq!(session.patterns[0].data[0].len(), 16);
+
+    session.time_signature = TimeSignature::new(5, 4);
+    session.patterns[0].resolution = StepResolution::SixteenthTriplet;
+    session.patterns[0].set_steps(30);
+    let json = serde_json::to_string(&Project::from_session(&session, &std::env::temp_dir())).unwrap();
+    let mut reloaded = Session::new();
+    serde_json::from_str::<Project>(&json).unwrap().apply(&mut reloaded, &std::env::temp_dir());
+    assert_eq!(reloaded.time_signature, TimeSignature::new(5, 4));
+    assert_eq!(reloaded.patterns[0].steps, 30);
+    assert_eq!(reloaded.patterns[0].resolution, StepResolution::SixteenthTriplet);
+    assert_eq!(reloaded.patterns[0].length(), 5.0);
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use remdaw_engine::project::Project;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{ClipType, Instrument, Pattern, PlacedClip, Session};
+
+/// Exit code of the renderer when samples are missing
+const EXIT_MISSING_SAMPLES: i32 = 2;
+
+/// An empty directory in the temp directory that no other test uses
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("remdaw-render-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Saves a four bar song of one pattern in `dir`, playing a sample that isn't on disk when `missing`
+fn save_song(dir: &Path, missing: bool) -> PathBuf {
+    let mut session = Session::new();
+    session.bpm = 120;
+    if missing {
+        session.add_instrument(Instrument::new(dir.join("gone.wav"), Sample::default()));
+    }
+    session.patterns.push(Pattern::empty("Pattern 1".to_string(), session.instruments.len()));
+    session.playlist.clips.push(PlacedClip {
+        clip_type: ClipType::Pattern(0),
+        name: "Pattern 1".to_string(),
+        track_index: 0,
+        start_time: 0.0,
+        length: 16.0,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    });
+
+    let path = dir.join("song.remdaw");
+    Project::from_session(&session, dir).save(&path).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_remdaw-render")).args(args).output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Frames in a rendered WAV
+fn frames(path: &Path) -> u32 {
+    hound::WavReader::open(path).unwrap().duration()
+}
+
+#[test]
+fn help_and_bad_flags() {
+    let help = run(&["--help"]);
+    assert!(help.status.success());
+    assert!(String::from_utf8_lossy(&help.stdout).starts_with("usage: remdaw-render"));
+
+    for (args, message) in [
+        (&["song.remdaw", "--bpm", "20"][..], "--bpm must be between 40 and 300"),
+        (&["song.remdaw", "--bpm", "fast"], "--bpm expects a number, got 'fast'"),
+        (&["song.remdaw", "--start", "0"], "--start must be between 1"),
+        (&["song.remdaw", "--end"], "--end needs a value"),
+        (&["song.remdaw", "--bit-depth", "8"], "unsupported bit depth '8'"),
+        (&["song.remdaw", "--loud"], "unknown option '--loud'"),
+        (&["song.remdaw", "other.remdaw"], "unexpected argument 'other.remdaw'"),
+        (&[], "no project file given"),
+    ] {
+        let output = run(args);
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        assert!(stderr(&output).contains(message), "{args:?}: {}", stderr(&output));
+    }
+}
+
+#[test]
+fn bpm_and_bars_set_the_length() {
+    let dir = temp_dir("range");
+    let song = save_song(&dir, false);
+    let song = song.to_str().unwrap();
+    let out = dir.join("out.wav");
+    let out_arg = out.to_str().unwrap();
+
+    // the whole song by default, named after the project
+    assert!(run(&[song, "--sample-rate", "8000"]).status.success());
+    assert_eq!(frames(&dir.join("song.wav")), 16 * 4000);
+
+    // bars 2 up to 4 are two bars of four beats
+    assert!(run(&[song, "-o", out_arg, "--sample-rate", "8000", "--start", "2", "--end", "4"]).status.success());
+    assert_eq!(frames(&out), 8 * 4000);
+
+    // twice the tempo, half the frames
+    assert!(run(&[song, "-o", out_arg, "--sample-rate", "8000", "--start", "2", "--end", "4", "--bpm", "240"]).status.success());
+    assert_eq!(frames(&out), 8 * 2000);
+
+    let spec = hound::WavReader::open(&out).unwrap().spec();
+    assert_eq!((spec.sample_rate, spec.bits_per_sample), (8000, 16));
+    assert!(run(&[song, "-o", out_arg, "--bit-depth", "32f"]).status.success());
+    assert_eq!(hound::WavReader::open(&out).unwrap().spec().sample_format, hound::SampleFormat::Float);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn missing_samples_stop_the_render_unless_allowed() {
+    let dir = temp_dir("missing");
+    let song = save_song(&dir, true);
+    let song = song.to_str().unwrap();
+    let out = dir.join("song.wav");
+
+    let refused = run(&[song]);
+    assert_eq!(refused.status.code(), Some(EXIT_MISSING_SAMPLES));
+    assert!(stderr(&refused).contains("warning:"), "{}", stderr(&refused));
+    assert!(!out.exists());
+
+    let allowed = run(&[song, "--allow-missing", "--sample-rate", "8000"]);
+    assert!(allowed.status.success(), "{}", stderr(&allowed));
+    assert_eq!(frames(&out), 16 * 4000);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
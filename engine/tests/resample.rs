@@ -0,0 +1,100 @@
+use std::f64::consts::TAU;
+use remdaw_engine::resample::Interpolation;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::voice::{Source, StealPolicy, VoicePool};
+
+/// One second of a sine wave at `frequency`, recorded at `rate`
+fn sine(frequency: f64, rate: u32) -> Sample {
+    let samples = (0..rate)
+        .map(|i| (TAU * frequency * i as f64 / rate as f64).sin() as f32 * 0.5)
+        .collect();
+    Sample::mono(samples, rate)
+}
+
+/// Plays `sample` once through a pool running at `output_rate`
+fn play(sample: &Sample, output_rate: u32, interpolation: Interpolation) -> Vec<f32> {
+    let mut pool = VoicePool::new(output_rate as f32);
+    pool.interpolation = interpolation;
+    pool.trigger(Source::Instrument(0), 1.0, 1.0, 1, StealPolicy::Oldest);
+
+    let mut out = Vec::new();
+    while !pool.voices().is_empty() {
+        let [left, right] = pool.next_frame(|_| Some(sample));
+        assert_eq!(left, right);
+        out.push(left);
+    }
+    out
+}
+
+/// Frequency of a sine from the time between its first and last rising zero crossings
+fn measure_frequency(signal: &[f32], rate: u32) -> f64 {
+    let crossings: Vec<f64> = signal.windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+        .map(|(i, pair)| i as f64 + (-pair[0] / (pair[1] - pair[0])) as f64)
+        .collect();
+
+    let periods = (crossings.len() - 1) as f64;
+    periods * rate as f64 / (crossings[crossings.len() - 1] - crossings[0])
+}
+
+fn rms(signal: &[f32]) -> f64 {
+    (signal.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / signal.len() as f64).sqrt()
+}
+
+#[test]
+fn converted_sines_keep_their_pitch() {
+    for (source_rate, output_rate) in [(44100, 48000), (48000, 44100), (22050, 48000), (96000, 44100)] {
+        for interpolation in Interpolation::ALL {
+            let out = play(&sine(1000.0, source_rate), output_rate, interpolation);
+            let frequency = measure_frequency(&out, output_rate);
+
+            assert!(
+                (frequency - 1000.0).abs() < 0.5,
+                "{source_rate} Hz -> {output_rate} Hz with {:?}: {frequency} Hz", interpolation,
+            );
+            // one second of audio stays one second long
+            assert!((out.len() as i64 - output_rate as i64).abs() <= 1);
+        }
+    }
+}
+
+#[test]
+fn sinc_is_closer_to_the_ideal_sine_than_linear() {
+    let frequency = 5000.0;
+    let source = sine(frequency, 44100);
+    let ideal: Vec<f32> = (0..48000)
+        .map(|i| (TAU * frequency * i as f64 / 48000.0).sin() as f32 * 0.5)
+        .collect();
+
+    // skip the edges, where the kernel runs off the ends of the sample
+    let error = |interpolation| {
+        let out = play(&source, 48000, interpolation);
+        let diff: Vec<f32> = out.iter().zip(&ideal).map(|(a, b)| a - b).skip(100).take(47000).collect();
+        rms(&diff)
+    };
+    let linear = error(Interpolation::Linear);
+    let sinc = error(Interpolation::Sinc);
+
+    assert!(sinc < 1e-3, "sinc error {sinc}");
+    assert!(sinc * 20.0 < linear, "sinc {sinc} vs linear {linear}");
+}
+
+#[test]
+fn sinc_filters_what_the_output_rate_cannot_hold() {
+    // 15 kHz is above the 11025 Hz Nyquist limit of the output
+    let out = play(&sine(15000.0, 48000), 22050, Interpolation::Sinc);
+    let level = rms(&out[100..out.len() - 100]);
+
+    // the input sits at about 0.35 rms
+    assert!(level < 0.005, "aliased level {level}");
+}
+
+#[test]
+fn matching_rates_pass_samples_through_untouched() {
+    let source = sine(1000.0, 44100);
+    let out = play(&source, 44100, Interpolation::Sinc);
+
+    let expected: Vec<f32> = source.frames.iter().map(|frame| frame[0]).collect();
+    assert_eq!(out, expected);
+}
@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+use remdaw_engine::mixer::Mixer;
+use remdaw_engine::project::Project;
+use remdaw_engine::routing::{break_loops, creates_loop, process_order, validate, AuxSend, MAX_AUX_BUSES};
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{AuxBus, Error, Instrument, Playlist, Session};
+
+const SAMPLE_RATE: f32 = 8000.0;
+
+fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+    (a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5
+}
+
+/// Buses named A, B, C... where bus `i` sends to every bus in `sends[i]`
+fn buses(sends: &[&[usize]]) -> Vec<AuxBus> {
+    sends.iter()
+        .enumerate()
+        .map(|(index, sends)| AuxBus {
+            sends: sends.iter().map(|&bus| AuxSend::new(bus)).collect(),
+            ..AuxBus::new(((b'A' + index as u8) as char).to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn sends_that_lead_back_are_loops() {
+    // A -> B -> C
+    let graph = buses(&[&[1], &[2], &[]]);
+    assert!(validate(&graph).is_ok());
+    assert!(creates_loop(&graph, 2, 0));
+    assert!(creates_loop(&graph, 1, 0));
+    assert!(creates_loop(&graph, 1, 1));
+    assert!(!creates_loop(&graph, 0, 2));
+
+    let looped = buses(&[&[1], &[2], &[0]]);
+    assert!(matches!(validate(&looped), Err(Error::Routing(_))));
+    assert!(validate(&buses(&[&[0]])).is_err());
+}
+
+#[test]
+fn breaking_loops_keeps_the_earliest_sends() {
+    let mut graph = buses(&[&[1], &[2], &[0, 1]]);
+    assert_eq!(break_loops(&mut graph), 2);
+    assert!(validate(&graph).is_ok());
+    assert_eq!(graph[0].sends.len(), 1);
+    assert_eq!(graph[1].sends.len(), 1);
+    assert!(graph[2].sends.is_empty());
+}
+
+#[test]
+fn buses_run_after_everything_feeding_them() {
+    // C -> A and A -> B, so C runs first and B last
+    let graph = buses(&[&[1], &[], &[0]]);
+    let mut order = [0; MAX_AUX_BUSES];
+    let count = process_order(&graph, &mut order);
+    assert_eq!(&order[..count], &[2, 0, 1]);
+
+    // loops still run every bus once
+    let looped = buses(&[&[1], &[0]]);
+    let count = process_order(&looped, &mut order);
+    assert_eq!(count, 2);
+    assert_ne!(order[0], order[1]);
+}
+
+#[test]
+fn removing_a_bus_removes_and_renumbers_sends() {
+    let mut session = Session::new();
+    session.aux_buses = buses(&[&[], &[], &[0, 1]]);
+    session.playlist.tracks[0].sends = vec![AuxSend::new(1), AuxSend::new(2)];
+    session.add_instrument(Instrument::new(PathBuf::from("test.wav"), Sample::default()));
+    session.instruments[0].sends = vec![AuxSend::new(0)];
+
+    session.remove_aux_bus(1);
+    assert_eq!(session.aux_buses.len(), 2);
+    assert_eq!(session.playlist.tracks[0].sends, vec![AuxSend::new(1)]);
+    assert_eq!(session.instruments[0].sends, vec![AuxSend::new(0)]);
+    assert_eq!(session.aux_buses[1].sends, vec![AuxSend::new(0)]);
+}
+
+#[test]
+fn sends_tap_before_or_after_the_fader() {
+    let mut playlist = Playlist::new();
+    playlist.tracks[0].mix.volume = 0.5;
+    playlist.tracks[0].sends = vec![AuxSend { amount: 0.5, ..AuxSend::new(0) }];
+    let aux = buses(&[&[]]);
+
+    // 0.2 from the track, a quarter of that back from the bus
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.begin_block(1, playlist.tracks.len(), aux.len());
+    mixer.add(0, Some(0), [0.4, 0.4]);
+    assert!(close(mixer.end_block(&playlist.tracks, &aux, 1.0)[0], [0.3, 0.3]));
+
+    // pre fader the bus gets half the full level
+    playlist.tracks[0].sends[0].pre_fader = true;
+    mixer.begin_block(1, playlist.tracks.len(), aux.len());
+    mixer.add(0, Some(0), [0.4, 0.4]);
+    assert!(close(mixer.end_block(&playlist.tracks, &aux, 1.0)[0], [0.4, 0.4]));
+
+    // muting the track silences pre fader sends too
+    playlist.tracks[0].mix.muted = true;
+    mixer.begin_block(1, playlist.tracks.len(), aux.len());
+    mixer.add(0, Some(0), [0.4, 0.4]);
+    assert!(close(mixer.end_block(&playlist.tracks, &aux, 1.0)[0], [0.0, 0.0]));
+}
+
+#[test]
+fn buses_feed_later_buses_and_return_to_master() {
+    let mut playlist = Playlist::new();
+    playlist.tracks[0].sends = vec![AuxSend::new(1)];
+    // B sends to A, so A has to wait for B even though it comes first
+    let mut aux = buses(&[&[], &[0]]);
+    aux[0].mix.volume = 0.5;
+
+    let mut mixer = Mixer::new(SAMPLE_RATE);
+    mixer.begin_block(1, playlist.tracks.len(), aux.len());
+    mixer.add(0, Some(0), [0.1, 0.1]);
+    // the track, B's return, and half of what B sent A
+    assert!(close(mixer.end_block(&playlist.tracks, &aux, 1.0)[0], [0.25, 0.25]));
+
+    // instruments send from the voice loop
+    mixer.begin_block(1, playlist.tracks.len(), aux.len());
+    mixer.send(0, &[AuxSend::new(0)], [0.1, 0.1], [0.2, 0.2]);
+    assert!(close(mixer.end_block(&playlist.tracks, &aux, 1.0)[0], [0.1, 0.1]));
+}
+
+#[test]
+fn looping_sends_in_projects_are_dropped_and_reported() {
+    let project: Project = serde_json::from_str(r#"{
+        "version": 1,
+        "bpm": 120,
+        "aux_buses": [
+            { "name": "Reverb", "sends": [{ "bus": 1, "amount": 0.5 }] },
+            { "name": "Delay", "sends": [{ "bus": 0, "amount": 0.5 }] }
+        ]
+    }"#).unwrap();
+
+    let mut session = Session::new();
+    let errors = project.apply(&mut session, &std::env::temp_dir());
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0], Error::Routing(_)));
+    assert!(validate(&session.aux_buses).is_ok());
+    assert_eq!(session.aux_buses[0].sends.len(), 1);
+}
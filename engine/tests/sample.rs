@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use remdaw_engine::sample::load_wav;
+
+/// A path in the temp directory that no other test uses
+fn temp_wav(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("remdaw-{}-{}.wav", name, std::process::id()))
+}
+
+fn spec(channels: u16, bits_per_sample: u16, sample_format: SampleFormat) -> WavSpec {
+    WavSpec { channels, sample_rate: 22050, bits_per_sample, sample_format }
+}
+
+/// Writes integer samples, interleaved, and returns the path
+fn write_int(name: &str, spec: WavSpec, samples: &[i32]) -> PathBuf {
+    let path = temp_wav(name);
+    let mut writer = WavWriter::create(&path, spec).unwrap();
+    for &sample in samples {
+        match spec.bits_per_sample {
+            8 => writer.write_sample(sample as i8).unwrap(),
+            16 => writer.write_sample(sample as i16).unwrap(),
+            _ => writer.write_sample(sample).unwrap(),
+        }
+    }
+    writer.finalize().unwrap();
+    path
+}
+
+#[test]
+fn integer_depths_normalize_to_unit_range() {
+    for bits in [8u16, 16, 24, 32] {
+        let half = 1i64 << (bits - 1);
+        let samples = [(half / 2) as i32, (-half) as i32, 0];
+        let path = write_int(&format!("int{bits}"), spec(1, bits, SampleFormat::Int), &samples);
+
+        let sample = load_wav(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&sample.frames[..], &[[0.5, 0.5], [-1.0, -1.0], [0.0, 0.0]], "{bits} bit");
+        assert_eq!(sample.sample_rate, 22050);
+    }
+}
+
+#[test]
+fn float_files_load_as_is() {
+    let path = temp_wav("float");
+    let mut writer = WavWriter::create(&path, spec(1, 32, SampleFormat::Float)).unwrap();
+    for sample in [0.25f32, -0.75] {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let sample = load_wav(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(&sample.frames[..], &[[0.25, 0.25], [-0.75, -0.75]]);
+}
+
+#[test]
+fn stereo_keeps_its_channels_at_full_length() {
+    let path = write_int("stereo", spec(2, 16, SampleFormat::Int), &[16384, -16384, 0, 8192]);
+
+    let sample = load_wav(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(sample.len(), 2);
+    assert_eq!(&sample.frames[..], &[[0.5, -0.5], [0.0, 0.25]]);
+}
+
+#[test]
+fn extra_channels_fold_into_both_sides() {
+    let path = write_int("quad", spec(4, 16, SampleFormat::Int), &[16384, 0, 8192, 8192]);
+
+    let sample = load_wav(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(&sample.frames[..], &[[0.75, 0.25]]);
+}
+
+#[test]
+fn unreadable_files_are_errors() {
+    assert!(load_wav(&temp_wav("does-not-exist")).is_err());
+
+    let path = temp_wav("garbage");
+    std::fs::write(&path, b"not a wav file").unwrap();
+    let result = load_wav(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+use remdaw_engine::envelope::Envelope;
+use remdaw_engine::project::Project;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::sampler::{PlayMode, SamplerSettings};
+use remdaw_engine::voice::{Sound, Source, StealPolicy, VoicePool};
+use remdaw_engine::{Instrument, Session};
+
+const SOURCE: Source = Source::Instrument(0);
+// a frame a millisecond keeps envelope times readable
+const RATE: u32 = 1000;
+
+/// Ten frames counting up from 0
+fn ramp() -> Sample {
+    Sample::mono((0..10).map(|i| i as f32).collect(), RATE)
+}
+
+/// Starts a note playing `settings`
+fn play(pool: &mut VoicePool) {
+    pool.trigger(SOURCE, 1.0, 1.0, 1, StealPolicy::Oldest).unwrap().key = Some(60);
+}
+
+/// The left channel of the next `frames` frames, stopping early once the voice is gone
+fn render(pool: &mut VoicePool, sample: &Sample, settings: &SamplerSettings, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map_while(|_| (!pool.voices().is_empty()).then(|| pool.next_frame(|_| Some(Sound::Sample(sample, settings)))[0]))
+        .collect()
+}
+
+#[test]
+fn start_and_end_trim_the_sample_and_reverse_plays_it_backwards() {
+    let sample = ramp();
+    let mut settings = SamplerSettings { start: 0.2, end: 0.6, ..SamplerSettings::default() };
+    let mut pool = VoicePool::new(RATE as f32);
+
+    play(&mut pool);
+    assert_eq!(render(&mut pool, &sample, &settings, 20), vec![2.0, 3.0, 4.0, 5.0]);
+    assert!(pool.voices().is_empty());
+
+    settings.reverse = true;
+    play(&mut pool);
+    assert_eq!(render(&mut pool, &sample, &settings, 20), vec![5.0, 4.0, 3.0, 2.0]);
+}
+
+#[test]
+fn loops_hold_the_note_until_its_note_off() {
+    let sample = ramp();
+    let settings = SamplerSettings { looping: true, loop_start: 0.4, loop_end: 0.8, ..SamplerSettings::default() };
+    let mut pool = VoicePool::new(RATE as f32);
+
+    play(&mut pool);
+    let held = render(&mut pool, &sample, &settings, 16);
+    assert_eq!(held, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 4.0, 5.0, 6.0, 7.0, 4.0, 5.0, 6.0, 7.0]);
+
+    // the release takes its 5 ms and keeps looping while it fades, then
+    // the voice goes on the frame it reaches silence
+    pool.release_note(SOURCE, 60, None);
+    let released = render(&mut pool, &sample, &settings, 20);
+    assert!(released.len() <= 6, "{released:?}");
+    assert!(released[0] < 4.0);
+    assert!(pool.voices().is_empty());
+}
+
+#[test]
+fn crossfades_blend_into_the_loop_start() {
+    let sample = ramp();
+    let settings = SamplerSettings {
+        looping: true,
+        loop_start: 0.4,
+        loop_end: 0.8,
+        crossfade: 0.5,
+        ..SamplerSettings::default()
+    };
+    let mut pool = VoicePool::new(RATE as f32);
+
+    // the last two frames of the loop fade towards the two before its start
+    play(&mut pool);
+    let held = render(&mut pool, &sample, &settings, 14);
+    assert_eq!(held, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 5.0, 4.0, 5.0, 6.0, 5.0, 4.0, 5.0]);
+}
+
+#[test]
+fn one_shots_ignore_note_offs_and_loops() {
+    let sample = ramp();
+    let settings = SamplerSettings {
+        mode: PlayMode::OneShot,
+        looping: true,
+        loop_start: 0.4,
+        loop_end: 0.8,
+        ..SamplerSettings::default()
+    };
+    let mut pool = VoicePool::new(RATE as f32);
+
+    play(&mut pool);
+    render(&mut pool, &sample, &settings, 2);
+    pool.release_note(SOURCE, 60, None);
+    let rest = render(&mut pool, &sample, &settings, 20);
+    assert_eq!(rest, vec![2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+}
+
+#[test]
+fn gain_and_attack_shape_the_level() {
+    let sample = Sample::mono(vec![1.0; 10], RATE);
+    let settings = SamplerSettings {
+        gain: 0.5,
+        envelope: Envelope { attack: 0.004, ..Envelope::FLAT },
+        ..SamplerSettings::default()
+    };
+    let mut pool = VoicePool::new(RATE as f32);
+
+    play(&mut pool);
+    assert_eq!(render(&mut pool, &sample, &settings, 5), vec![0.125, 0.25, 0.375, 0.5, 0.5]);
+}
+
+#[test]
+fn sampler_settings_round_trip_through_projects() {
+    let mut session = Session::new();
+    let mut instrument = Instrument::new(PathBuf::from("missing.wav"), Sample::default());
+    instrument.sampler = SamplerSettings {
+        start: 0.1,
+        reverse: true,
+        looping: true,
+        crossfade: 0.2,
+        mode: PlayMode::OneShot,
+        ..SamplerSettings::default()
+    };
+    session.add_instrument(instrument);
+
+    let json = serde_json::to_string(&Project::from_session(&session, &std::env::temp_dir())).unwrap();
+    let mut loaded = Session::new();
+    serde_json::from_str::<Project>(&json).unwrap().apply(&mut loaded, &std::env::temp_dir());
+    assert_eq!(loaded.instruments[0].sampler, session.instruments[0].sampler);
+
+    // projects from before sampler settings play samples whole
+    let json = json.replace("\"sampler\"", "\"ignored\"");
+    serde_json::from_str::<Project>(&json).unwrap().apply(&mut loaded, &std::env::temp_dir());
+    assert_eq!(loaded.instruments[0].sampler, SamplerSettings::default());
+}
@@ -0,0 +1,324 @@
+use remdaw_engine::groove::Groove;
+use remdaw_engine::sequencer::{schedule_block, Block, Event, EventKind, MAX_EVENTS};
+use remdaw_engine::{ClipType, Error, Pattern, PlacedClip, Step, StepResolution, TimeSignature};
+
+// 120 bpm at 8 kHz: 4000 frames per beat, 1000 per step
+const SAMPLES_PER_BEAT: f64 = 4000.0;
+
+fn pattern_clip(start_time: f64, length: f64) -> PlacedClip {
+    PlacedClip {
+        clip_type: ClipType::Pattern(0),
+        name: "Pattern 1".to_string(),
+        track_index: 0,
+        start_time,
+        length,
+        color: [0, 0, 0, 255],
+        offset: 0.0,
+    }
+}
+
+/// One instrument with the given steps active
+fn pattern(steps: &[usize]) -> Pattern {
+    let mut pattern = Pattern::empty("Pattern 1".to_string(), 1);
+    for &step in steps {
+        pattern.data[0][step] = Step::ON;
+    }
+    pattern
+}
+
+/// Schedules `total` frames split into buffers of `block_size`, the way the
+/// audio callback would, and returns the absolute frame of every event
+fn schedule_in_blocks(
+    clips: &[PlacedClip],
+    patterns: &[Pattern],
+    total: usize,
+    block_size: usize,
+    metronome: Option<f64>,
+) -> Vec<(usize, EventKind)> {
+    let mut events = Vec::with_capacity(MAX_EVENTS);
+    let mut hits = Vec::new();
+    let mut position = 0.0;
+    let mut frame = 0;
+
+    while frame < total {
+        let frames = block_size.min(total - frame);
+        let block = Block::new(position, frames, SAMPLES_PER_BEAT);
+        schedule_block(clips, patterns, &[], &block, 0.0, metronome, &mut events);
+        hits.extend(events.iter().map(|event| (frame + event.offset, event.kind)));
+        position = block.end;
+        frame += frames;
+    }
+    hits
+}
+
+#[test]
+fn steps_land_on_exact_offsets() {
+    let clips = [pattern_clip(0.0, 4.0)];
+    let patterns = [pattern(&[0, 1, 6])];
+    let block = Block::new(0.0, 8000, SAMPLES_PER_BEAT);
+    let mut events = Vec::with_capacity(MAX_EVENTS);
+
+    schedule_block(&clips, &patterns, &[], &block, 0.0, None, &mut events);
+    assert_eq!(events, vec![
+        Event { offset: 0, kind: EventKind::Step(0, Step::ON, 0.25), track: Some(0) },
+        Event { offset: 1000, kind: EventKind::Step(0, Step::ON, 0.25), track: Some(0) },
+        Event { offset: 6000, kind: EventKind::Step(0, Step::ON, 0.25), track: Some(0) },
+    ]);
+}
+
+#[test]
+fn block_size_does_not_change_the_schedule() {
+    let clips = [pattern_clip(0.0, 4.0), pattern_clip(5.3, 2.0)];
+    let patterns = [pattern(&[0, 3, 4, 5, 15])];
+    let whole = schedule_in_blocks(&clips, &patterns, 32000, 32000, Some(1.0));
+
+    for block_size in [1, 7, 64, 333, 999, 1000, 1024] {
+        assert_eq!(schedule_in_blocks(&clips, &patterns, 32000, block_size, Some(1.0)), whole, "block size {block_size}");
+    }
+}
+
+#[test]
+fn clip_start_fires_once() {
+    let clips = [pattern_clip(1.0, 1.0)];
+    let patterns = [pattern(&[0])];
+
+    // the clip starts exactly on the boundary between two blocks
+    let hits = schedule_in_blocks(&clips, &patterns, 12000, 4000, None);
+    assert_eq!(hits, vec![(4000, EventKind::Step(0, Step::ON, 0.25))]);
+}
+
+#[test]
+fn clip_starting_between_frames_fires_on_the_frame_containing_it() {
+    let start = 1000.5 / SAMPLES_PER_BEAT;
+    let clips = [pattern_clip(start, 1.0)];
+    let patterns = [pattern(&[0])];
+
+    for block_size in [1, 1000, 1001, 4000] {
+        let hits = schedule_in_blocks(&clips, &patterns, 4000, block_size, None);
+        assert_eq!(hits, vec![(1000, EventKind::Step(0, Step::ON, 0.25))], "block size {block_size}");
+    }
+}
+
+#[test]
+fn adjacent_clips_share_no_steps() {
+    let clips = [pattern_clip(0.0, 4.0), pattern_clip(4.0, 4.0)];
+    let patterns = [pattern(&[0])];
+
+    let hits = schedule_in_blocks(&clips, &patterns, 32000, 512, None);
+    assert_eq!(hits, vec![(0, EventKind::Step(0, Step::ON, 0.25)), (16000, EventKind::Step(0, Step::ON, 0.25))]);
+}
+
+#[test]
+fn pattern_loops_inside_a_long_clip() {
+    let clips = [pattern_clip(0.0, 8.0)];
+    let patterns = [pattern(&[0, 15])];
+
+    let hits = schedule_in_blocks(&clips, &patterns, 40000, 256, None);
+    assert_eq!(hits, vec![
+        (0, EventKind::Step(0, Step::ON, 0.25)),
+        (15000, EventKind::Step(0, Step::ON, 0.25)),
+        (16000, EventKind::Step(0, Step::ON, 0.25)),
+        (31000, EventKind::Step(0, Step::ON, 0.25)),
+    ]);
+}
+
+#[test]
+fn clip_end_is_exclusive() {
+    // a two step clip only plays the first two steps of its pattern
+    let clips = [pattern_clip(0.0, 0.5)];
+    let patterns = [pattern(&[0, 1, 2])];
+
+    let hits = schedule_in_blocks(&clips, &patterns, 8000, 100, None);
+    assert_eq!(hits, vec![(0, EventKind::Step(0, Step::ON, 0.25)), (1000, EventKind::Step(0, Step::ON, 0.25))]);
+}
+
+#[test]
+fn tempo_change_between_blocks_keeps_every_step() {
+    let clips = [pattern_clip(0.0, 4.0)];
+    let patterns = [pattern(&(0..16).collect::<Vec<_>>())];
+    let mut events = Vec::with_capacity(MAX_EVENTS);
+    let mut steps = 0;
+
+    // one beat at 120 bpm, then the rest at 90 bpm in odd sized buffers
+    let first = Block::new(0.0, 4000, SAMPLES_PER_BEAT);
+    schedule_block(&clips, &patterns, &[], &first, 0.0, None, &mut events);
+    steps += events.len();
+    assert_eq!(events.last().map(|event| event.offset), Some(3000));
+
+    let slower = SAMPLES_PER_BEAT * 120.0 / 90.0;
+    let mut position = first.end;
+    while position < 4.0 {
+        let block = Block::new(position, 777, slower);
+        schedule_block(&clips, &patterns, &[], &block, 0.0, None, &mut events);
+        steps += events.len();
+        position = block.end;
+    }
+    assert_eq!(steps, 16);
+}
+
+#[test]
+fn metronome_clicks_on_every_beat() {
+    let hits = schedule_in_blocks(&[], &[], 12001, 100, Some(1.0));
+    assert_eq!(hits, vec![
+        (0, EventKind::Click),
+        (4000, EventKind::Click),
+        (8000, EventKind::Click),
+        (12000, EventKind::Click),
+    ]);
+}
+
+#[test]
+fn patterns_loop_at_their_own_length() {
+    // 12 steps of 1/8 triplets is one bar of 4/4 in triplets
+    let mut triplets = pattern(&[0, 11]);
+    triplets.resolution = StepResolution::EighthTriplet;
+    triplets.set_steps(12);
+    assert_eq!(triplets.length(), 4.0);
+
+    let clips = [pattern_clip(0.0, 5.0)];
+    let hits = schedule_in_blocks(&clips, &[triplets], 20000, 300, None);
+    let frames: Vec<usize> = hits.iter().map(|(frame, _)| *frame).collect();
+    // a triplet step is a third of a beat, so step 11 lands 44000 / 3 frames in
+    assert_eq!(frames, vec![0, 14666, 16000]);
+
+    // five 16ths repeat every 1.25 beats, against the bar
+    let mut fives = pattern(&[0]);
+    fives.set_steps(5);
+    let hits = schedule_in_blocks(&[pattern_clip(0.0, 4.0)], &[fives], 16000, 512, None);
+    let frames: Vec<usize> = hits.iter().map(|(frame, _)| *frame).collect();
+    assert_eq!(frames, vec![0, 5000, 10000, 15000]);
+}
+
+#[test]
+fn shrinking_a_pattern_keeps_the_steps_that_fit() {
+    let mut shrunk = pattern(&[0, 6, 15]);
+    shrunk.set_steps(7);
+    assert_eq!(shrunk.data[0].len(), 7);
+    assert!(shrunk.data[0][6].active);
+
+    shrunk.set_steps(10);
+    assert_eq!(shrunk.data[0].len(), 10);
+    assert!(!shrunk.data[0][9].active);
+
+    // never empty, so there's always a step to loop on
+    shrunk.set_steps(0);
+    assert_eq!(shrunk.steps, 1);
+}
+
+#[test]
+fn metronome_follows_the_time_signature() {
+    let seven_eight = TimeSignature::new(7, 8);
+    assert_eq!(seven_eight.bar_length(), 3.5);
+
+    // 7/8 clicks on every eighth note
+    let hits = schedule_in_blocks(&[], &[], 6001, 333, Some(seven_eight.beat_length()));
+    let frames: Vec<usize> = hits.iter().map(|(frame, _)| *frame).collect();
+    assert_eq!(frames, vec![0, 2000, 4000, 6000]);
+}
+
+/// Frames of every step in one beat of 16ths, with the given swing
+fn swung(pattern: &Pattern, swing: f32, block_size: usize) -> Vec<usize> {
+    swung_clip(pattern_clip(0.0, 1.0), pattern, swing, block_size)
+}
+
+/// Frames of every step `clip` plays in the first beat, with the given swing
+fn swung_clip(clip: PlacedClip, pattern: &Pattern, swing: f32, block_size: usize) -> Vec<usize> {
+    let clips = [clip];
+    let mut events = Vec::with_capacity(MAX_EVENTS);
+    let mut frames = Vec::new();
+    let mut position = 0.0;
+    while position < 1.0 {
+        let block = Block::new(position, block_size, SAMPLES_PER_BEAT);
+        schedule_block(&clips, std::slice::from_ref(pattern), &[], &block, swing, None, &mut events);
+        let start = (position * SAMPLES_PER_BEAT).round() as usize;
+        frames.extend(events.iter().map(|event| start + event.offset));
+        position = block.end;
+    }
+    frames
+}
+
+#[test]
+fn swing_delays_every_second_step() {
+    let mut four = pattern(&[0, 1, 2, 3]);
+    assert_eq!(swung(&four, 0.0, 4000), vec![0, 1000, 2000, 3000]);
+    // full swing puts the off-beat three quarters through the pair
+    assert_eq!(swung(&four, 1.0, 4000), vec![0, 1500, 2000, 3500]);
+
+    // a pattern's own swing wins over the session's
+    four.swing = Some(0.5);
+    assert_eq!(swung(&four, 1.0, 4000), vec![0, 1250, 2000, 3250]);
+}
+
+#[test]
+fn steps_swung_past_the_clip_end_are_skipped() {
+    // the clip ends between the second step and where swing moves it to
+    let two = pattern(&[0, 1]);
+    for block_size in [1, 1000, 4000] {
+        assert_eq!(swung_clip(pattern_clip(0.0, 0.3), &two, 1.0, block_size), vec![0], "block size {block_size}");
+    }
+}
+
+#[test]
+fn grooves_shift_timing_and_scale_velocity() {
+    let mut grooved = pattern(&[0, 1, 2, 3]);
+    grooved.data[0][0].velocity = 0.8;
+    grooved.groove = Some(Groove {
+        name: "Test".to_string(),
+        timing: vec![-0.2, 0.1],
+        velocity: vec![0.5, 1.0, 2.0],
+    });
+
+    // the first step can't move before its clip
+    let whole = swung(&grooved, 0.0, 4000);
+    assert_eq!(whole, vec![0, 1100, 1800, 3100]);
+    for block_size in [1, 99, 1000, 1024] {
+        assert_eq!(swung(&grooved, 0.0, block_size), whole, "block size {block_size}");
+    }
+
+    let clips = [pattern_clip(0.0, 1.0)];
+    let mut events = Vec::with_capacity(MAX_EVENTS);
+    schedule_block(&clips, &[grooved], &[], &Block::new(0.0, 4000, SAMPLES_PER_BEAT), 0.0, None, &mut events);
+    let velocities: Vec<f32> = events.iter()
+        .map(|event| match event.kind {
+            EventKind::Step(_, step, _) => step.velocity,
+            _ => 0.0,
+        })
+        .collect();
+    // accents never go past full velocity
+    assert_eq!(velocities, vec![0.4, 1.0, 1.0, 0.5]);
+
+    // templates round trip through their files
+    let path = std::env::temp_dir().join(format!("remdaw-groove-{}.groove", std::process::id()));
+    let preset = Groove::presets().remove(0);
+    std::fs::write(&path, serde_json::to_string(&preset).unwrap()).unwrap();
+    let loaded = Groove::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), preset);
+    assert!(matches!(Groove::load(&path), Err(Error::Groove { .. })));
+}
+
+#[test]
+fn crowded_blocks_drop_events_instead_of_growing_the_buffer() {
+    let clips = [pattern_clip(0.0, 4.0)];
+    let mut crowded = Pattern::empty("Pattern 1".to_string(), 8);
+    for row in &mut crowded.data {
+        row[0] = Step::ON;
+    }
+    let block = Block::new(0.0, 1000, SAMPLES_PER_BEAT);
+    let mut events = Vec::with_capacity(5);
+    let capacity = events.capacity();
+
+    schedule_block(&clips, &[crowded], &[], &block, 0.0, Some(1.0), &mut events);
+    assert_eq!(events.len(), capacity);
+    assert_eq!(events.capacity(), capacity);
+}
+
+#[test]
+fn steps_last_one_step_of_their_pattern() {
+    for resolution in StepResolution::ALL {
+        let mut patterns = [pattern(&[0])];
+        patterns[0].resolution = resolution;
+        let hits = schedule_in_blocks(&[pattern_clip(0.0, 1.0)], &patterns, 1000, 1000, None);
+        assert_eq!(hits, vec![(0, EventKind::Step(0, Step::ON, 1.0 / resolution.steps_per_beat()))], "{resolution:?}");
+    }
+}
@@ -0,0 +1,150 @@
+use remdaw_engine::envelope::Envelope;
+use remdaw_engine::project::Project;
+use remdaw_engine::synth::{key_frequency, Oscillator, SynthPatch, SynthVoice, Waveform};
+use remdaw_engine::{ClipType, Command, Engine, Generator, Instrument, Pattern, PlacedClip, Session, Step, StepResolution};
+
+const SAMPLE_RATE: f32 = 48000.0;
+
+/// A bare sine through a wide open filter, with an envelope of `attack` and
+/// `release` seconds and full sustain
+fn sine_patch(attack: f32, release: f32) -> SynthPatch {
+    let mut patch = SynthPatch {
+        cutoff: 20000.0,
+        envelope_amount: 0.0,
+        amp_envelope: Envelope { attack, decay: 0.0, sustain: 1.0, release },
+        ..SynthPatch::default()
+    };
+    patch.oscillators = [
+        Oscillator { waveform: Waveform::Sine, level: 1.0, octave: 0, detune: 0.0 },
+        Oscillator { level: 0.0, ..Oscillator::default() },
+    ];
+    patch
+}
+
+fn render(voice: &mut SynthVoice, patch: &SynthPatch, frames: usize) -> Vec<f32> {
+    (0..frames).map_while(|_| voice.next(patch, SAMPLE_RATE)).collect()
+}
+
+#[test]
+fn keys_map_to_equal_tempered_frequencies() {
+    assert_eq!(key_frequency(69), 440.0);
+    assert!((key_frequency(57) - 220.0).abs() < 1e-3);
+    assert!((key_frequency(60) - 261.626).abs() < 1e-2);
+}
+
+#[test]
+fn the_envelope_rises_holds_and_releases() {
+    let patch = sine_patch(0.01, 0.01);
+    let mut voice = SynthVoice::new(1000.0, None);
+
+    // a tenth of a second of 1 kHz: 100 cycles at full level once the attack is over
+    let held = render(&mut voice, &patch, 4800);
+    assert_eq!(held.len(), 4800);
+    let peak = |frames: &[f32]| frames.iter().fold(0.0f32, |peak, frame| peak.max(frame.abs()));
+    assert!(peak(&held[..48]) < 0.2, "still attacking");
+    assert!((peak(&held[2400..]) - 1.0).abs() < 0.02);
+    let crossings = held.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0).count();
+    assert!((99..=101).contains(&crossings), "{crossings} cycles");
+
+    // the release takes its 480 frames, then the voice is done
+    voice.release();
+    let released = render(&mut voice, &patch, 4800);
+    assert!((479..=482).contains(&released.len()), "{} frames", released.len());
+    assert!(voice.next(&patch, SAMPLE_RATE).is_none());
+}
+
+#[test]
+fn held_hits_let_go_by_themselves() {
+    let patch = sine_patch(0.0, 0.0);
+    let mut voice = SynthVoice::new(440.0, Some(100));
+    let frames = render(&mut voice, &patch, 1000);
+    assert!((100..=102).contains(&frames.len()), "{} frames", frames.len());
+}
+
+#[test]
+fn the_filter_darkens_a_saw() {
+    let mut patch = sine_patch(0.0, 0.1);
+    patch.oscillators[0].waveform = Waveform::Saw;
+    let energy = |patch: &SynthPatch| {
+        let mut voice = SynthVoice::new(1000.0, None);
+        render(&mut voice, patch, 4800)[2400..].iter().map(|frame| frame * frame).sum::<f32>()
+    };
+    let open = energy(&patch);
+    patch.cutoff = 200.0;
+    assert!(energy(&patch) < open * 0.1);
+}
+
+#[test]
+fn the_engine_plays_synths_from_notes_and_follows_patch_changes() {
+    let mut engine = Engine::new(SAMPLE_RATE);
+    let idx = engine.session.add_instrument(Instrument::synth("Synth 1".to_string()));
+    engine.apply(Command::SetSynth(idx, sine_patch(0.0, 0.01)));
+
+    engine.apply(Command::TriggerNote { instrument: idx, key: 69 });
+    let mut out = vec![0.0; 480 * 2];
+    engine.render(&mut out);
+    assert_eq!(engine.voices.voices().len(), 1);
+    assert!(out.iter().any(|sample| sample.abs() > 0.5));
+
+    // the note rings on until it is released, then fades over the release
+    engine.render(&mut out);
+    assert_eq!(engine.voices.voices().len(), 1);
+    engine.apply(Command::ReleaseNote { instrument: idx, key: 69 });
+    engine.render(&mut out);
+    engine.render(&mut out);
+    assert!(engine.voices.voices().is_empty());
+    assert!(out[out.len() - 100..].iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn pattern_steps_gate_synths_for_one_step() {
+    // 24000 frames per beat at 120 bpm
+    for (resolution, gate) in [(StepResolution::Eighth, 12000), (StepResolution::EighthTriplet, 8000), (StepResolution::ThirtySecond, 3000)] {
+        let mut engine = Engine::new(SAMPLE_RATE);
+        engine.set_bpm(120);
+        let idx = engine.session.add_instrument(Instrument::synth("Synth 1".to_string()));
+        engine.apply(Command::SetSynth(idx, sine_patch(0.0, 0.001)));
+        let mut pattern = Pattern::empty("Pattern 1".to_string(), 1);
+        pattern.resolution = resolution;
+        pattern.data[0][0] = Step::ON;
+        engine.session.patterns.push(pattern);
+        engine.session.playlist.clips.push(PlacedClip {
+            clip_type: ClipType::Pattern(0),
+            name: "Pattern 1".to_string(),
+            track_index: 0,
+            start_time: 0.0,
+            length: 1.0 / resolution.steps_per_beat(),
+            color: [0, 0, 0, 255],
+            offset: 0.0,
+        });
+        engine.play();
+
+        // held for the whole step, then gone once the 48 frame release is over
+        let mut out = vec![0.0; 100 * 2];
+        let mut frames = 0;
+        while !engine.voices.voices().is_empty() || frames == 0 {
+            engine.render(&mut out);
+            frames += 100;
+        }
+        assert!(frames > gate && frames <= gate + 200, "{resolution:?}: {frames}");
+    }
+}
+
+#[test]
+fn synths_round_trip_through_projects_without_a_sample() {
+    let mut session = Session::new();
+    let mut synth = Instrument::synth("Bass".to_string());
+    let patch = sine_patch(0.2, 0.3);
+    synth.generator = Generator::Synth(patch);
+    session.add_instrument(synth);
+
+    let json = serde_json::to_string(&Project::from_session(&session, &std::env::temp_dir())).unwrap();
+    let mut loaded = Session::new();
+    let errors = serde_json::from_str::<Project>(&json).unwrap().apply(&mut loaded, &std::env::temp_dir());
+
+    // nothing to load, so nothing fails
+    assert!(errors.is_empty());
+    assert_eq!(loaded.instruments[0].name, "Bass");
+    assert_eq!(loaded.instruments[0].generator, Generator::Synth(patch));
+    assert!(loaded.instruments[0].sample.is_empty());
+}
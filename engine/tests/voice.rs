@@ -0,0 +1,130 @@
+use remdaw_engine::resample::Interpolation;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::voice::{Source, StealPolicy, VoicePool, MAX_VOICES};
+
+const KICK: Source = Source::Instrument(0);
+const RATE: u32 = 44100;
+
+/// Renders `frames` frames of a pool playing a single mono sample and
+/// returns the left channel
+fn render(pool: &mut VoicePool, sample: &[f32], frames: usize) -> Vec<f32> {
+    let stereo = Sample::mono(sample.to_vec(), RATE);
+    (0..frames)
+        .map(|_| pool.next_frame(|_| Some(&stereo)))
+        .inspect(|frame| assert_eq!(frame[0], frame[1], "channels should match"))
+        .map(|frame| frame[0])
+        .collect()
+}
+
+#[test]
+fn retriggered_hits_overlap() {
+    let sample = [1.0; 8];
+    let mut pool = VoicePool::new(RATE as f32);
+
+    pool.trigger(KICK, 1.0, 1.0, 4, StealPolicy::Oldest);
+    let first = render(&mut pool, &sample, 4);
+    pool.trigger(KICK, 1.0, 1.0, 4, StealPolicy::Oldest);
+    let second = render(&mut pool, &sample, 8);
+
+    assert_eq!(first, vec![1.0; 4]);
+    assert_eq!(second, vec![2.0, 2.0, 2.0, 2.0, 1.0, 1.0, 1.0, 1.0]);
+    assert!(pool.voices().is_empty());
+}
+
+#[test]
+fn stolen_voice_fades_out_instead_of_cutting() {
+    let sample = [1.0; 1000];
+    let mut pool = VoicePool::new(RATE as f32);
+
+    pool.trigger(KICK, 1.0, 1.0, 1, StealPolicy::Oldest);
+    render(&mut pool, &sample, 10);
+    pool.trigger(KICK, 1.0, 1.0, 1, StealPolicy::Oldest);
+    assert_eq!(pool.voices().len(), 2);
+
+    let out = render(&mut pool, &sample, 100);
+    // the old voice ramps down under the new one, one step per frame
+    assert_eq!(out[0], 2.0);
+    assert!(out.windows(2).take(63).all(|pair| pair[1] < pair[0]));
+    assert!(out[64..].iter().all(|&s| s == 1.0));
+    assert_eq!(pool.voices().len(), 1);
+}
+
+#[test]
+fn no_steal_policy_drops_the_new_hit() {
+    let mut pool = VoicePool::new(RATE as f32);
+
+    pool.trigger(KICK, 1.0, 1.0, 1, StealPolicy::None);
+    pool.trigger(KICK, 1.0, 1.0, 1, StealPolicy::None);
+    assert_eq!(pool.voices().len(), 1);
+}
+
+#[test]
+fn quietest_policy_steals_the_lowest_gain() {
+    let mut pool = VoicePool::new(RATE as f32);
+
+    pool.trigger(KICK, 1.0, 1.0, 2, StealPolicy::Quietest);
+    pool.trigger(KICK, 0.25, 1.0, 2, StealPolicy::Quietest);
+    pool.trigger(KICK, 0.5, 1.0, 2, StealPolicy::Quietest);
+
+    let gains: Vec<f32> = pool.voices().iter().map(|voice| voice.gain).collect();
+    assert_eq!(gains, vec![1.0, 0.25, 0.5]);
+    assert!(pool.is_sounding(KICK));
+
+    // the stolen quiet voice is the only one that drops out after the fade
+    render(&mut pool, &[1.0; 1000], 64);
+    let mut gains: Vec<f32> = pool.voices().iter().map(|voice| voice.gain).collect();
+    gains.sort_by(f32::total_cmp);
+    assert_eq!(gains, vec![0.5, 1.0]);
+}
+
+#[test]
+fn polyphony_is_per_source() {
+    let mut pool = VoicePool::new(RATE as f32);
+
+    pool.trigger(KICK, 1.0, 1.0, 1, StealPolicy::None);
+    pool.trigger(Source::Instrument(1), 1.0, 1.0, 1, StealPolicy::None);
+    pool.trigger(Source::Metronome, 1.0, 1.0, 1, StealPolicy::None);
+    assert_eq!(pool.voices().len(), 3);
+}
+
+#[test]
+fn pitch_changes_playback_rate() {
+    let sample = [0.0, 1.0, 2.0, 3.0];
+    let mut pool = VoicePool::new(RATE as f32);
+    pool.interpolation = Interpolation::Linear;
+
+    pool.trigger(KICK, 1.0, 2.0, 1, StealPolicy::Oldest);
+    assert_eq!(render(&mut pool, &sample, 3), vec![0.0, 2.0, 0.0]);
+
+    pool.trigger(KICK, 1.0, 0.5, 1, StealPolicy::Oldest);
+    assert_eq!(render(&mut pool, &sample, 4), vec![0.0, 0.5, 1.0, 1.5]);
+}
+
+#[test]
+fn pool_never_grows_past_its_capacity() {
+    let mut pool = VoicePool::new(RATE as f32);
+
+    for i in 0..MAX_VOICES * 2 {
+        pool.trigger(Source::Instrument(i), 1.0, 1.0, 1, StealPolicy::Oldest);
+    }
+    assert_eq!(pool.voices().len(), MAX_VOICES);
+}
+
+#[test]
+fn voices_of_missing_sources_are_freed() {
+    let mut pool = VoicePool::new(RATE as f32);
+
+    pool.trigger(KICK, 1.0, 1.0, 1, StealPolicy::Oldest);
+    assert_eq!(pool.next_frame(|_| None::<&Sample>), [0.0, 0.0]);
+    assert!(pool.voices().is_empty());
+}
+
+#[test]
+fn stereo_sources_keep_their_channels() {
+    let sample = Sample::stereo(vec![[1.0, -1.0], [0.5, 0.25]], RATE);
+    let mut pool = VoicePool::new(RATE as f32);
+
+    pool.trigger(KICK, 0.5, 1.0, 1, StealPolicy::Oldest);
+    assert_eq!(pool.next_frame(|_| Some(&sample)), [0.5, -0.5]);
+    assert_eq!(pool.next_frame(|_| Some(&sample)), [0.25, 0.125]);
+}
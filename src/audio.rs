@@ -1,260 +1,325 @@
-use crate::models::{AudioState};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{SampleFormat, Stream};
-use hound;
-use std::sync::{Arc, Mutex};
+use cpal::{BufferSize, Device, FromSample, Host, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig, SupportedBufferSize, SupportedStreamConfig};
+use remdaw_engine::decoder::load_sample;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{Engine, EngineHandle, Error, Instrument, Pattern, Session};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Samples loaded into the channel rack on startup
+const DEFAULT_INSTRUMENTS: [&str; 3] = ["test_instruments/cowbell.wav", "test_instruments/Clap Dance.wav", "test_instruments/St 808.wav"];
+
+/// Sample played by the metronome on every beat
+const METRONOME_SAMPLE: &str = "test_instruments/St 808.wav";
+
+/// Rates offered in the settings, filtered by what the device supports
+const COMMON_SAMPLE_RATES: [u32; 6] = [22050, 44100, 48000, 88200, 96000, 192000];
+
+/// Buffer sizes offered in the settings, in frames
+const COMMON_BUFFER_SIZES: [u32; 7] = [64, 128, 256, 512, 1024, 2048, 4096];
+
+/// Rate and buffer size of the null output when none are configured
+const NULL_SAMPLE_RATE: u32 = 44100;
+const NULL_BUFFER_SIZE: u32 = 512;
+
+/// Samples the engine renders at a time before they are converted to the
+/// device's format. Larger callbacks are rendered in several passes.
+const SCRATCH_SAMPLES: usize = 8192;
+
+/// Which output the engine plays through
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum OutputDevice {
+    /// Whatever the host reports as its default output
+    Default,
+    /// A device picked by name
+    Named(String),
+    /// No sound hardware: the engine runs on a timer and the audio is discarded
+    Null,
+}
+
+/// The user's output choice, saved in the app config
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub host: Option<String>,      // None = the platform's default host
+    pub device: OutputDevice,
+    pub sample_rate: Option<u32>,  // None = the device's default rate
+    pub buffer_size: Option<u32>,  // in frames. None = let the driver decide
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            host: None,
+            device: OutputDevice::Default,
+            sample_rate: None,
+            buffer_size: None,
+        }
+    }
+}
+
+/// A running output. Dropping it stops the engine.
+pub enum Output {
+    Device(Stream),
+    Null(NullOutput),
+}
+
+/// Drives the engine from a thread at the configured rate and throws the
+/// audio away, so the transport keeps moving without sound hardware
+pub struct NullOutput {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for NullOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// What the settings window can offer for the current host and device
+#[derive(Default)]
+pub struct DeviceList {
+    pub hosts: Vec<String>,
+    pub devices: Vec<String>,
+    pub sample_rates: Vec<u32>,
+    pub buffer_sizes: Vec<u32>,
+}
+
+/// Lists hosts, output devices, and the rates and buffer sizes the chosen device supports
+pub fn list_devices(settings: &AudioSettings) -> DeviceList {
+    let hosts = cpal::available_hosts().iter().map(|id| id.name().to_string()).collect();
 
-/// Loads a WAV file from disk and converts it to a vector of f32 samples
-/// normalized to the range [-1.0, 1.0]
+    let Ok(host) = find_host(settings.host.as_deref()) else {
+        return DeviceList { hosts, ..Default::default() };
+    };
+    let devices = host
+        .output_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default();
+
+    let mut list = DeviceList {
+        hosts,
+        devices,
+        sample_rates: COMMON_SAMPLE_RATES.to_vec(),
+        buffer_sizes: COMMON_BUFFER_SIZES.to_vec(),
+    };
+    if settings.device == OutputDevice::Null {
+        return list;
+    }
+
+    let ranges: Vec<_> = find_device(&host, &settings.device)
+        .ok()
+        .and_then(|device| device.supported_output_configs().ok())
+        .map(|configs| configs.collect())
+        .unwrap_or_default();
+
+    list.sample_rates.retain(|&rate| {
+        ranges.iter().any(|r| (r.min_sample_rate().0..=r.max_sample_rate().0).contains(&rate))
+    });
+    list.buffer_sizes.retain(|&frames| {
+        ranges.iter().any(|r| match *r.buffer_size() {
+            SupportedBufferSize::Range { min, max } => (min..=max).contains(&frames),
+            SupportedBufferSize::Unknown => true,
+        })
+    });
+    list
+}
+
+/// Initializes the audio system by setting up the output device and audio stream.
+/// The engine is moved into the audio callback; the UI controls it through the handle.
 ///
 /// # Arguments
-/// * `instrument_path` - File path to the WAV file
+/// * `session` - The song the engine starts with
+/// * `metronome` - Sample played on every beat when the metronome is on
+/// * `settings` - Which host, device, rate and buffer size to open
 ///
 /// # Returns
-/// * `Vec<f32>` - Vector of normalized audio samples
-pub fn path_to_vector(instrument_path: &str) -> Vec<f32> {
-    // Open the WAV file using the hound library
-    let mut reader = match hound::WavReader::open(instrument_path) {
-        Err(err) => panic!("{}", err),
-        Ok(result) => result,
-    };
+/// * `Result<(Output, EngineHandle), Error>` - The running output and the handle for sending commands
+pub fn init(session: &Session, metronome: Sample, settings: &AudioSettings) -> Result<(Output, EngineHandle), Error> {
+    if settings.device == OutputDevice::Null {
+        return Ok(null_output(session, metronome, settings));
+    }
 
-    // Read all samples as i16 (16-bit audio)
-    let samples = reader.samples::<i16>();
+    // Get the audio host (OS audio system)
+    let host = find_host(settings.host.as_deref())?;
 
-    // Convert i16 samples to f32 normalized values
-    let vector: Vec<f32> = samples
-        .map(|result| result.unwrap()) // Unwrap each Result<i16>
-        .map(|i16_value| i16_value as f32 / i16::MAX as f32) // Normalize to [-1.0, 1.0]
-        .collect();
-    vector
-}
+    // Get the output device (speakers/headphones)
+    let device = find_device(&host, &settings.device)?;
 
-/// Initializes the audio system by setting up the output device and audio stream
-///
-/// # Returns
-/// * `(Stream, Arc<Mutex<AudioState>>)` - The audio stream and shared audio state
-pub fn init() -> (Stream, Arc<Mutex<AudioState>>) {
-    // Get the default audio host (OS audio system)
-    let host = cpal::default_host();
-
-    // Get the default output device (speakers/headphones)
-    let device = host
-        .default_output_device()
-        .expect("no output device available");
-
-    // Get the default audio configuration for this device
-    let supported_config = device
-        .default_output_config()
-        .expect("error getting default config");
+    // Get a configuration for this device at the chosen rate
+    let supported_config = output_config(&device, settings.sample_rate)?;
 
-    let config = supported_config.config();
+    let mut config = supported_config.config();
+    if let Some(frames) = settings.buffer_size {
+        config.buffer_size = BufferSize::Fixed(frames);
+    }
     let sample_format = supported_config.sample_format();
     let sample_rate = config.sample_rate.0 as f32; // e.g., 48000 Hz
 
-    // Create shared audio state with the device's sample rate
-    let audio_state = Arc::new(Mutex::new(AudioState::new(sample_rate)));
-    let audio_state_clone = audio_state.clone();
-
-    // Error callback for the audio stream
-    let err_fn = |err| eprintln!("error: {}", err);
+    // Create the audio engine with the device's sample rate
+    let mut engine = new_engine(session, metronome, sample_rate);
+    let handle = engine.connect();
 
-    // Build the output stream with F32 sample format
+    // Build the output stream in whatever format the device wants
     let stream = match sample_format {
-        SampleFormat::F32 => device.build_output_stream(
-            &config,
-            move |data: &mut [f32], _| play_instrument(data, &audio_state_clone),
-            err_fn,
-            None,
-        ),
-        _ => panic!("Unsupported format"),
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, engine),
+        SampleFormat::F64 => build_stream::<f64>(&device, &config, engine),
+        SampleFormat::I8 => build_stream::<i8>(&device, &config, engine),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, engine),
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, engine),
+        SampleFormat::I64 => build_stream::<i64>(&device, &config, engine),
+        SampleFormat::U8 => build_stream::<u8>(&device, &config, engine),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, engine),
+        SampleFormat::U32 => build_stream::<u32>(&device, &config, engine),
+        SampleFormat::U64 => build_stream::<u64>(&device, &config, engine),
+        format => return Err(Error::device(format!("unsupported sample format {}", format))),
     }
-        .unwrap();
+        .map_err(|err| Error::device(format!("could not open output stream: {}", err)))?;
 
     // Start the audio stream
-    stream.play().unwrap();
-    (stream, audio_state)
+    stream.play().map_err(|err| Error::device(format!("could not start output stream: {}", err)))?;
+    Ok((Output::Device(stream), handle))
 }
 
-/// Audio callback function that gets called continuously by the audio system
-/// Fills the output buffer with mixed audio from all active sources
-///
-/// # Arguments
-/// * `data` - Output buffer to fill with audio samples (stereo interleaved)
-/// * `state` - Shared audio state containing all instruments, patterns, and playback info
-fn play_instrument(data: &mut [f32], state: &Arc<Mutex<AudioState>>) {
-    // Lock the audio state for this callback
-    let mut state = state.lock().unwrap();
-
-    // Stereo audio (left and right channels)
-    let channels = 2;
-
-    // Process each stereo frame (2 samples: left + right)
-    for frame in data.chunks_mut(channels) {
-
-        // Only process audio if playback is active
-        if state.is_playing {
-
-            // Get current playback position in beats
-            let current_beat = state.playhead_position;
-            let samples_per_beat = state.samples_per_beat;
-
-            // Clone data to avoid borrow checker issues
-            // (we need to mutate instruments while reading clips/patterns)
-            let clips = state.playlist.clips.clone();
-            let patterns = state.patterns.clone();
-            let just_started = state.just_started;
-
-            // Vectors to store which sounds should be triggered this frame
-            let mut triggers: Vec<(usize, usize)> = Vec::new(); // (instrument_idx, step)
-            let mut audio_triggers: Vec<usize> = Vec::new(); // instrument_idx for audio files
-
-            // Check all clips in the playlist
-            for clip in &clips {
-                let clip_start = clip.start_time;
-                let clip_end = clip.start_time + clip.length;
-
-                // Only process clips that are currently playing
-                if current_beat >= clip_start && current_beat < clip_end {
-                    // Calculate position within this clip (in beats)
-                    let position_in_clip = current_beat - clip_start;
-
-                    match &clip.clip_type {
-                        // Pattern clips: trigger instruments based on 16-step sequencer
-                        crate::models::ClipType::Pattern(pattern_idx) => {
-                            // Convert beat position to step (0-15)
-                            // Multiply by 4 because each beat = 4 steps in a 16-step pattern
-                            let step_in_pattern = ((position_in_clip * 4.0) as usize) % 16;
-
-                            // Get the previous step to detect step changes
-                            // At the very start of a clip, use a sentinel value to force trigger
-                            let last_step = if position_in_clip < 1.0 / samples_per_beat as f64 || just_started {
-                                999 // Force trigger at start of clip OR on first playback
-                            } else {
-                                (((current_beat - 1.0 / samples_per_beat as f64) - clip_start) * 4.0) as usize % 16
-                            };
-
-                            // DEBUG - print first few beats
-                            if current_beat < 0.1 {
-                                println!("beat: {:.6}, pos_in_clip: {:.6}, step: {}, last_step: {}, just_started: {}",
-                                         current_beat, position_in_clip, step_in_pattern, last_step, just_started);
-                            }
-
-                            // Only trigger on step boundaries (when step changes)
-                            if step_in_pattern != last_step {
-                                if let Some(pattern) = patterns.get(*pattern_idx) {
-                                    // Check each instrument row in the pattern
-                                    for (i, row) in pattern.data.iter().enumerate() {
-                                        // If this step is active, trigger the instrument
-                                        if row[step_in_pattern] {
-                                            if current_beat < 0.1 {
-                                                println!("TRIGGERING instrument {} at step {}", i, step_in_pattern);
-                                            }
-                                            triggers.push((i, step_in_pattern));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        // Audio file clips: trigger the audio file to play
-                        crate::models::ClipType::AudioFile(instrument_idx) => {
-                            // Only trigger at the very start of the clip
-                            if position_in_clip < 1.0 / samples_per_beat as f64 {
-                                audio_triggers.push(*instrument_idx);
-                            }
-                        }
-                    }
-                }
+/// Runs the engine without a device, for when none is wanted or none could be opened
+pub fn null_output(session: &Session, metronome: Sample, settings: &AudioSettings) -> (Output, EngineHandle) {
+    let sample_rate = settings.sample_rate.unwrap_or(NULL_SAMPLE_RATE);
+    let frames = settings.buffer_size.unwrap_or(NULL_BUFFER_SIZE) as usize;
+
+    let mut engine = new_engine(session, metronome, sample_rate as f32);
+    let handle = engine.connect();
+
+    let running = Arc::new(AtomicBool::new(true));
+    let thread = std::thread::spawn({
+        let running = running.clone();
+        move || {
+            let period = Duration::from_secs_f64(frames as f64 / sample_rate as f64);
+            let mut buffer = vec![0.0; frames * 2];
+            let mut deadline = Instant::now();
+            while running.load(Ordering::Relaxed) {
+                engine.render(&mut buffer);
+                // keep to the schedule rather than drifting by the render time
+                deadline += period;
+                std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
             }
+        }
+    });
 
-            // Apply all pattern triggers: start playing instruments
-            for (instrument_idx, _) in triggers {
-                if let Some(instrument) = state.instruments.get_mut(instrument_idx) {
-                    instrument.is_playing = true;
-                    instrument.position = 0; // Reset to start of sample
-                }
-            }
+    (Output::Null(NullOutput { running, thread: Some(thread) }), handle)
+}
 
-            // Apply all audio file triggers
-            for instrument_idx in audio_triggers {
-                if let Some(instrument) = state.instruments.get_mut(instrument_idx) {
-                    // Only start if not already playing (prevents retriggering)
-                    if !instrument.is_playing {
-                        instrument.is_playing = true;
-                        instrument.position = 0;
-                    }
-                }
-            }
+fn new_engine(session: &Session, metronome: Sample, sample_rate: f32) -> Engine {
+    let mut engine = Engine::with_session(session.clone(), sample_rate);
+    engine.metronome = Instrument::new(PathBuf::from(METRONOME_SAMPLE), metronome);
+    engine
+}
 
-            // Handle metronome click on beat boundaries
-            // Handle metronome click on beat boundaries
-            // Handle metronome click on beat boundaries
-            if state.is_metronome {
-                let beat = state.playhead_position.floor() as i32;  // Change to i32
-                let last_beat = (state.playhead_position - 1.0 / samples_per_beat as f64).floor() as i32;  // Change to i32
-
-                // Trigger metronome when crossing a beat boundary
-                if beat != last_beat {
-                    state.metronome_playing = true;
-                    state.metronome_position = 0;
-                }
-            }
+/// The host with this name, or the platform default
+fn find_host(name: Option<&str>) -> Result<Host, Error> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name() == name)
+        .ok_or_else(|| Error::device(format!("audio host \"{}\" is not available", name)))?;
+    cpal::host_from_id(id).map_err(|err| Error::device(format!("could not open audio host {}: {}", name, err)))
+}
 
-            // Clear just_started flag after first frame
-            if state.just_started {
-                state.just_started = false;
-            }
+fn find_device(host: &Host, device: &OutputDevice) -> Result<Device, Error> {
+    match device {
+        OutputDevice::Named(name) => host
+            .output_devices()
+            .map_err(|err| Error::device(format!("could not list output devices: {}", err)))?
+            .find(|d| d.name().is_ok_and(|n| n == *name))
+            .ok_or_else(|| Error::device(format!("output device \"{}\" not found", name))),
+        _ => host
+            .default_output_device()
+            .ok_or_else(|| Error::device("no output device available")),
+    }
+}
 
-            // Advance the playhead
-            state.metronome_counter += 1.0; // Increment sample counter
-            state.playhead_position = (state.metronome_counter / samples_per_beat) as f64; // Convert to beats
-        }
+/// The device's default configuration, moved to `sample_rate` if one is asked for.
+/// Prefers keeping the default sample format and channel count.
+fn output_config(device: &Device, sample_rate: Option<u32>) -> Result<SupportedStreamConfig, Error> {
+    let default = device
+        .default_output_config()
+        .map_err(|err| Error::device(format!("error getting default config: {}", err)))?;
+    let Some(rate) = sample_rate else {
+        return Ok(default);
+    };
+    if default.sample_rate().0 == rate {
+        return Ok(default);
+    }
 
-        // Mix all active audio sources together
-        let mut mix = 0.0;
-
-        // Mix all instruments
-        for instrument in &mut state.instruments {
-            if instrument.is_playing {
-                // If we haven't reached the end of the sample
-                if instrument.position < instrument.samples.len() {
-                    mix += instrument.samples[instrument.position]; // Add to mix
-                    instrument.position += 1; // Advance playback position
-                } else {
-                    // Sample finished playing
-                    instrument.is_playing = false;
-                }
-            }
-        }
+    let mut ranges: Vec<_> = device
+        .supported_output_configs()
+        .map_err(|err| Error::device(format!("error getting supported configs: {}", err)))?
+        .collect();
+    ranges.sort_by_key(|r| (r.sample_format() != default.sample_format(), r.channels() != default.channels()));
 
-        // Mix metronome
-        if state.metronome_playing {
-            if state.metronome_position < state.metronome_sample.len() {
-                mix += state.metronome_sample[state.metronome_position];
-                state.metronome_position += 1;
-            } else {
-                state.metronome_playing = false;
-            }
-        }
+    ranges
+        .into_iter()
+        .find_map(|r| r.try_with_sample_rate(SampleRate(rate)))
+        .ok_or_else(|| Error::device(format!("device does not support {} Hz", rate)))
+}
 
-        // Mix preview sound (file browser preview)
-        if let Some(ref mut preview) = state.preview_sound {
-            if preview.is_playing {
-                if preview.position < preview.samples.len() {
-                    mix += preview.samples[preview.position];
-                    preview.position += 1;
-                } else {
-                    // Preview finished, remove it
-                    preview.is_playing = false;
-                    state.preview_sound = None;
+/// Opens an output stream of sample type `T` and moves the engine into its
+/// callback. The engine renders f32 for every channel of the device, which
+/// is then converted to `T`.
+fn build_stream<T>(device: &Device, config: &StreamConfig, mut engine: Engine) -> Result<Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    // whole frames only, so each pass lines up with the device's channels
+    let mut scratch = vec![0.0f32; SCRATCH_SAMPLES - SCRATCH_SAMPLES % channels.max(1)];
+
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            for chunk in data.chunks_mut(scratch.len()) {
+                let mix = &mut scratch[..chunk.len()];
+                engine.render_channels(mix, channels);
+                for (out, &sample) in chunk.iter_mut().zip(mix.iter()) {
+                    *out = T::from_sample(sample);
                 }
             }
-        }
+        },
+        |err| eprintln!("error: {}", err),
+        None,
+    )
+}
 
-        // Write the mixed audio to both channels (stereo)
-        for sample in frame.iter_mut() {
-            *sample = mix;
-        }
+/// The metronome click bundled with the app
+pub fn load_metronome() -> Result<Sample, Error> {
+    load_sample(Path::new(METRONOME_SAMPLE))
+}
+
+/// The session the app opens with: the bundled test instruments and one empty pattern.
+/// Instruments that fail to load are kept as silence so the rack layout stays the same.
+///
+/// # Returns
+/// * `(Session, Vec<Error>)` - The session and why any instrument couldn't be loaded
+pub fn default_session() -> (Session, Vec<Error>) {
+    let mut session = Session::new();
+    let mut errors = Vec::new();
+
+    for path in DEFAULT_INSTRUMENTS {
+        let sample = load_sample(Path::new(path)).unwrap_or_else(|err| {
+            errors.push(err);
+            Sample::default()
+        });
+        session.instruments.push(Instrument::new(PathBuf::from(path), sample));
     }
-}
\ No newline at end of file
+    session.patterns.push(Pattern::empty("Pattern 1".to_string(), session.instruments.len()));
+    (session, errors)
+}
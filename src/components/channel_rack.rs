@@ -1,77 +1,335 @@
-use crate::audio::path_to_vector;
+use egui::{Color32, Rangef, Rect, Sense, Stroke};
+use remdaw_engine::decoder::{load_sample, supported_extensions};
+use remdaw_engine::groove::{Groove, GROOVE_EXTENSION};
+use remdaw_engine::models::{DEFAULT_PATTERN_STEPS, MAX_PATTERN_STEPS, STEP_PITCH_RANGE};
+use remdaw_engine::{Command, Step, StepResolution};
+use crate::components::mixer::pan_label;
 use crate::models::{Instrument, MyApp};
-use crate::utils::get_file_name;
+
+const LANE_HEIGHT: f32 = 60.0;
+const LANE_BACKGROUND: Color32 = Color32::from_gray(30);
+const LANE_BAR: Color32 = Color32::from_rgb(200, 60, 60);
+const LANE_BAR_OFF: Color32 = Color32::from_gray(80);
+
+/// Which setting of the steps the event lane edits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StepParam {
+    #[default]
+    Velocity,
+    Pan,
+    Pitch,
+    Probability,
+}
+
+impl StepParam {
+    const ALL: [StepParam; 4] = [StepParam::Velocity, StepParam::Pan, StepParam::Pitch, StepParam::Probability];
+
+    fn label(self) -> &'static str {
+        match self {
+            StepParam::Velocity => "Velocity",
+            StepParam::Pan => "Pan",
+            StepParam::Pitch => "Fine pitch",
+            StepParam::Probability => "Probability",
+        }
+    }
+
+    fn range(self) -> (f32, f32) {
+        match self {
+            StepParam::Velocity | StepParam::Probability => (0.0, 1.0),
+            StepParam::Pan => (-1.0, 1.0),
+            StepParam::Pitch => (-STEP_PITCH_RANGE, STEP_PITCH_RANGE),
+        }
+    }
+
+    fn get(self, step: &Step) -> f32 {
+        match self {
+            StepParam::Velocity => step.velocity,
+            StepParam::Pan => step.pan,
+            StepParam::Pitch => step.pitch,
+            StepParam::Probability => step.probability,
+        }
+    }
+
+    fn set(self, step: &mut Step, value: f32) {
+        let (min, max) = self.range();
+        let value = value.clamp(min, max);
+        match self {
+            StepParam::Velocity => step.velocity = value,
+            StepParam::Pan => step.pan = value,
+            StepParam::Pitch => step.pitch = value.round(),
+            StepParam::Probability => step.probability = value,
+        }
+    }
+
+    fn format(self, value: f32) -> String {
+        match self {
+            StepParam::Velocity | StepParam::Probability => format!("{:.0}%", value * 100.0),
+            StepParam::Pan => pan_label(value),
+            StepParam::Pitch => format!("{:+.0} cents", value),
+        }
+    }
+}
+
+/// Bar graph of one setting across a row of steps, drawn under the step
+/// buttons. Dragging across it sets the bars under the pointer, right
+/// clicking resets one.
+///
+/// # Arguments
+/// * `columns` - Horizontal extent of each step button, so the bars line up
+/// * `row` - The instrument's steps
+///
+/// # Returns
+/// * `Vec<usize>` - Steps that changed
+fn event_lane(ui: &mut egui::Ui, param: StepParam, columns: &[Rangef], row: &mut [Step]) -> Vec<usize> {
+    let mut changed = Vec::new();
+    let (Some(first), Some(last)) = (columns.first(), columns.last()) else {
+        return changed;
+    };
+
+    ui.add_space(first.min - ui.cursor().left());
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(last.max - first.min, LANE_HEIGHT), Sense::click_and_drag());
+    let (min, max) = param.range();
+    let y_of = |value: f32| rect.bottom() - (value - min) / (max - min) * rect.height();
+    // bipolar settings grow from the middle
+    let base = y_of(if min < 0.0 { 0.0 } else { min });
+
+    if let Some(pointer) = response.interact_pointer_pos()
+        && let Some(step) = columns.iter().position(|column| column.expand(2.5).contains(pointer.x))
+        && let Some(cell) = row.get_mut(step) {
+        let value = if response.secondary_clicked() {
+            param.get(&Step::OFF)
+        } else {
+            min + (rect.bottom() - pointer.y) / rect.height() * (max - min)
+        };
+        let before = *cell;
+        param.set(cell, value);
+        if *cell != before {
+            changed.push(step);
+        }
+    }
+
+    let painter = ui.painter();
+    for (column, step) in columns.iter().zip(row.iter()) {
+        let cell = Rect::from_x_y_ranges(*column, rect.y_range());
+        painter.rect_filled(cell, 0.0, LANE_BACKGROUND);
+
+        let top = y_of(param.get(step));
+        let bar = Rect::from_x_y_ranges(*column, Rangef::new(top.min(base), top.max(base) + 1.0));
+        painter.rect_filled(bar, 0.0, if step.active { LANE_BAR } else { LANE_BAR_OFF });
+    }
+    if min < 0.0 {
+        painter.hline(rect.x_range(), base, Stroke::new(1.0, Color32::from_gray(110)));
+    }
+
+    if let Some(hover) = response.hover_pos()
+        && let Some(step) = columns.iter().position(|column| column.contains(hover.x)) {
+        response.on_hover_text(format!("Step {}: {}", step + 1, param.format(param.get(&row[step]))));
+    }
+    changed
+}
 
 pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let mut new_instrument = None;
+    let mut pattern_changed = false;
+    let mut step_edits = Vec::new(); // sent once the window lets go of the session
+
     ctx.request_repaint();
     egui::Window::new("Channel Rack")
         .collapsible(true)
         .open(&mut app.ui_state.is_channel_rack_open)
         .show(ctx, |ui| {
-            let mut state = app.audio_state.lock().unwrap(); // unlock audio state mutex
+            let session = &mut app.session;
             let mut clicked_instrument: Option<usize> = None;
-            let current_step = state.current_step;
+            let current_pattern = app.ui_state.current_pattern_index;
+            let is_playing = app.engine.is_playing();
+
+            // Length and note value of the open pattern's steps
+            if let Some(pattern) = current_pattern.and_then(|idx| session.patterns.get_mut(idx)) {
+                ui.horizontal(|ui| {
+                    let mut steps = pattern.steps;
+                    ui.label("Steps:");
+                    if ui.add(egui::DragValue::new(&mut steps).range(1..=MAX_PATTERN_STEPS)).changed() {
+                        pattern.set_steps(steps);
+                        pattern_changed = true;
+                    }
+
+                    ui.label("Step:");
+                    egui::ComboBox::from_id_salt("step_resolution")
+                        .selected_text(pattern.resolution.label())
+                        .show_ui(ui, |ui| {
+                            for resolution in StepResolution::ALL {
+                                pattern_changed |= ui.selectable_value(&mut pattern.resolution, resolution, resolution.label()).changed();
+                            }
+                        });
+                    ui.weak(format!("{} beats", pattern.length()));
+
+                    ui.add_space(12.0);
+                    let mut own_swing = pattern.swing.is_some();
+                    if ui.checkbox(&mut own_swing, "Swing")
+                        .on_hover_text("Use this swing instead of the song's")
+                        .changed() {
+                        pattern.swing = own_swing.then_some(session.swing);
+                        pattern_changed = true;
+                    }
+                    if let Some(swing) = &mut pattern.swing {
+                        pattern_changed |= ui.add(egui::Slider::new(swing, 0.0..=1.0)
+                            .custom_formatter(|swing, _| format!("{:.0}%", swing * 100.0)))
+                            .changed();
+                    }
+
+                    ui.label("Groove:");
+                    egui::ComboBox::from_id_salt("groove")
+                        .selected_text(pattern.groove.as_ref().map_or("None", |groove| groove.name.as_str()))
+                        .show_ui(ui, |ui| {
+                            if ui.selectable_label(pattern.groove.is_none(), "None").clicked() {
+                                pattern.groove = None;
+                                pattern_changed = true;
+                            }
+                            for preset in Groove::presets() {
+                                let selected = pattern.groove.as_ref() == Some(&preset);
+                                if ui.selectable_label(selected, &preset.name).clicked() {
+                                    pattern.groove = Some(preset);
+                                    pattern_changed = true;
+                                }
+                            }
+                            if ui.selectable_label(false, "Load...").clicked()
+                                && let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Groove", &[GROOVE_EXTENSION])
+                                    .pick_file() {
+                                match Groove::load(&path) {
+                                    Ok(groove) => {
+                                        pattern.groove = Some(groove);
+                                        pattern_changed = true;
+                                    }
+                                    Err(err) => app.notifications.error(err),
+                                }
+                            }
+                        });
+                });
+            }
+
+            let (steps, steps_per_beat) = current_pattern
+                .and_then(|idx| session.patterns.get(idx))
+                .map_or((DEFAULT_PATTERN_STEPS, 4.0), |pattern| (pattern.steps, pattern.resolution.steps_per_beat()));
+            let current_step = ((app.engine.playhead_position() * steps_per_beat) as usize) % steps;
 
             ui.spacing_mut().item_spacing = egui::Vec2::new(1.0, 5.0);
 
-            for instrument in 0..state.instruments.len() {
+            for instrument in 0..session.instruments.len() {
+                let mut columns = Vec::with_capacity(steps);
+
                 ui.horizontal(|ui| {
                     ui.spacing_mut().item_spacing.x = 5.0;
 
                     // Label
                     if ui.add_sized(
                         [100.0, 25.0],
-                        egui::Button::new(&state.instruments[instrument].name).truncate()
-                    ).on_hover_text(&state.instruments[instrument].name).clicked() {
+                        egui::Button::new(&session.instruments[instrument].name).truncate()
+                    ).on_hover_text(&session.instruments[instrument].name).clicked() {
                         clicked_instrument = Some(instrument);
                     }
 
+                    app.ui_state.meters.instrument_meter(ui, app.engine.meters(), instrument, egui::vec2(9.0, 25.0));
+
+                    let lane_open = app.ui_state.step_lane == Some(instrument);
+                    if ui.add_sized([30.0, 25.0], egui::Button::selectable(lane_open, "Lane"))
+                        .on_hover_text("Edit velocity, pan, pitch and probability per step")
+                        .clicked() {
+                        app.ui_state.step_lane = if lane_open { None } else { Some(instrument) };
+                    }
+
                     // Step buttons
-                    for step in 0..16 {
-                        let is_active = state.pattern[instrument][step];
-                        let is_current = step == current_step && state.is_playing;
+                    for step in 0..steps {
+                        let cell = current_pattern
+                            .and_then(|idx| session.patterns.get(idx))
+                            .and_then(|pattern| pattern.data.get(instrument))
+                            .and_then(|row| row.get(step))
+                            .copied()
+                            .unwrap_or_default();
+                        let is_current = step == current_step && is_playing;
 
                         let button = egui::Button::new("")
                             .min_size(egui::Vec2::new(20.0, 25.0));
 
-                        let valid = vec![4, 5, 6, 7, 12, 13, 14, 15];
-                        let is_colored = !valid.contains(&step);
+                        // every other beat is shaded darker
+                        let is_colored = ((step as f64 / steps_per_beat) as usize).is_multiple_of(2);
 
                         let button = if is_current {
                             // Highlight current step with bright color
                             button.fill(egui::Color32::from_rgb(0, 200, 255))
-                        } else if is_active {
-                            button.fill(egui::Color32::from_rgb(150, 0, 0))
+                        } else if cell.active {
+                            // quieter steps are darker
+                            button.fill(egui::Color32::from_rgb((60.0 + 90.0 * cell.velocity) as u8, 0, 0))
                         } else if is_colored {
                             button.fill(egui::Color32::from_rgb(50, 50, 50))
                         } else {
                             button.fill(egui::Color32::from_rgb(90, 90, 90))
                         };
 
-                        if ui.add(button).clicked() {
-                            state.pattern[instrument][step] = !is_active;
-
-                            if let Some(current_idx) = state.current_pattern_index {
-                                state.patterns[current_idx].data = state.pattern.clone();
-                            }
+                        let response = ui.add(button);
+                        columns.push(response.rect.x_range());
+                        if response.clicked()
+                            && let Some(pattern) = current_pattern
+                            && let Some(row) = session.patterns.get_mut(pattern)
+                                .and_then(|p| p.data.get_mut(instrument)) {
+                            // turning a step off keeps its settings for later
+                            let value = Step { active: !cell.active, ..cell };
+                            row[step] = value;
+                            step_edits.push(Command::SetStep { pattern, instrument, step, value });
                         }
                     }
                 });
-            }
 
-            if ui.button("+").on_hover_text("Add new file").clicked() {
-                if let Some(path) = rfd::FileDialog::new().pick_file() {
-                    state.instruments.push(Instrument {file_path: path.clone(), name: get_file_name(&path), is_playing: false, position: 0, samples: path_to_vector(path.to_str().unwrap())});
-                    state.pattern.push(vec![false; 16]);
+                if app.ui_state.step_lane == Some(instrument)
+                    && let Some(pattern) = current_pattern
+                    && let Some(row) = session.patterns.get_mut(pattern).and_then(|p| p.data.get_mut(instrument)) {
+                    ui.horizontal(|ui| {
+                        let param = &mut app.ui_state.step_param;
+                        egui::ComboBox::from_id_salt("step_param")
+                            .width(100.0)
+                            .selected_text(param.label())
+                            .show_ui(ui, |ui| {
+                                for option in StepParam::ALL {
+                                    ui.selectable_value(param, option, option.label());
+                                }
+                            });
+
+                        for step in event_lane(ui, *param, &columns, row) {
+                            step_edits.push(Command::SetStep { pattern, instrument, step, value: row[step] });
+                        }
+                    });
                 }
             }
 
+            ui.horizontal(|ui| {
+                if ui.button("+").on_hover_text("Add new file").clicked()
+                    && let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Audio", &supported_extensions())
+                        .pick_file() {
+                    match load_sample(&path) {
+                        Ok(sample) => new_instrument = Some(Instrument::new(path, sample)),
+                        Err(err) => app.notifications.error(err),
+                    }
+                }
+                if ui.button("+ Synth").on_hover_text("Add a subtractive synth").clicked() {
+                    let synths = session.instruments.iter().filter(|instrument| instrument.is_synth()).count();
+                    new_instrument = Some(Instrument::synth(format!("Synth {}", synths + 1)));
+                }
+            });
+
             // Handle the click after the loop
             if let Some(idx) = clicked_instrument {
-                let file_path = state.instruments[idx].file_path.clone();
-                drop(state);
-                app.selected_file = Some(file_path);
-                app.ui_state.is_file_info_open = true;
+                app.ui_state.instrument_window = Some(idx);
             }
         });
-}
\ No newline at end of file
+
+    for command in step_edits {
+        app.send(command);
+    }
+    if pattern_changed {
+        app.sync_patterns();
+    }
+    if let Some(instrument) = new_instrument {
+        app.add_instrument(instrument);
+    }
+}
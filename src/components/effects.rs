@@ -0,0 +1,144 @@
+use remdaw_engine::effects::{EffectKind, EffectSlot, EffectTarget, Param};
+use remdaw_engine::Command;
+use crate::models::MyApp;
+
+/// What to do with a slot once the window is drawn
+enum SlotEdit {
+    Remove(usize),
+    Swap(usize, usize),
+    Add(EffectKind),
+}
+
+/// Draws the control of one parameter. Returns true when it changed.
+fn param_control(ui: &mut egui::Ui, id: usize, param: &Param, value: &mut f32) -> bool {
+    if !param.labels.is_empty() {
+        // choices are stored as the index of the option
+        let mut selected = *value as usize;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(("effect_param", id))
+                .selected_text(param.labels.get(selected).copied().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (index, label) in param.labels.iter().enumerate() {
+                        changed |= ui.selectable_value(&mut selected, index, *label).changed();
+                    }
+                });
+            ui.label(param.name);
+        });
+        *value = selected as f32;
+        return changed;
+    }
+
+    let suffix = match param.unit {
+        "" => String::new(),
+        unit if unit.starts_with(':') => unit.to_string(),
+        unit => format!(" {}", unit),
+    };
+    ui.add(egui::Slider::new(value, param.min..=param.max)
+        .logarithmic(param.logarithmic)
+        .suffix(suffix)
+        .text(param.name))
+        .changed()
+}
+
+/// Lists the inserts of the chain picked in the mixer. Parameter and bypass
+/// changes go straight to the live effects; adding, removing or reordering
+/// rebuilds the chain.
+pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(target) = app.ui_state.effects_target else {
+        return;
+    };
+    let name = match target {
+        EffectTarget::Master => Some("Master".to_string()),
+        EffectTarget::Track(track) => app.session.playlist.tracks.get(track).map(|track| track.name.clone()),
+        EffectTarget::Aux(bus) => app.session.aux_buses.get(bus).map(|bus| bus.name.clone()),
+    };
+    // the track or bus is gone
+    let Some(name) = name else {
+        app.ui_state.effects_target = None;
+        return;
+    };
+    let Some(slots) = app.session.effects_mut(target) else {
+        return;
+    };
+
+    let mut open = true;
+    let mut commands = Vec::new();
+    let mut edit = None;
+
+    egui::Window::new(format!("Effects: {}", name))
+        .id(egui::Id::new("effects_window"))
+        .open(&mut open)
+        .default_width(300.0)
+        .show(ctx, |ui| {
+            if slots.is_empty() {
+                ui.weak("No effects");
+            }
+
+            let count = slots.len();
+            for (index, slot) in slots.iter_mut().enumerate() {
+                ui.push_id(index, |ui| {
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            let mut active = !slot.bypass;
+                            if ui.toggle_value(&mut active, "On").on_hover_text("Bypass when off").changed() {
+                                slot.bypass = !active;
+                                commands.push(Command::SetEffectBypass { target, slot: index, bypass: slot.bypass });
+                            }
+                            ui.strong(slot.kind.label());
+
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                if ui.button("Remove").clicked() {
+                                    edit = Some(SlotEdit::Remove(index));
+                                }
+                                if ui.add_enabled(index + 1 < count, egui::Button::new("Down")).clicked() {
+                                    edit = Some(SlotEdit::Swap(index, index + 1));
+                                }
+                                if ui.add_enabled(index > 0, egui::Button::new("Up")).clicked() {
+                                    edit = Some(SlotEdit::Swap(index - 1, index));
+                                }
+                            });
+                        });
+
+                        for (param_index, (param, value)) in slot.kind.params().iter().zip(&mut slot.params).enumerate() {
+                            if param_control(ui, param_index, param, value) {
+                                commands.push(Command::SetEffectParam { target, slot: index, param: param_index, value: *value });
+                            }
+                        }
+                    });
+                });
+            }
+
+            ui.separator();
+            egui::ComboBox::from_id_salt("add_effect")
+                .selected_text("Add effect")
+                .show_ui(ui, |ui| {
+                    for kind in EffectKind::ALL {
+                        if ui.selectable_label(false, kind.label()).clicked() {
+                            edit = Some(SlotEdit::Add(kind));
+                        }
+                    }
+                });
+        });
+
+    let rebuild = edit.is_some();
+    if let Some(edit) = edit {
+        match edit {
+            SlotEdit::Remove(index) => {
+                slots.remove(index);
+            }
+            SlotEdit::Swap(a, b) => slots.swap(a, b),
+            SlotEdit::Add(kind) => slots.push(EffectSlot::new(kind)),
+        }
+    }
+
+    for command in commands {
+        app.send(command);
+    }
+    if rebuild {
+        app.sync_effects(target);
+    }
+    if !open {
+        app.ui_state.effects_target = None;
+    }
+}
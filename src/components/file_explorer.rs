@@ -1,6 +1,8 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use crate::models::MyApp;
+use remdaw_engine::decoder::{is_supported, load_sample};
+use remdaw_engine::Command;
+use crate::models::{Instrument, MyApp};
 
 pub fn render(app: &mut MyApp, ctx: &egui::Context) {
     egui::SidePanel::left("files")
@@ -45,9 +47,9 @@ fn render_directory(ui: &mut egui::Ui, app: &mut MyApp, ctx: &egui::Context, pat
                             render_directory(ui, app, ctx, &path, depth + 1);
                         });
                 } else {
-                    let is_wav = path.extension().and_then(|s| s.to_str()) == Some("wav");
+                    let is_audio = is_supported(&path);
 
-                    if is_wav {
+                    if is_audio {
                         // Check if this file is being dragged
                         let is_being_dragged = ctx.memory(|mem| {
                             mem.data.get_temp::<PathBuf>(egui::Id::new("dragging_audio_file"))
@@ -94,16 +96,10 @@ fn render_directory(ui: &mut egui::Ui, app: &mut MyApp, ctx: &egui::Context, pat
 
                         // Handle click to preview
                         if response.clicked() {
-                            let samples = crate::audio::path_to_vector(path.to_str().unwrap());
-                            let mut state = app.audio_state.lock().unwrap();
-
-                            state.preview_sound = Some(crate::models::Instrument {
-                                name: name.clone(),
-                                file_path: path.clone(),
-                                samples,
-                                position: 0,
-                                is_playing: true,
-                            });
+                            match load_sample(&path) {
+                                Ok(sample) => app.send(Command::Preview(Some(Instrument::new(path.clone(), sample)))),
+                                Err(err) => app.notifications.error(err),
+                            }
                         }
                     } else {
                         ui.label(format!("📄 {}", name));
@@ -0,0 +1,200 @@
+use std::path::Path;
+use egui::{Color32, Rect, Sense, Stroke, Ui, Vec2};
+use remdaw_engine::decoder::{probe, SampleInfo};
+use remdaw_engine::sample::Sample;
+use remdaw_engine::sampler::{PlayMode, SamplerSettings, MAX_CROSSFADE};
+use remdaw_engine::{Command, Generator};
+use crate::components::mixer::gain_label;
+use crate::components::{synth, waveform};
+use crate::models::MyApp;
+
+/// Loudest the sampler gain goes, as linear gain (+12 dB)
+const MAX_SAMPLER_GAIN: f32 = 4.0;
+const WAVEFORM_SIZE: Vec2 = Vec2::new(400.0, 90.0);
+const TRIMMED_SHADE: Color32 = Color32::from_black_alpha(150);
+const LOOP_COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+
+/// Settings of the instrument picked in the channel rack: the patch of a
+/// synth, or the file and sampler settings of a sample. Every change is
+/// applied in place, so playing voices follow it.
+pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(index) = app.ui_state.instrument_window else {
+        return;
+    };
+    // the instrument is gone
+    let Some(instrument) = app.session.instruments.get(index) else {
+        app.ui_state.instrument_window = None;
+        return;
+    };
+    let name = instrument.name.clone();
+    let path = instrument.file_path.clone();
+    let sample = instrument.sample.clone();
+    let mut generator = instrument.generator;
+    let mut sampler = instrument.sampler;
+
+    // reading the header every frame would hit the disk while the window is open
+    if app.ui_state.sample_info.as_ref().is_none_or(|(probed, _)| *probed != path) {
+        app.ui_state.sample_info = Some((path.clone(), probe(&path).ok()));
+    }
+    let info = app.ui_state.sample_info.as_ref().and_then(|(_, info)| info.clone());
+
+    let mut open = true;
+    let mut changed = false;
+
+    egui::Window::new(format!("Instrument: {}", name))
+        .id(egui::Id::new("instrument_window"))
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| match &mut generator {
+            Generator::Synth(patch) => changed = synth::controls(ui, patch),
+            Generator::Sampler => {
+                file_information(ui, &path, info.as_ref());
+                ui.separator();
+                sample_view(ui, &sample, &sampler);
+                changed = sampler_controls(ui, &mut sampler);
+            }
+        });
+
+    if changed {
+        let instrument = &mut app.session.instruments[index];
+        instrument.generator = generator;
+        instrument.sampler = sampler;
+        match generator {
+            Generator::Synth(patch) => app.send(Command::SetSynth(index, patch)),
+            Generator::Sampler => app.send(Command::SetSampler(index, sampler)),
+        }
+    }
+    if !open {
+        app.ui_state.instrument_window = None;
+    }
+}
+
+/// Name, location and format of the sample's file
+fn file_information(ui: &mut Ui, file: &Path, info: Option<&SampleInfo>) {
+    let name = file.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Unknown");
+
+    ui.label(format!("Name: {}", name));
+    ui.label(format!("Path: {}", file.display()));
+
+    if let Some(info) = info {
+        ui.label(format!("Format: {}", info.format));
+        ui.label(format!("Sample Rate: {} Hz", info.sample_rate));
+        ui.label(format!("Channels: {}", info.channels));
+        if let Some(bits) = info.bits_per_sample {
+            ui.label(format!("Bits per Sample: {}", bits));
+        }
+        if let Some(duration) = info.duration() {
+            ui.label(format!("Duration: {:.2}s", duration));
+        }
+    } else {
+        ui.label("Could not read file");
+    }
+}
+
+/// The whole sample, with the trimmed ends shaded and the loop marked
+fn sample_view(ui: &mut Ui, sample: &Sample, settings: &SamplerSettings) {
+    let (rect, _) = ui.allocate_exact_size(WAVEFORM_SIZE, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, Color32::from_gray(25));
+    let frames_per_pixel = sample.len() as f64 / rect.width() as f64;
+    waveform::draw(&painter, rect, sample, 0.0, frames_per_pixel, Color32::from_rgb(0, 200, 255));
+
+    let x = |fraction: f32| rect.left() + fraction.clamp(0.0, 1.0) * rect.width();
+    painter.rect_filled(Rect::from_x_y_ranges(rect.left()..=x(settings.start), rect.y_range()), 0.0, TRIMMED_SHADE);
+    painter.rect_filled(Rect::from_x_y_ranges(x(settings.end)..=rect.right(), rect.y_range()), 0.0, TRIMMED_SHADE);
+    if settings.loops() {
+        for point in [settings.loop_start, settings.loop_end] {
+            painter.vline(x(point), rect.y_range(), Stroke::new(1.5, LOOP_COLOR));
+        }
+    }
+    if settings.reverse {
+        painter.text(rect.left_top() + Vec2::splat(4.0), egui::Align2::LEFT_TOP, "Reversed", egui::FontId::proportional(11.0), Color32::WHITE);
+    }
+}
+
+fn percent(value: f64) -> String {
+    format!("{:.1}%", value * 100.0)
+}
+
+/// Trim, loop, direction, gain, envelope and play mode
+fn sampler_controls(ui: &mut Ui, settings: &mut SamplerSettings) -> bool {
+    let mut changed = false;
+
+    egui::Grid::new("sampler_settings").num_columns(2).show(ui, |ui| {
+        ui.label("Mode");
+        egui::ComboBox::from_id_salt("sampler_mode")
+            .selected_text(settings.mode.label())
+            .show_ui(ui, |ui| {
+                for mode in PlayMode::ALL {
+                    changed |= ui.selectable_value(&mut settings.mode, mode, mode.label()).changed();
+                }
+            })
+            .response
+            .on_hover_text("Gated notes stop at their note off, one-shots play through");
+        ui.end_row();
+
+        ui.label("Start");
+        let end = settings.end;
+        changed |= ui.add(egui::Slider::new(&mut settings.start, 0.0..=end).custom_formatter(|v, _| percent(v))).changed();
+        ui.end_row();
+
+        ui.label("End");
+        let start = settings.start;
+        changed |= ui.add(egui::Slider::new(&mut settings.end, start..=1.0).custom_formatter(|v, _| percent(v))).changed();
+        ui.end_row();
+
+        ui.label("Reverse");
+        changed |= ui.checkbox(&mut settings.reverse, "").changed();
+        ui.end_row();
+
+        ui.label("Gain");
+        changed |= ui.add(egui::Slider::new(&mut settings.gain, 0.0..=MAX_SAMPLER_GAIN)
+            .custom_formatter(|gain, _| gain_label(gain)))
+            .changed();
+        ui.end_row();
+    });
+    ui.separator();
+
+    // loops only hold gated notes, a one-shot has no note off to end them
+    let gated = settings.mode == PlayMode::Gated;
+    ui.add_enabled_ui(gated, |ui| {
+        changed |= ui.checkbox(&mut settings.looping, "Loop").changed();
+        ui.add_enabled_ui(settings.looping, |ui| {
+            egui::Grid::new("sampler_loop").num_columns(2).show(ui, |ui| {
+                let (start, end) = (settings.start, settings.end);
+                ui.label("Loop start");
+                let loop_end = settings.loop_end.min(end);
+                changed |= ui.add(egui::Slider::new(&mut settings.loop_start, start..=loop_end)
+                    .custom_formatter(|v, _| percent(v)))
+                    .changed();
+                ui.end_row();
+
+                ui.label("Loop end");
+                let loop_start = settings.loop_start.max(start);
+                changed |= ui.add(egui::Slider::new(&mut settings.loop_end, loop_start..=end)
+                    .custom_formatter(|v, _| percent(v)))
+                    .changed();
+                ui.end_row();
+
+                ui.label("Crossfade");
+                changed |= ui.add(egui::Slider::new(&mut settings.crossfade, 0.0..=MAX_CROSSFADE)
+                    .custom_formatter(|v, _| percent(v)))
+                    .on_hover_text("Share of the loop blended across the loop point")
+                    .changed();
+                ui.end_row();
+            });
+        });
+    }).response.on_disabled_hover_text("Loops only play on gated notes");
+    ui.separator();
+
+    ui.strong("Amplitude");
+    changed |= synth::envelope_controls(ui, "sampler_envelope", &mut settings.envelope);
+
+    if ui.button("Reset").on_hover_text("Play the whole sample once, unchanged").clicked() {
+        *settings = SamplerSettings::default();
+        changed = true;
+    }
+    changed
+}
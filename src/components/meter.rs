@@ -0,0 +1,170 @@
+use egui::{Color32, Rect, Sense, Vec2};
+use remdaw_engine::meter::{gain_to_db, AtomicLevels, Levels, Meters, PeakHold, METER_FLOOR_DB};
+
+/// Length of the clip light at the end of a meter, in points
+const CLIP_LIGHT: f32 = 6.0;
+const GAP: f32 = 1.0;
+
+const BACKGROUND: Color32 = Color32::from_gray(25);
+const RMS_COLOR: Color32 = Color32::from_rgb(30, 120, 60);
+const HOLD_COLOR: Color32 = Color32::from_gray(220);
+const CLIP_COLOR: Color32 = Color32::from_rgb(230, 40, 40);
+const CLIP_OFF_COLOR: Color32 = Color32::from_gray(45);
+
+/// What one meter shows this frame
+#[derive(Clone, Copy, Default)]
+pub struct MeterDisplay {
+    pub rms: [f32; 2],
+    pub peaks: [PeakHold; 2],
+    pub clipped: bool,
+}
+
+impl MeterDisplay {
+    fn update(&mut self, levels: &AtomicLevels, dt: f32) {
+        let Levels { peak, rms } = levels.take();
+        for (hold, peak) in self.peaks.iter_mut().zip(peak) {
+            hold.update(peak, dt);
+        }
+        self.rms = rms;
+        self.clipped = levels.clipped();
+    }
+}
+
+/// Every meter's display. The engine's peaks are taken once per UI frame
+/// here, so the same meter can be drawn in several windows.
+#[derive(Default)]
+pub struct MeterDisplays {
+    pub master: MeterDisplay,
+    pub tracks: Vec<MeterDisplay>,
+    pub instruments: Vec<MeterDisplay>,
+    pub aux: Vec<MeterDisplay>,
+}
+
+impl MeterDisplays {
+    pub fn master_meter(&self, ui: &mut egui::Ui, meters: &Meters, size: Vec2) -> egui::Response {
+        stereo_meter(ui, &self.master, &meters.master, size)
+    }
+
+    pub fn track_meter(&self, ui: &mut egui::Ui, meters: &Meters, track: usize, size: Vec2) -> egui::Response {
+        match (self.tracks.get(track), meters.tracks.get(track)) {
+            (Some(display), Some(levels)) => stereo_meter(ui, display, levels, size),
+            _ => ui.allocate_exact_size(size, Sense::hover()).1,
+        }
+    }
+
+    pub fn instrument_meter(&self, ui: &mut egui::Ui, meters: &Meters, instrument: usize, size: Vec2) -> egui::Response {
+        match (self.instruments.get(instrument), meters.instruments.get(instrument)) {
+            (Some(display), Some(levels)) => stereo_meter(ui, display, levels, size),
+            _ => ui.allocate_exact_size(size, Sense::hover()).1,
+        }
+    }
+
+    pub fn aux_meter(&self, ui: &mut egui::Ui, meters: &Meters, bus: usize, size: Vec2) -> egui::Response {
+        match (self.aux.get(bus), meters.aux.get(bus)) {
+            (Some(display), Some(levels)) => stereo_meter(ui, display, levels, size),
+            _ => ui.allocate_exact_size(size, Sense::hover()).1,
+        }
+    }
+
+    /// Takes the peaks the engine collected since the last frame
+    pub fn update(&mut self, meters: &Meters, tracks: usize, instruments: usize, aux: usize, dt: f32) {
+        self.master.update(&meters.master, dt);
+
+        for (displays, levels, count) in [
+            (&mut self.tracks, &meters.tracks, tracks),
+            (&mut self.instruments, &meters.instruments, instruments),
+            (&mut self.aux, &meters.aux, aux),
+        ] {
+            displays.resize(count.min(levels.len()), MeterDisplay::default());
+            for (display, levels) in displays.iter_mut().zip(levels.iter()) {
+                display.update(levels, dt);
+            }
+        }
+    }
+}
+
+/// How far up the meter a level reaches, 0 at the floor and 1 at full scale
+fn fraction(gain: f32) -> f32 {
+    ((gain_to_db(gain) - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// Green in the safe range, yellow approaching full scale, red at it
+fn peak_color(gain: f32) -> Color32 {
+    let db = gain_to_db(gain);
+    if db >= -0.1 {
+        Color32::from_rgb(230, 40, 40)
+    } else if db >= -6.0 {
+        Color32::from_rgb(230, 200, 40)
+    } else {
+        Color32::from_rgb(60, 200, 90)
+    }
+}
+
+/// Stereo level meter with peak hold and a clip light. Horizontal when wider
+/// than tall. Clicking it resets the clip light.
+///
+/// # Arguments
+/// * `display` - Levels to draw
+/// * `levels` - The engine's meter the display was read from, to reset its clip light
+pub fn stereo_meter(ui: &mut egui::Ui, display: &MeterDisplay, levels: &AtomicLevels, size: Vec2) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+    let horizontal = size.x > size.y;
+
+    if response.clicked() {
+        levels.reset_clip();
+    }
+
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+    let painter = ui.painter();
+
+    // split off the clip light at the loud end
+    let (bars, light) = if horizontal {
+        let (bars, light) = rect.split_left_right_at_x(rect.right() - CLIP_LIGHT);
+        (bars.shrink2(Vec2::new(GAP / 2.0, 0.0)), light)
+    } else {
+        let (light, bars) = rect.split_top_bottom_at_y(rect.top() + CLIP_LIGHT);
+        (bars.shrink2(Vec2::new(0.0, GAP / 2.0)), light)
+    };
+    painter.rect_filled(light, 1.0, if display.clipped { CLIP_COLOR } else { CLIP_OFF_COLOR });
+
+    for channel in 0..2 {
+        // left channel on top or to the left
+        let bar = if horizontal {
+            let height = (bars.height() - GAP) / 2.0;
+            Rect::from_min_size(bars.min + Vec2::new(0.0, channel as f32 * (height + GAP)), Vec2::new(bars.width(), height))
+        } else {
+            let width = (bars.width() - GAP) / 2.0;
+            Rect::from_min_size(bars.min + Vec2::new(channel as f32 * (width + GAP), 0.0), Vec2::new(width, bars.height()))
+        };
+        painter.rect_filled(bar, 0.0, BACKGROUND);
+
+        // the part of the bar from silence up to `fraction` of full scale
+        let filled = |fraction: f32| if horizontal {
+            Rect::from_min_max(bar.min, egui::pos2(bar.left() + bar.width() * fraction, bar.bottom()))
+        } else {
+            Rect::from_min_max(egui::pos2(bar.left(), bar.bottom() - bar.height() * fraction), bar.max)
+        };
+
+        let hold = display.peaks[channel];
+        painter.rect_filled(filled(fraction(hold.peak)), 0.0, peak_color(hold.peak));
+        painter.rect_filled(filled(fraction(display.rms[channel])), 0.0, RMS_COLOR);
+
+        if hold.hold > 0.0 {
+            let edge = filled(fraction(hold.hold));
+            let line = if horizontal {
+                Rect::from_min_max(egui::pos2(edge.right() - 1.0, bar.top()), egui::pos2(edge.right(), bar.bottom()))
+            } else {
+                Rect::from_min_max(egui::pos2(bar.left(), edge.top()), egui::pos2(bar.right(), edge.top() + 1.0))
+            };
+            painter.rect_filled(line, 0.0, HOLD_COLOR);
+        }
+    }
+
+    response.on_hover_text(format!(
+        "Peak {:.1} dB, RMS {:.1} dB. Click to reset the clip light",
+        gain_to_db(display.peaks[0].hold.max(display.peaks[1].hold)),
+        gain_to_db(display.rms[0].max(display.rms[1])),
+    ))
+}
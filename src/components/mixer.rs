@@ -0,0 +1,196 @@
+use remdaw_engine::effects::{EffectKind, EffectSlot, EffectTarget};
+use remdaw_engine::mixer::ChannelMix;
+use remdaw_engine::routing::SendSource;
+use remdaw_engine::{AuxBus, Command};
+use crate::models::MyApp;
+
+/// Loudest a fader goes, as linear gain (about +6 dB)
+const MAX_VOLUME: f32 = 2.0;
+const STRIP_WIDTH: f32 = 70.0;
+const METER_SIZE: egui::Vec2 = egui::vec2(12.0, 110.0);
+
+/// Button that opens a channel's effects, highlighted while it has any
+fn effects_button(ui: &mut egui::Ui, count: usize, target: EffectTarget, open: &mut Option<EffectTarget>) {
+    let label = if count > 0 { format!("FX ({})", count) } else { "FX".to_string() };
+    if ui.selectable_label(*open == Some(target), label).on_hover_text("Effects").clicked() {
+        *open = if *open == Some(target) { None } else { Some(target) };
+    }
+}
+
+/// Button that opens a channel's sends
+fn sends_button(ui: &mut egui::Ui, count: usize, source: SendSource, open: &mut Option<SendSource>) {
+    let label = if count > 0 { format!("Sends ({})", count) } else { "Sends".to_string() };
+    if ui.selectable_label(*open == Some(source), label).on_hover_text("Send to aux buses").clicked() {
+        *open = if *open == Some(source) { None } else { Some(source) };
+    }
+}
+
+/// A new return bus, optionally with one effect set fully wet since the
+/// dry signal already reaches master through the channels
+fn new_aux_bus(number: usize, effect: Option<EffectKind>) -> AuxBus {
+    let name = effect.map_or_else(|| format!("Aux {}", number), |kind| kind.label().to_string());
+    let mut bus = AuxBus::new(name);
+    if let Some(kind) = effect {
+        let mut slot = EffectSlot::new(kind);
+        if let Some(mix) = kind.params().iter().position(|param| param.name == "Mix") {
+            slot.params[mix] = 1.0;
+        }
+        bus.effects.push(slot);
+    }
+    bus
+}
+
+/// Draws one channel strip: fader and meter, pan, and mute and solo toggles.
+/// Returns true when anything changed.
+fn strip(ui: &mut egui::Ui, name: &str, mix: &mut ChannelMix, meter: impl FnOnce(&mut egui::Ui)) -> bool {
+    let mut changed = false;
+
+    ui.vertical(|ui| {
+        ui.set_width(STRIP_WIDTH);
+        ui.add(egui::Label::new(egui::RichText::new(name).strong()).truncate());
+
+        ui.horizontal(|ui| {
+            changed |= ui.add(volume_slider(&mut mix.volume)).changed();
+            meter(ui);
+        });
+
+        changed |= ui.add(egui::Slider::new(&mut mix.pan, -1.0..=1.0)
+            .show_value(false))
+            .on_hover_text(pan_label(mix.pan))
+            .changed();
+        ui.small(pan_label(mix.pan));
+
+        ui.horizontal(|ui| {
+            changed |= ui.toggle_value(&mut mix.muted, "M").on_hover_text("Mute").changed();
+            changed |= ui.toggle_value(&mut mix.solo, "S").on_hover_text("Solo").changed();
+        });
+    });
+    changed
+}
+
+fn volume_slider(volume: &mut f32) -> egui::Slider<'_> {
+    egui::Slider::new(volume, 0.0..=MAX_VOLUME)
+        .vertical()
+        .custom_formatter(|volume, _| gain_label(volume))
+}
+
+/// A linear gain in dB
+pub fn gain_label(gain: f64) -> String {
+    if gain <= 0.0 {
+        "-inf dB".to_string()
+    } else {
+        format!("{:.1} dB", 20.0 * gain.log10())
+    }
+}
+
+pub fn pan_label(pan: f32) -> String {
+    match (pan * 100.0).round() as i32 {
+        0 => "C".to_string(),
+        p if p < 0 => format!("{}L", -p),
+        p => format!("{}R", p),
+    }
+}
+
+pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let mut commands = Vec::new();
+    let mut add_bus = None;
+    let mut remove_bus = None;
+    let displays = &app.ui_state.meters;
+    let meters = app.engine.meters();
+    let effects_target = &mut app.ui_state.effects_target;
+    let sends_source = &mut app.ui_state.sends_source;
+
+    egui::Window::new("Mixer")
+        .open(&mut app.ui_state.is_mixer_open)
+        .default_width(600.0)
+        .show(ctx, |ui| {
+            egui::ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    // master
+                    ui.vertical(|ui| {
+                        ui.set_width(STRIP_WIDTH);
+                        ui.label(egui::RichText::new("Master").strong());
+                        ui.horizontal(|ui| {
+                            if ui.add(volume_slider(&mut app.session.master_volume)).changed() {
+                                commands.push(Command::SetMasterVolume(app.session.master_volume));
+                            }
+                            displays.master_meter(ui, meters, METER_SIZE);
+                        });
+                        effects_button(ui, app.session.master_effects.len(), EffectTarget::Master, effects_target);
+                    });
+                    ui.separator();
+
+                    for (idx, track) in app.session.playlist.tracks.iter_mut().enumerate() {
+                        let meter = |ui: &mut egui::Ui| {
+                            displays.track_meter(ui, meters, idx, METER_SIZE);
+                        };
+                        ui.vertical(|ui| {
+                            if strip(ui, &track.name, &mut track.mix, meter) {
+                                commands.push(Command::SetTrackMix(idx, track.mix));
+                            }
+                            effects_button(ui, track.effects.len(), EffectTarget::Track(idx), effects_target);
+                            sends_button(ui, track.sends.len(), SendSource::Track(idx), sends_source);
+                        });
+                    }
+                    ui.separator();
+
+                    for (idx, instrument) in app.session.instruments.iter_mut().enumerate() {
+                        let meter = |ui: &mut egui::Ui| {
+                            displays.instrument_meter(ui, meters, idx, METER_SIZE);
+                        };
+                        ui.vertical(|ui| {
+                            if strip(ui, &instrument.name, &mut instrument.mix, meter) {
+                                commands.push(Command::SetInstrumentMix(idx, instrument.mix));
+                            }
+                            sends_button(ui, instrument.sends.len(), SendSource::Instrument(idx), sends_source);
+                        });
+                    }
+                    ui.separator();
+
+                    // returns
+                    for (idx, bus) in app.session.aux_buses.iter_mut().enumerate() {
+                        let meter = |ui: &mut egui::Ui| {
+                            displays.aux_meter(ui, meters, idx, METER_SIZE);
+                        };
+                        ui.vertical(|ui| {
+                            if strip(ui, &bus.name, &mut bus.mix, meter) {
+                                commands.push(Command::SetAuxMix(idx, bus.mix));
+                            }
+                            effects_button(ui, bus.effects.len(), EffectTarget::Aux(idx), effects_target);
+                            sends_button(ui, bus.sends.len(), SendSource::Aux(idx), sends_source);
+                            if ui.button("Remove").clicked() {
+                                remove_bus = Some(idx);
+                            }
+                        });
+                    }
+
+                    let number = app.session.aux_buses.len() + 1;
+                    ui.vertical(|ui| {
+                        ui.set_width(STRIP_WIDTH);
+                        ui.menu_button("+ Bus", |ui| {
+                            for (label, effect) in [
+                                ("Reverb", Some(EffectKind::Reverb)),
+                                ("Delay", Some(EffectKind::Delay)),
+                                ("Empty", None),
+                            ] {
+                                if ui.button(label).clicked() {
+                                    add_bus = Some(new_aux_bus(number, effect));
+                                    ui.close();
+                                }
+                            }
+                        }).response.on_hover_text("Add an aux bus to send to");
+                    });
+                });
+            });
+        });
+
+    for command in commands {
+        app.send(command);
+    }
+    if let Some(bus) = add_bus {
+        app.add_aux_bus(bus);
+    }
+    if let Some(index) = remove_bus {
+        app.remove_aux_bus(index);
+    }
+}
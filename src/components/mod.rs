@@ -2,8 +2,16 @@ pub mod toolbar;
 pub mod channel_rack;
 pub mod file_explorer;
 pub mod settings;
-pub mod file_information;
+pub mod effects;
+pub mod instrument;
+pub mod meter;
+pub mod mixer;
+pub mod notifications;
 pub mod patterns;
+pub mod piano_roll;
+pub mod sends;
+pub mod synth;
 pub mod playlist;
 pub mod popups;
-pub mod snap_to_grid;
\ No newline at end of file
+pub mod snap_to_grid;
+pub mod waveform;
\ No newline at end of file
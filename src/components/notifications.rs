@@ -0,0 +1,119 @@
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+use crate::models::MyApp;
+
+/// How long a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+/// Most toasts stacked at once; older ones are still in the log
+const MAX_TOASTS: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+pub struct Notification {
+    pub level: Level,
+    pub message: String,
+    pub time: Instant,
+}
+
+/// Messages for the user: shown as toasts when they arrive and kept in the log window
+#[derive(Default)]
+pub struct Notifications {
+    pub log: Vec<Notification>,
+    pub is_log_open: bool,
+}
+
+impl Notifications {
+    pub fn push(&mut self, level: Level, message: impl Into<String>) {
+        let message = message.into();
+        if level == Level::Error {
+            eprintln!("{}", message);
+        }
+        self.log.push(Notification { level, message, time: Instant::now() });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Level::Info, message);
+    }
+
+    pub fn error(&mut self, err: impl Display) {
+        self.push(Level::Error, err.to_string());
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.log.iter().filter(|n| n.level == Level::Error).count()
+    }
+}
+
+fn color(level: Level) -> egui::Color32 {
+    match level {
+        Level::Info => egui::Color32::from_rgb(80, 160, 255),
+        Level::Error => egui::Color32::from_rgb(255, 90, 80),
+    }
+}
+
+/// Draws recent toasts in the bottom right corner and the log window
+pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let notifications = &mut app.notifications;
+
+    let recent: Vec<&Notification> = notifications.log.iter()
+        .rev()
+        .take_while(|n| n.time.elapsed() < TOAST_DURATION)
+        .take(MAX_TOASTS)
+        .collect();
+
+    if !recent.is_empty() {
+        let mut open_log = false;
+
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for notification in recent {
+                    egui::Frame::popup(ui.style())
+                        .stroke(egui::Stroke::new(1.0, color(notification.level)))
+                        .show(ui, |ui| {
+                            ui.set_max_width(360.0);
+                            let label = egui::Label::new(&notification.message).sense(egui::Sense::click());
+                            if ui.add(label).on_hover_text("Open the log").clicked() {
+                                open_log = true;
+                            }
+                        });
+                }
+            });
+
+        if open_log {
+            notifications.is_log_open = true;
+        }
+        // keep repainting so toasts disappear on time
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+
+    let mut clear = false;
+    egui::Window::new("Log")
+        .open(&mut notifications.is_log_open)
+        .default_size([480.0, 240.0])
+        .show(ctx, |ui| {
+            if ui.button("Clear").clicked() {
+                clear = true;
+            }
+            ui.separator();
+
+            egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                for notification in &notifications.log {
+                    let age = notification.time.elapsed().as_secs();
+                    ui.horizontal_wrapped(|ui| {
+                        ui.colored_label(color(notification.level), format!("{}s ago", age));
+                        ui.label(&notification.message);
+                    });
+                }
+            });
+        });
+
+    if clear {
+        notifications.log.clear();
+    }
+}
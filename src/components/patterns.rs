@@ -1,10 +1,12 @@
 use eframe::emath;
-use crate::components::popups::rename_pattern;
-use crate::models::{MyApp, Pattern};
+use crate::models::{MyApp, NotePattern, Pattern};
 
 pub fn render(app: &mut MyApp, ctx: &egui::Context) {
     let mut pattern_to_load: Option<usize> = None;
     let mut should_add_pattern = false;
+    let mut should_add_note_pattern = false;
+    let mut note_pattern_to_open: Option<usize> = None;
+    let mut note_pattern_to_remove: Option<usize> = None;
 
     egui::SidePanel::left("patterns")
         .resizable(true)
@@ -15,12 +17,9 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
             ui.separator();
 
             ui.vertical_centered(|ui| {
-                let patterns = {
-                    let state = app.audio_state.lock().unwrap();
-                    state.patterns.clone()
-                };
+                let patterns = app.session.patterns.clone();
 
-                for (idx, mut pattern) in patterns.iter().enumerate() {
+                for (idx, pattern) in patterns.iter().enumerate() {
                     let (rect, response) = ui.allocate_exact_size(
                         emath::vec2(100.0, 25.0),
                         egui::Sense::click_and_drag()
@@ -71,7 +70,8 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
                     response.context_menu(|ui| {
                         if ui.button("Delete").clicked() {
                             // Handle delete
-                            app.audio_state.lock().unwrap().patterns.remove(idx);
+                            app.session.patterns.remove(idx);
+                            app.sync_patterns();
                             ui.close();
                         }
                         if ui.button("Rename").clicked() {
@@ -83,8 +83,8 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
                         }
                         if ui.button("Duplicate").clicked() {
                             // Handle duplicate
-                            let mut state = app.audio_state.lock().unwrap();
-                            state.patterns.push(pattern.clone());
+                            app.session.patterns.push(pattern.clone());
+                            app.sync_patterns();
                             ui.close();
                         }
                         if ui.button("Open").clicked() {
@@ -102,34 +102,94 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
                     should_add_pattern = true;
                 }
             });
-        });
 
-    if should_add_pattern || pattern_to_load.is_some() {
-        let mut state = app.audio_state.lock().unwrap();
+            ui.add_space(12.0);
+            ui.label(egui::RichText::new("Notes").strong().size(20.0));
+            ui.separator();
+
+            ui.vertical_centered(|ui| {
+                for (idx, pattern) in app.session.note_patterns.iter().enumerate() {
+                    let (rect, response) = ui.allocate_exact_size(
+                        emath::vec2(100.0, 25.0),
+                        egui::Sense::click_and_drag()
+                    );
+
+                    let is_being_dragged = ctx.memory(|mem| {
+                        mem.data.get_temp::<usize>(egui::Id::new("dragging_note_pattern")) == Some(idx)
+                    });
+                    let color = if is_being_dragged {
+                        egui::Color32::from_rgb(90, 170, 110)
+                    } else if response.hovered() {
+                        egui::Color32::from_gray(80)
+                    } else {
+                        egui::Color32::from_gray(60)
+                    };
+                    ui.painter().rect_filled(rect, 3.0, color);
+                    ui.painter().text(
+                        rect.center(),
+                        egui::Align2::CENTER_CENTER,
+                        &pattern.name,
+                        egui::FontId::default(),
+                        egui::Color32::WHITE
+                    );
+
+                    // dropped on the playlist as a clip
+                    if response.drag_started() {
+                        ctx.memory_mut(|mem| {
+                            mem.data.insert_temp(egui::Id::new("dragging_note_pattern"), idx);
+                        });
+                    }
+                    if response.clicked() {
+                        note_pattern_to_open = Some(idx);
+                    }
+
+                    response.context_menu(|ui| {
+                        if ui.button("Open").clicked() {
+                            note_pattern_to_open = Some(idx);
+                            ui.close();
+                        }
+                        if ui.button("Delete").clicked() {
+                            note_pattern_to_remove = Some(idx);
+                            ui.close();
+                        }
+                    });
 
-        if should_add_pattern {
-            let num = state.patterns.len() + 1;
-            // Create a blank pattern with the same number of instruments as current
-            let num_instruments = state.instruments.len();
-            let blank_pattern = vec![vec![false; 16]; num_instruments];
+                    ui.add_space(4.0);
+                }
 
-            state.patterns.push(Pattern {
-                name: format!("Pattern {}", num),
-                data: blank_pattern,
+                ui.add_space(4.0);
+                let add_button = egui::Button::new("+").min_size(emath::vec2(100.0, 20.0));
+                if ui.add(add_button).on_hover_text("New note pattern").clicked() {
+                    should_add_note_pattern = true;
+                }
             });
-        }
-
-        if let Some(idx) = pattern_to_load {
-            // FIRST: Save the current pattern before switching
-            if let Some(current_idx) = state.current_pattern_index {
-                state.patterns[current_idx].data = state.pattern.clone();
-            }
-
-            // THEN: Load the new pattern
-            if let Some(pattern) = state.patterns.get(idx) {
-                state.pattern = pattern.data.clone();
-                state.current_pattern_index = Some(idx); // Update which pattern we're editing
-            }
-        }
+        });
+
+    if should_add_note_pattern {
+        let num = app.session.note_patterns.len() + 1;
+        app.session.note_patterns.push(NotePattern::new(format!("Notes {}", num), 0));
+        app.sync_note_patterns();
+        note_pattern_to_open = Some(num - 1);
+    }
+    if let Some(idx) = note_pattern_to_remove {
+        app.remove_note_pattern(idx);
+    }
+    if let Some(idx) = note_pattern_to_open
+        && idx < app.session.note_patterns.len() {
+        app.ui_state.piano_roll.open(idx);
+    }
+
+    if should_add_pattern {
+        let num = app.session.patterns.len() + 1;
+        // Create a blank pattern with the same number of instruments as current
+        let num_instruments = app.session.instruments.len();
+        app.session.patterns.push(Pattern::empty(format!("Pattern {}", num), num_instruments));
+        app.sync_patterns();
+    }
+
+    // Show the clicked pattern in the channel rack
+    if let Some(idx) = pattern_to_load
+        && idx < app.session.patterns.len() {
+        app.ui_state.current_pattern_index = Some(idx);
     }
 }
\ No newline at end of file
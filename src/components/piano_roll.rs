@@ -0,0 +1,279 @@
+use egui::{Align, Color32, Pos2, Rect, Sense, Stroke, Vec2};
+use remdaw_engine::models::{key_name, DEFAULT_ROOT_KEY};
+use remdaw_engine::{Command, Note, StepResolution};
+use crate::models::MyApp;
+
+const KEY_HEIGHT: f32 = 12.0;
+const KEYBOARD_WIDTH: f32 = 50.0;
+const PIXELS_PER_BEAT: f32 = 80.0;
+const KEYS: u8 = 128;
+/// How close to a note's right end a drag resizes it instead of moving it
+const EDGE_GRAB: f32 = 6.0;
+
+const WHITE_ROW: Color32 = Color32::from_gray(45);
+const BLACK_ROW: Color32 = Color32::from_gray(35);
+const NOTE_COLOR: Color32 = Color32::from_rgb(90, 200, 120);
+
+/// What a drag in the note area is doing
+#[derive(Clone, Copy, Debug)]
+pub enum NoteDrag {
+    Move { note: usize, grab: f64 }, // grab is where in the note it was picked up, in beats
+    Resize(usize),
+}
+
+/// Editing state of the piano roll window
+#[derive(Default)]
+pub struct PianoRollState {
+    pub pattern: Option<usize>, // note pattern being edited, None when closed
+    pub grid: StepResolution,   // what note starts and lengths snap to
+    pub selected: Option<usize>,
+    pub drag: Option<NoteDrag>,
+    pub held_key: Option<u8>, // key held down on the keyboard
+    pub scroll_to_notes: bool, // scroll to the notes next frame, set when opening
+}
+
+impl PianoRollState {
+    pub fn open(&mut self, pattern: usize) {
+        self.pattern = Some(pattern);
+        self.selected = None;
+        self.drag = None;
+        self.scroll_to_notes = true;
+    }
+}
+
+fn is_black(key: u8) -> bool {
+    matches!(key % 12, 1 | 3 | 6 | 8 | 10)
+}
+
+/// Edits the notes of a note pattern on a grid of keys against time.
+/// Click to add a note, drag to move it or its right end to resize it,
+/// right click to delete. The keys on the left play the instrument.
+pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(index) = app.ui_state.piano_roll.pattern else {
+        return;
+    };
+    let Some(name) = app.session.note_patterns.get(index).map(|pattern| pattern.name.clone()) else {
+        app.ui_state.piano_roll.pattern = None;
+        return;
+    };
+
+    let mut open = true;
+    let mut changed = false;
+    let mut instruments_changed = false;
+    let mut key_commands = Vec::new(); // sent once the window lets go of the session
+    let bar_length = app.session.time_signature.bar_length();
+
+    egui::Window::new(format!("Piano Roll: {}", name))
+        .id(egui::Id::new("piano_roll"))
+        .open(&mut open)
+        .default_size([700.0, 400.0])
+        .show(ctx, |ui| {
+            let state = &mut app.ui_state.piano_roll;
+            let session = &mut app.session;
+            let pattern = &mut session.note_patterns[index];
+
+            ui.horizontal(|ui| {
+                ui.label("Instrument:");
+                let selected = session.instruments.get(pattern.instrument).map_or("None", |instrument| instrument.name.as_str());
+                egui::ComboBox::from_id_salt("piano_roll_instrument")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (idx, instrument) in session.instruments.iter().enumerate() {
+                            changed |= ui.selectable_value(&mut pattern.instrument, idx, &instrument.name).changed();
+                        }
+                    });
+
+                if let Some(instrument) = session.instruments.get_mut(pattern.instrument) {
+                    ui.label("Root:");
+                    instruments_changed |= ui.add(egui::DragValue::new(&mut instrument.root_key)
+                        .range(0..=KEYS - 1)
+                        .custom_formatter(|key, _| key_name(key as u8)))
+                        .on_hover_text("Key the sample plays at its own pitch")
+                        .changed();
+                }
+
+                ui.label("Length:");
+                changed |= ui.add(egui::DragValue::new(&mut pattern.length)
+                    .speed(0.25)
+                    .range(0.25..=256.0)
+                    .suffix(" beats"))
+                    .changed();
+
+                ui.label("Grid:");
+                egui::ComboBox::from_id_salt("piano_roll_grid")
+                    .selected_text(state.grid.label())
+                    .show_ui(ui, |ui| {
+                        for resolution in StepResolution::ALL {
+                            ui.selectable_value(&mut state.grid, resolution, resolution.label());
+                        }
+                    });
+
+                if let Some(note) = state.selected.and_then(|selected| pattern.notes.get_mut(selected)) {
+                    ui.label("Velocity:");
+                    changed |= ui.add(egui::Slider::new(&mut note.velocity, 0.0..=1.0)
+                        .custom_formatter(|velocity, _| format!("{:.0}%", velocity * 100.0)))
+                        .changed();
+                }
+            });
+            ui.separator();
+
+            let grid = 1.0 / state.grid.steps_per_beat();
+            let snap = |beat: f64| (beat / grid).floor() * grid;
+
+            egui::ScrollArea::both().id_salt("piano_roll_scroll").show(ui, |ui| {
+                let size = Vec2::new(
+                    KEYBOARD_WIDTH + pattern.length as f32 * PIXELS_PER_BEAT,
+                    KEYS as f32 * KEY_HEIGHT,
+                );
+                let (response, painter) = ui.allocate_painter(size, Sense::click_and_drag());
+                let rect = response.rect;
+                let grid_left = rect.left() + KEYBOARD_WIDTH;
+
+                let key_top = |key: u8| rect.top() + (KEYS - 1 - key) as f32 * KEY_HEIGHT;
+                let beat_x = |beat: f64| grid_left + beat as f32 * PIXELS_PER_BEAT;
+                let key_at = |y: f32| (KEYS as f32 - 1.0 - ((y - rect.top()) / KEY_HEIGHT).floor()).clamp(0.0, KEYS as f32 - 1.0) as u8;
+                let beat_at = |x: f32| ((x - grid_left) / PIXELS_PER_BEAT).max(0.0) as f64;
+                let note_rect = |note: &Note| Rect::from_min_max(
+                    Pos2::new(beat_x(note.start), key_top(note.key)),
+                    Pos2::new(beat_x(note.start + note.length), key_top(note.key) + KEY_HEIGHT),
+                );
+                let note_at = |notes: &[Note], pos: Pos2| notes.iter().rposition(|note| note_rect(note).contains(pos));
+
+                if state.scroll_to_notes {
+                    state.scroll_to_notes = false;
+                    let key = pattern.notes.first().map_or(DEFAULT_ROOT_KEY, |note| note.key);
+                    let row = Rect::from_min_size(Pos2::new(rect.left(), key_top(key)), Vec2::new(1.0, KEY_HEIGHT));
+                    ui.scroll_to_rect(row, Some(Align::Center));
+                }
+
+                // rows, with the black keys darker
+                for key in 0..KEYS {
+                    let row = Rect::from_min_size(Pos2::new(grid_left, key_top(key)), Vec2::new(rect.right() - grid_left, KEY_HEIGHT));
+                    painter.rect_filled(row, 0.0, if is_black(key) { BLACK_ROW } else { WHITE_ROW });
+                }
+
+                // grid lines, stronger on beats and bars
+                let lines = (pattern.length / grid).ceil() as usize;
+                for line in 0..=lines {
+                    let beat = line as f64 * grid;
+                    let color = if (beat / bar_length).fract().abs() < 1e-9 {
+                        Color32::from_gray(140)
+                    } else if beat.fract().abs() < 1e-9 {
+                        Color32::from_gray(90)
+                    } else {
+                        Color32::from_gray(60)
+                    };
+                    painter.vline(beat_x(beat.min(pattern.length)), rect.y_range(), Stroke::new(1.0, color));
+                }
+
+                for (idx, note) in pattern.notes.iter().enumerate() {
+                    let fill = NOTE_COLOR.gamma_multiply(0.4 + 0.6 * note.velocity);
+                    let note_rect = note_rect(note).shrink(0.5);
+                    painter.rect_filled(note_rect, 2.0, fill);
+                    if state.selected == Some(idx) {
+                        painter.rect_stroke(note_rect, 2.0, Stroke::new(1.5, Color32::WHITE), egui::StrokeKind::Inside);
+                    }
+                }
+
+                // keyboard
+                for key in 0..KEYS {
+                    let key_rect = Rect::from_min_size(Pos2::new(rect.left(), key_top(key)), Vec2::new(KEYBOARD_WIDTH, KEY_HEIGHT));
+                    let held = state.held_key == Some(key);
+                    let fill = match (held, is_black(key)) {
+                        (true, _) => Color32::from_rgb(0, 200, 255),
+                        (false, true) => Color32::from_gray(20),
+                        (false, false) => Color32::from_gray(220),
+                    };
+                    painter.rect_filled(key_rect.shrink2(Vec2::new(0.0, 0.5)), 0.0, fill);
+                    if key % 12 == 0 {
+                        painter.text(key_rect.right_center() - Vec2::new(3.0, 0.0), egui::Align2::RIGHT_CENTER,
+                            key_name(key), egui::FontId::proportional(9.0), Color32::BLACK);
+                    }
+                }
+
+                let pointer = response.interact_pointer_pos();
+                let on_keyboard = pointer.is_some_and(|pos| pos.x < grid_left);
+
+                // the keys play while held down
+                let pressed_key = pointer
+                    .filter(|_| on_keyboard && response.is_pointer_button_down_on())
+                    .map(|pos| key_at(pos.y));
+                if pressed_key != state.held_key {
+                    if let Some(key) = state.held_key {
+                        key_commands.push(Command::ReleaseNote { instrument: pattern.instrument, key });
+                    }
+                    if let Some(key) = pressed_key {
+                        key_commands.push(Command::TriggerNote { instrument: pattern.instrument, key });
+                    }
+                    state.held_key = pressed_key;
+                }
+
+                if let Some(pos) = pointer.filter(|_| !on_keyboard) {
+                    if response.secondary_clicked() {
+                        if let Some(idx) = note_at(&pattern.notes, pos) {
+                            pattern.notes.remove(idx);
+                            state.selected = None;
+                            changed = true;
+                        }
+                    } else if response.clicked() {
+                        match note_at(&pattern.notes, pos) {
+                            Some(idx) => state.selected = Some(idx),
+                            None => {
+                                let start = snap(beat_at(pos.x));
+                                if start < pattern.length {
+                                    pattern.notes.push(Note { key: key_at(pos.y), start, length: grid, velocity: 0.8 });
+                                    state.selected = Some(pattern.notes.len() - 1);
+                                    changed = true;
+                                }
+                            }
+                        }
+                    }
+
+                    if response.drag_started() {
+                        let origin = ui.input(|i| i.pointer.press_origin()).unwrap_or(pos);
+                        state.drag = note_at(&pattern.notes, origin).map(|idx| {
+                            let note = &pattern.notes[idx];
+                            state.selected = Some(idx);
+                            if note_rect(note).right() - origin.x < EDGE_GRAB {
+                                NoteDrag::Resize(idx)
+                            } else {
+                                NoteDrag::Move { note: idx, grab: beat_at(origin.x) - note.start }
+                            }
+                        });
+                    }
+                    match state.drag {
+                        Some(NoteDrag::Move { note, grab }) if response.dragged() => {
+                            if let Some(note) = pattern.notes.get_mut(note) {
+                                let start = ((beat_at(pos.x) - grab) / grid).round() * grid;
+                                note.start = start.clamp(0.0, (pattern.length - grid).max(0.0));
+                                note.key = key_at(pos.y);
+                            }
+                        }
+                        Some(NoteDrag::Resize(note)) if response.dragged() => {
+                            if let Some(note) = pattern.notes.get_mut(note) {
+                                let end = (beat_at(pos.x) / grid).round() * grid;
+                                note.length = (end - note.start).max(grid);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if response.drag_stopped() && state.drag.take().is_some() {
+                    changed = true;
+                }
+            });
+        });
+
+    for command in key_commands {
+        app.send(command);
+    }
+    if changed {
+        app.sync_note_patterns();
+    }
+    if instruments_changed {
+        app.sync_instruments();
+    }
+    if !open {
+        app.ui_state.piano_roll.pattern = None;
+    }
+}
@@ -1,6 +1,7 @@
 // src/components/playlist/config.rs
 
 use eframe::epaint::Color32;
+use remdaw_engine::TimeSignature;
 
 /// Visual configuration for the playlist
 pub struct PlaylistConfig {
@@ -9,18 +10,19 @@ pub struct PlaylistConfig {
     pub timeline_header_height: f32,
     pub track_height: f32,
     pub clip_vertical_padding: f32,
+    pub mute_button_offset: f32, // from the left edge to the button's centre
+    pub mute_button_radius: f32,
 
     // Grid
     pub pixels_per_beat: f32,
-    pub beats_per_bar: i32,
+    pub beats_per_bar: i32, // beats of the time signature, not quarter notes
+    pub beat_length: f32,   // in quarter note beats
 
     // Resize
     pub edge_grab_distance: f32,
     pub min_clip_length: f64,
 
     // Preview
-    pub preview_dash_length: f32,
-    pub preview_gap_length: f32,
     pub preview_default_length: f32,
 
     // Colors - Timeline
@@ -34,12 +36,15 @@ pub struct PlaylistConfig {
     pub track_odd_bg: Color32,
     pub track_text_color: Color32,
     pub mute_button_color: Color32,
+    pub mute_button_muted_color: Color32,
     pub mute_button_outline: Color32,
 
     // Colors - Clips
     pub pattern_clip_color: Color32,
     pub audio_clip_color: Color32,
+    pub notes_clip_color: Color32,
     pub clip_text_color: Color32,
+    pub waveform_color: Color32,
     pub clip_corner_radius: f32,
 
     // Colors - Playhead
@@ -61,18 +66,19 @@ impl Default for PlaylistConfig {
             timeline_header_height: 50.0,
             track_height: 60.0,
             clip_vertical_padding: 5.0,
+            mute_button_offset: 15.0,
+            mute_button_radius: 10.0,
 
             // Grid
             pixels_per_beat: 100.0,
             beats_per_bar: 4,
+            beat_length: 1.0,
 
             // Resize
             edge_grab_distance: 8.0,
             min_clip_length: 0.25,
 
             // Preview
-            preview_dash_length: 8.0,
-            preview_gap_length: 4.0,
             preview_default_length: 4.0,
 
             // Colors - Timeline
@@ -85,13 +91,16 @@ impl Default for PlaylistConfig {
             track_even_bg: Color32::from_gray(40),
             track_odd_bg: Color32::from_gray(50),
             track_text_color: Color32::WHITE,
-            mute_button_color: Color32::from_rgb(155, 0, 0),
+            mute_button_color: Color32::from_rgb(0, 155, 60),
+            mute_button_muted_color: Color32::from_rgb(155, 0, 0),
             mute_button_outline: Color32::WHITE,
 
             // Colors - Clips
             pattern_clip_color: Color32::from_rgb(80, 120, 200),
             audio_clip_color: Color32::from_rgb(200, 120, 80),
+            notes_clip_color: Color32::from_rgb(90, 170, 110),
             clip_text_color: Color32::WHITE,
+            waveform_color: Color32::from_black_alpha(140),
             clip_corner_radius: 5.0,
 
             // Colors - Playhead
@@ -105,4 +114,15 @@ impl Default for PlaylistConfig {
             preview_outline_alpha: 180,
         }
     }
-}
\ No newline at end of file
+}
+
+impl PlaylistConfig {
+    /// Default layout with the grid split into bars of `signature`
+    pub fn for_time_signature(signature: TimeSignature) -> Self {
+        Self {
+            beats_per_bar: signature.numerator as i32,
+            beat_length: signature.beat_length() as f32,
+            ..Self::default()
+        }
+    }
+}
@@ -3,7 +3,7 @@
 use std::path::PathBuf;
 use egui::{Context, Pos2, Rect, Id};
 use crate::models::{MyApp, PlacedClip, ClipType, Instrument};
-use crate::audio::path_to_vector;
+use remdaw_engine::decoder::load_sample;
 use super::config::PlaylistConfig;
 
 pub fn handle_pattern_drop(
@@ -26,20 +26,21 @@ pub fn handle_pattern_drop(
             let relative_x = pointer_pos.x - timeline_start_x;
             let start_beat = (relative_x / config.pixels_per_beat).max(0.0).round();
 
-            let mut state = app.audio_state.lock().unwrap();
-            if track_idx < state.playlist.tracks.len() {
-                let name = state.patterns.get(pattern_idx)
-                    .map(|p| p.name.clone())
-                    .unwrap_or_else(|| "Unknown".to_string());
+            if track_idx < app.session.playlist.tracks.len() {
+                let (name, length) = app.session.patterns.get(pattern_idx)
+                    .map(|p| (p.name.clone(), p.length()))
+                    .unwrap_or_else(|| ("Unknown".to_string(), config.preview_default_length as f64));
 
-                state.playlist.clips.push(PlacedClip {
+                app.session.playlist.clips.push(PlacedClip {
                     clip_type: ClipType::Pattern(pattern_idx),
                     track_index: track_idx,
                     start_time: start_beat as f64,
                     name,
-                    length: config.preview_default_length as f64,
-                    color: config.pattern_clip_color,
+                    length,
+                    color: config.pattern_clip_color.to_array(),
+                    offset: 0.0,
                 });
+                app.sync_clips();
             }
         }
 
@@ -49,6 +50,47 @@ pub fn handle_pattern_drop(
     }
 }
 
+pub fn handle_note_pattern_drop(
+    app: &mut MyApp,
+    ctx: &Context,
+    pointer_pos: Pos2,
+    rect: Rect,
+    config: &PlaylistConfig,
+) {
+    if let Some(pattern_idx) = ctx.memory(|mem| {
+        mem.data.get_temp::<usize>(Id::new("dragging_note_pattern"))
+    }) {
+        if rect.contains(pointer_pos) {
+            let timeline_start_x = rect.left() + config.track_label_width;
+            let tracks_start_y = rect.top() + config.timeline_header_height;
+
+            let relative_y = pointer_pos.y - tracks_start_y;
+            let track_idx = (relative_y / config.track_height).floor() as usize;
+
+            let relative_x = pointer_pos.x - timeline_start_x;
+            let start_beat = (relative_x / config.pixels_per_beat).max(0.0).round();
+
+            if track_idx < app.session.playlist.tracks.len()
+                && let Some(pattern) = app.session.note_patterns.get(pattern_idx) {
+                app.session.playlist.clips.push(PlacedClip {
+                    clip_type: ClipType::Notes(pattern_idx),
+                    track_index: track_idx,
+                    start_time: start_beat as f64,
+                    name: pattern.name.clone(),
+                    length: pattern.length,
+                    color: config.notes_clip_color.to_array(),
+                    offset: 0.0,
+                });
+                app.sync_clips();
+            }
+        }
+
+        ctx.memory_mut(|mem| {
+            mem.data.remove::<usize>(Id::new("dragging_note_pattern"));
+        });
+    }
+}
+
 pub fn handle_audio_drop(
     app: &mut MyApp,
     ctx: &Context,
@@ -69,33 +111,28 @@ pub fn handle_audio_drop(
             let relative_x = pointer_pos.x - timeline_start_x;
             let start_beat = (relative_x / config.pixels_per_beat).max(0.0).round();
 
-            let mut state = app.audio_state.lock().unwrap();
-            if track_idx < state.playlist.tracks.len() {
-                let samples = path_to_vector(file_path.to_str().unwrap());
-                let name = file_path.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("Unknown")
-                    .to_string();
-
-                state.instruments.push(Instrument {
-                    name: name.clone(),
-                    file_path: file_path.clone(),
-                    samples,
-                    position: 0,
-                    is_playing: false,
-                });
-                state.pattern.push(vec![false; 16]);
-
-                let instrument_idx = state.instruments.len() - 1;
+            if track_idx < app.session.playlist.tracks.len() {
+                match load_sample(&file_path) {
+                    Ok(sample) => {
+                        let instrument = Instrument::new(file_path.clone(), sample);
+                        let name = instrument.name.clone();
+                        // as long as the sample plays for
+                        let length = instrument.length_in_beats(app.session.bpm);
+                        let instrument_idx = app.add_instrument(instrument);
 
-                state.playlist.clips.push(PlacedClip {
-                    clip_type: ClipType::AudioFile(instrument_idx),
-                    track_index: track_idx,
-                    start_time: start_beat as f64,
-                    name,
-                    length: config.preview_default_length as f64,
-                    color: config.audio_clip_color,
-                });
+                        app.session.playlist.clips.push(PlacedClip {
+                            clip_type: ClipType::AudioFile(instrument_idx),
+                            track_index: track_idx,
+                            start_time: start_beat as f64,
+                            name,
+                            length: if length > 0.0 { length } else { config.preview_default_length as f64 },
+                            color: config.audio_clip_color.to_array(),
+                            offset: 0.0,
+                        });
+                        app.sync_clips();
+                    }
+                    Err(err) => app.notifications.error(err),
+                }
             }
         }
 
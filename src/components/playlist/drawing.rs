@@ -2,7 +2,9 @@
 
 use eframe::epaint::{Color32, Stroke, Rect, Pos2, FontId, Vec2};
 use egui::{Align2, Painter};
-use crate::models::{Playlist};
+use remdaw_engine::{ClipType, Session};
+use crate::components::waveform;
+use crate::models::Playlist;
 use super::config::PlaylistConfig;
 
 pub fn draw_timeline_header(
@@ -29,9 +31,11 @@ pub fn draw_beat_markers(
     max_beats: i32,
 ) {
     let timeline_start_x = rect.left() + config.track_label_width;
+    // ticks fall on the beats of the time signature, so eighths in 7/8
+    let ticks = (max_beats as f32 / config.beat_length).ceil() as i32;
 
-    for beat in 0..max_beats {
-        let x = timeline_start_x + (beat as f32 * config.pixels_per_beat);
+    for beat in 0..ticks {
+        let x = timeline_start_x + (beat as f32 * config.beat_length * config.pixels_per_beat);
 
         if x > rect.right() {
             break;
@@ -79,11 +83,11 @@ pub fn draw_tracks(
             if idx % 2 == 0 { config.track_even_bg } else { config.track_odd_bg }
         );
 
-        // Mute button
+        // Mute button, lit while the track plays
         painter.circle(
-            Pos2::new(rect.left() + 15.0, y + config.track_height / 2.0),
-            10.0,
-            config.mute_button_color,
+            Pos2::new(rect.left() + config.mute_button_offset, y + config.track_height / 2.0),
+            config.mute_button_radius,
+            if track.mix.muted { config.mute_button_muted_color } else { config.mute_button_color },
             Stroke::new(1.0, config.mute_button_outline)
         );
 
@@ -97,16 +101,17 @@ pub fn draw_tracks(
     }
 }
 
+/// Draws every clip, with the waveform of its sample inside an audio clip
 pub fn draw_clips(
     painter: &Painter,
     rect: Rect,
-    playlist: &Playlist,
+    session: &Session,
     config: &PlaylistConfig,
 ) {
     let timeline_start_x = rect.left() + config.track_label_width;
     let tracks_start_y = rect.top() + config.timeline_header_height;
 
-    for clip in &playlist.clips {
+    for clip in &session.playlist.clips {
         let y = tracks_start_y + clip.track_index as f32 * config.track_height;
         let x = timeline_start_x + (clip.start_time as f32 * config.pixels_per_beat);
         let width = clip.length as f32 * config.pixels_per_beat;
@@ -116,7 +121,18 @@ pub fn draw_clips(
             Vec2::new(width, config.track_height - config.clip_vertical_padding * 2.0)
         );
 
-        painter.rect_filled(clip_rect, config.clip_corner_radius, clip.color);
+        let [r, g, b, a] = clip.color;
+        painter.rect_filled(clip_rect, config.clip_corner_radius, Color32::from_rgba_premultiplied(r, g, b, a));
+
+        if let ClipType::AudioFile(instrument) = clip.clip_type
+            && let Some(instrument) = session.instruments.get(instrument) {
+            let sample = &instrument.sample;
+            let region = instrument.sampler.region(sample.len());
+            let frames_per_beat = sample.sample_rate as f64 * 60.0 / session.bpm as f64;
+            let frames_per_pixel = frames_per_beat / config.pixels_per_beat as f64;
+            let clip_painter = painter.with_clip_rect(clip_rect.intersect(painter.clip_rect()));
+            waveform::draw_region(&clip_painter, clip_rect.shrink2(Vec2::new(0.0, 2.0)), sample, &region, clip.offset * frames_per_beat, frames_per_pixel, config.waveform_color);
+        }
 
         painter.text(
             Pos2::new(x + 5.0, y + config.track_height / 2.0),
@@ -143,56 +159,3 @@ pub fn draw_playhead(
         Stroke::new(config.playhead_width, config.playhead_color)
     );
 }
-
-pub fn draw_dashed_rect(
-    painter: &Painter,
-    rect: Rect,
-    color: Color32,
-    config: &PlaylistConfig,
-) {
-    let total_length = config.preview_dash_length + config.preview_gap_length;
-
-    // Top edge
-    let mut x = rect.left();
-    while x < rect.right() {
-        let end_x = (x + config.preview_dash_length).min(rect.right());
-        painter.line_segment(
-            [Pos2::new(x, rect.top()), Pos2::new(end_x, rect.top())],
-            Stroke::new(2.0, color)
-        );
-        x += total_length;
-    }
-
-    // Bottom edge
-    let mut x = rect.left();
-    while x < rect.right() {
-        let end_x = (x + config.preview_dash_length).min(rect.right());
-        painter.line_segment(
-            [Pos2::new(x, rect.bottom()), Pos2::new(end_x, rect.bottom())],
-            Stroke::new(2.0, color)
-        );
-        x += total_length;
-    }
-
-    // Left edge
-    let mut y = rect.top();
-    while y < rect.bottom() {
-        let end_y = (y + config.preview_dash_length).min(rect.bottom());
-        painter.line_segment(
-            [Pos2::new(rect.left(), y), Pos2::new(rect.left(), end_y)],
-            Stroke::new(2.0, color)
-        );
-        y += total_length;
-    }
-
-    // Right edge
-    let mut y = rect.top();
-    while y < rect.bottom() {
-        let end_y = (y + config.preview_dash_length).min(rect.bottom());
-        painter.line_segment(
-            [Pos2::new(rect.right(), y), Pos2::new(rect.right(), end_y)],
-            Stroke::new(2.0, color)
-        );
-        y += total_length;
-    }
-}
\ No newline at end of file
@@ -1,6 +1,7 @@
 // src/components/playlist/input.rs
 
-use egui::{Context, Response, Rect, Pos2, CursorIcon};
+use egui::{Context, Rect, Pos2, CursorIcon};
+use remdaw_engine::Command;
 use crate::models::MyApp;
 use super::config::PlaylistConfig;
 use super::{drag_drop, resize};
@@ -9,7 +10,6 @@ use super::{drag_drop, resize};
 pub fn handle_input(
     app: &mut MyApp,
     ctx: &Context,
-    response: &Response,
     rect: Rect,
     pointer_pos: Option<Pos2>,
     config: &PlaylistConfig,
@@ -18,17 +18,26 @@ pub fn handle_input(
     let pointer_released = ctx.input(|i| i.pointer.any_released());
     let pointer_down = ctx.input(|i| i.pointer.primary_down());
 
+    // Toggle a track's mute button
+    if ctx.input(|i| i.pointer.primary_clicked())
+        && let Some(pointer_pos) = pointer_pos
+        && let Some(track_idx) = mute_button_at(app, pointer_pos, rect, config) {
+        let track = &mut app.session.playlist.tracks[track_idx];
+        track.mix.muted = !track.mix.muted;
+        let mix = track.mix;
+        app.send(Command::SetTrackMix(track_idx, mix));
+    }
+
     // Handle resize ending
     if pointer_released && app.ui_state.resizing_clip.is_some() {
         resize::end_resize(app);
     }
 
     // Handle drag and drop ending
-    if pointer_released {
-        if let Some(pointer_pos) = pointer_pos {
-            drag_drop::handle_pattern_drop(app, ctx, pointer_pos, rect, config);
-            drag_drop::handle_audio_drop(app, ctx, pointer_pos, rect, config);
-        }
+    if pointer_released && let Some(pointer_pos) = pointer_pos {
+        drag_drop::handle_pattern_drop(app, ctx, pointer_pos, rect, config);
+        drag_drop::handle_note_pattern_drop(app, ctx, pointer_pos, rect, config);
+        drag_drop::handle_audio_drop(app, ctx, pointer_pos, rect, config);
     }
 
     // Detect resize hover
@@ -40,10 +49,9 @@ pub fn handle_input(
     }
 
     // Start resize
-    if pointer_pressed && hovered_edge.is_some() && app.ui_state.resizing_clip.is_none() {
-        if let Some((clip_idx, edge)) = hovered_edge {
-            resize::start_resize(app, clip_idx, edge);
-        }
+    if pointer_pressed && app.ui_state.resizing_clip.is_none()
+        && let Some((clip_idx, edge)) = hovered_edge {
+        resize::start_resize(app, clip_idx, edge);
     }
 
     // Perform resize
@@ -51,4 +59,18 @@ pub fn handle_input(
         let drag_delta = ctx.input(|i| i.pointer.delta());
         resize::perform_resize(app, drag_delta, config);
     }
-}
\ No newline at end of file
+}
+/// Track whose mute button is under the pointer
+fn mute_button_at(app: &MyApp, pointer_pos: Pos2, rect: Rect, config: &PlaylistConfig) -> Option<usize> {
+    let tracks_start_y = rect.top() + config.timeline_header_height;
+    let track_idx = ((pointer_pos.y - tracks_start_y) / config.track_height).floor();
+    if track_idx < 0.0 || track_idx as usize >= app.session.playlist.tracks.len() {
+        return None;
+    }
+
+    let center = Pos2::new(
+        rect.left() + config.mute_button_offset,
+        tracks_start_y + (track_idx + 0.5) * config.track_height,
+    );
+    (pointer_pos.distance(center) <= config.mute_button_radius).then_some(track_idx as usize)
+}
@@ -13,7 +13,7 @@ use crate::components::snap_to_grid;
 pub use config::PlaylistConfig;
 
 pub fn render(app: &mut MyApp, ctx: &egui::Context) {
-    let config = PlaylistConfig::default();
+    let config = PlaylistConfig::for_time_signature(app.session.time_signature);
 
     ctx.request_repaint();
 
@@ -36,18 +36,13 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
         let pointer_pos = ctx.pointer_interact_pos();
 
         // Handle input
-        input::handle_input(app, ctx, &response, rect, pointer_pos, &config);
-
-        // Lock state for drawing
-        let state = app.audio_state.lock().unwrap();
+        input::handle_input(app, ctx, rect, pointer_pos, &config);
 
         // Draw everything
         drawing::draw_timeline_header(&painter, rect, &config);
         drawing::draw_beat_markers(&painter, rect, &config, 40);
-        drawing::draw_tracks(&painter, rect, &state.playlist, &config);
-        drawing::draw_clips(&painter, rect, &state.playlist, &config);
-        drawing::draw_playhead(&painter, rect, state.playhead_position, &config);
-
-        drop(state);
+        drawing::draw_tracks(&painter, rect, &app.session.playlist, &config);
+        drawing::draw_clips(&painter, rect, &app.session, &config);
+        drawing::draw_playhead(&painter, rect, app.engine.playhead_position(), &config);
     });
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 // src/components/playlist/resize.rs
 
-use egui::{Context, Pos2, Rect, Vec2};
+use egui::{Pos2, Rect, Vec2};
+use remdaw_engine::Command;
 use crate::models::{MyApp, ResizeEdge, ResizeState, ClipType};
 use super::config::PlaylistConfig;
 
@@ -13,13 +14,12 @@ pub fn detect_resize_hover(
 ) -> Option<(usize, ResizeEdge)> {
     let pointer_pos = pointer_pos?;
 
-    let state = app.audio_state.lock().unwrap();
     let timeline_start_x = rect.left() + config.track_label_width;
     let tracks_start_y = rect.top() + config.timeline_header_height;
 
-    for (clip_idx, clip) in state.playlist.clips.iter().enumerate() {
-        // Only patterns are resizable
-        if !matches!(clip.clip_type, ClipType::Pattern(_)) {
+    for (clip_idx, clip) in app.session.playlist.clips.iter().enumerate() {
+        // Patterns, note and audio clips are resizable
+        if !matches!(clip.clip_type, ClipType::Pattern(_) | ClipType::Notes(_) | ClipType::AudioFile(_)) {
             continue;
         }
 
@@ -53,8 +53,7 @@ pub fn start_resize(
     clip_idx: usize,
     edge: ResizeEdge,
 ) {
-    let state = app.audio_state.lock().unwrap();
-    let clip = &state.playlist.clips[clip_idx];
+    let clip = &app.session.playlist.clips[clip_idx];
 
     app.ui_state.resizing_clip = Some(ResizeState {
         clip_index: clip_idx,
@@ -80,20 +79,26 @@ pub fn perform_resize(
     }
 
     let delta_beats = drag_delta.x / config.pixels_per_beat;
-    let mut state = app.audio_state.lock().unwrap();
-    let clip = &mut state.playlist.clips[resize_state.clip_index];
+    let clip = &mut app.session.playlist.clips[resize_state.clip_index];
 
     match resize_state.edge {
         ResizeEdge::Left => {
-            let new_start = clip.start_time + delta_beats as f64;
-            let new_length = clip.length - delta_beats as f64;
+            let mut new_start = clip.start_time + delta_beats as f64;
+            if app.ui_state.snap_to_grid {
+                let snap_div = app.ui_state.snap_division as f64;
+                new_start = (new_start / snap_div).round() * snap_div;
+            }
+            // the right edge stays where it is
+            let new_length = clip.start_time + clip.length - new_start;
 
             if new_length > config.min_clip_length && new_start >= 0.0 {
-                if app.ui_state.snap_to_grid {
-                    let snap_div = app.ui_state.snap_division as f64;
-                    clip.start_time = (new_start / snap_div).round() * snap_div;
-                    clip.length = (new_length / snap_div).round() * snap_div;
-                } else {
+                // Audio clips trim into the sample, but not past its start
+                let is_audio = matches!(clip.clip_type, ClipType::AudioFile(_));
+                let new_offset = clip.offset + (new_start - clip.start_time);
+                if !is_audio || new_offset >= 0.0 {
+                    if is_audio {
+                        clip.offset = new_offset;
+                    }
                     clip.start_time = new_start;
                     clip.length = new_length;
                 }
@@ -112,6 +117,15 @@ pub fn perform_resize(
             }
         }
     }
+
+    let command = Command::MoveClip {
+        index: resize_state.clip_index,
+        track_index: clip.track_index,
+        start_time: clip.start_time,
+        length: clip.length,
+        offset: clip.offset,
+    };
+    app.send(command);
 }
 
 /// End the resize operation
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use remdaw_engine::export::{self, BitDepth, ExportSettings};
+use remdaw_engine::resample::Interpolation;
+use remdaw_engine::{Error, Session};
+use crate::models::MyApp;
+
+const SAMPLE_RATES: [u32; 4] = [44100, 48000, 88200, 96000];
+/// How often the window redraws to show a running export's progress
+const PROGRESS_REFRESH: Duration = Duration::from_millis(100);
+
+/// A bounce running on its own thread, so long songs don't freeze the window
+pub struct ExportJob {
+    path: PathBuf,
+    progress: Arc<AtomicU32>, // share of the range written, as f32 bits
+    thread: JoinHandle<Result<(), Error>>,
+}
+
+impl ExportJob {
+    fn start(session: &Session, settings: &ExportSettings, path: PathBuf) -> Self {
+        let offline = export::offline_engine(session, settings);
+        let progress = Arc::new(AtomicU32::new(0));
+        let thread = {
+            let settings = settings.clone();
+            let path = path.clone();
+            let progress = progress.clone();
+            std::thread::spawn(move || {
+                export::write_wav(offline, &settings, &path, |done| progress.store(done.to_bits(), Ordering::Relaxed))
+            })
+        };
+        ExportJob { path, progress, thread }
+    }
+
+    fn progress(&self) -> f32 {
+        f32::from_bits(self.progress.load(Ordering::Relaxed))
+    }
+}
+
+/// Reports a finished export. Called every frame, whether or not the window is open.
+pub fn poll(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(job) = &app.ui_state.export_job else {
+        return;
+    };
+    if !job.thread.is_finished() {
+        ctx.request_repaint_after(PROGRESS_REFRESH);
+        return;
+    }
+
+    let job = app.ui_state.export_job.take().unwrap();
+    match job.thread.join() {
+        Ok(Ok(())) => {
+            app.notifications.info(format!("Exported {}", job.path.display()));
+            app.ui_state.is_export_open = false;
+        }
+        Ok(Err(err)) => app.notifications.error(err),
+        Err(_) => app.notifications.error(format!("Exporting {} failed", job.path.display())),
+    }
+}
+
+pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let beats_per_bar = app.session.time_signature.bar_length();
+    let mut should_export = false;
+
+    egui::Window::new("Export")
+        .resizable(false)
+        .collapsible(false)
+        .open(&mut app.ui_state.is_export_open)
+        .show(ctx, |ui| {
+            let settings = &mut app.ui_state.export_settings;
+
+            egui::Grid::new("export_grid").num_columns(2).show(ui, |ui| {
+                ui.label("Sample Rate:");
+                egui::ComboBox::from_id_salt("export_sample_rate")
+                    .selected_text(format!("{} Hz", settings.sample_rate))
+                    .show_ui(ui, |ui| {
+                        for rate in SAMPLE_RATES {
+                            ui.selectable_value(&mut settings.sample_rate, rate, format!("{} Hz", rate));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Bit Depth:");
+                egui::ComboBox::from_id_salt("export_bit_depth")
+                    .selected_text(settings.bit_depth.label())
+                    .show_ui(ui, |ui| {
+                        for depth in BitDepth::ALL {
+                            ui.selectable_value(&mut settings.bit_depth, depth, depth.label());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Resampling:");
+                egui::ComboBox::from_id_salt("export_interpolation")
+                    .selected_text(settings.interpolation.label())
+                    .show_ui(ui, |ui| {
+                        for interpolation in Interpolation::ALL {
+                            ui.selectable_value(&mut settings.interpolation, interpolation, interpolation.label());
+                        }
+                    });
+                ui.end_row();
+
+                // range is edited in bars, stored in beats
+                let mut start_bar = settings.start_beat / beats_per_bar + 1.0;
+                let mut end_bar = settings.end_beat / beats_per_bar + 1.0;
+
+                ui.label("Start Bar:");
+                if ui.add(egui::DragValue::new(&mut start_bar).speed(0.25).range(1.0..=f64::MAX)).changed() {
+                    settings.start_beat = (start_bar - 1.0) * beats_per_bar;
+                }
+                ui.end_row();
+
+                ui.label("End Bar:");
+                if ui.add(egui::DragValue::new(&mut end_bar).speed(0.25).range(start_bar..=f64::MAX)).changed() {
+                    settings.end_beat = (end_bar - 1.0) * beats_per_bar;
+                }
+                ui.end_row();
+            });
+
+            ui.checkbox(&mut settings.tail, "Include tails")
+                .on_hover_text("Keep rendering after the end bar until reverbs and delays have rung out");
+
+            ui.separator();
+
+            match &app.ui_state.export_job {
+                Some(job) => {
+                    ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+                }
+                None => should_export = ui.button("Export...").clicked(),
+            }
+        });
+
+    if should_export
+        && let Some(path) = rfd::FileDialog::new()
+            .add_filter("WAV", &["wav"])
+            .set_file_name("export.wav")
+            .save_file() {
+        app.ui_state.export_job = Some(ExportJob::start(&app.session, &app.ui_state.export_settings, path));
+    }
+}
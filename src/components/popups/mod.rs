@@ -1 +1,2 @@
-pub mod rename_pattern;
\ No newline at end of file
+pub mod rename_pattern;
+pub mod export;
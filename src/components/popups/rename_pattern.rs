@@ -1,6 +1,12 @@
 use crate::models::MyApp;
 
 pub fn render(app: &mut MyApp, ctx: &egui::Context, idx: usize) {
+    // the pattern was deleted while the popup was open
+    if app.session.patterns.get(idx).is_none() {
+        app.ui_state.pattern_rename_popup = None;
+        return;
+    }
+
     let mut is_open = true;
     egui::Window::new("Rename Pattern")
         .open(&mut is_open)
@@ -10,9 +16,9 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context, idx: usize) {
 
             ui.horizontal(|ui| {
                 if ui.button("OK").clicked() {
-                    let mut state = app.audio_state.lock().unwrap();
-                    if idx < state.patterns.len() {
-                        state.patterns[idx].name = app.ui_state.rename_buffer.clone();
+                    if let Some(pattern) = app.session.patterns.get_mut(idx) {
+                        pattern.name = app.ui_state.rename_buffer.clone();
+                        app.sync_patterns();
                     }
                     app.ui_state.pattern_rename_popup = None;
                 }
@@ -0,0 +1,114 @@
+use remdaw_engine::routing::{creates_loop, AuxSend, SendSource};
+use remdaw_engine::Command;
+use crate::components::mixer::gain_label;
+use crate::models::MyApp;
+
+/// Loudest a send goes, as linear gain
+const MAX_SEND: f32 = 1.0;
+
+/// Shows how much of the channel picked in the mixer goes to each aux bus.
+/// Changing an existing send is applied in place; adding or removing one
+/// replaces the channel's list in the engine.
+pub fn render(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(source) = app.ui_state.sends_source else {
+        return;
+    };
+    let session = &app.session;
+    let name = match source {
+        SendSource::Track(track) => session.playlist.tracks.get(track).map(|track| track.name.clone()),
+        SendSource::Instrument(instrument) => session.instruments.get(instrument).map(|instrument| instrument.name.clone()),
+        SendSource::Aux(bus) => session.aux_buses.get(bus).map(|bus| bus.name.clone()),
+    };
+    // the channel is gone
+    let Some(name) = name else {
+        app.ui_state.sends_source = None;
+        return;
+    };
+
+    // an aux bus can't send to anything that already leads back to it
+    let buses: Vec<(String, bool)> = session.aux_buses.iter()
+        .enumerate()
+        .map(|(index, bus)| {
+            let loops = match source {
+                SendSource::Aux(from) => creates_loop(&session.aux_buses, from, index),
+                _ => false,
+            };
+            (bus.name.clone(), loops)
+        })
+        .collect();
+    let Some(sends) = app.session.sends_mut(source) else {
+        return;
+    };
+
+    let mut open = true;
+    let mut commands = Vec::new();
+    let mut changed_list = false;
+
+    egui::Window::new(format!("Sends: {}", name))
+        .id(egui::Id::new("sends_window"))
+        .open(&mut open)
+        .show(ctx, |ui| {
+            if buses.is_empty() {
+                ui.weak("No aux buses yet. Add one in the mixer.");
+                return;
+            }
+
+            egui::Grid::new("sends_grid").num_columns(4).show(ui, |ui| {
+                for (bus, (bus_name, loops)) in buses.iter().enumerate() {
+                    let existing = sends.iter().position(|send| send.bus == bus);
+                    ui.label(bus_name);
+
+                    let mut amount = existing.map_or(0.0, |index| sends[index].amount);
+                    let slider = egui::Slider::new(&mut amount, 0.0..=MAX_SEND)
+                        .custom_formatter(|gain, _| gain_label(gain));
+                    let response = ui.add_enabled(!loops, slider);
+                    let response = if *loops {
+                        response.on_disabled_hover_text("Would feed back into itself")
+                    } else {
+                        response
+                    };
+                    if response.changed() {
+                        match existing {
+                            Some(index) => {
+                                sends[index].amount = amount;
+                                commands.push(Command::SetSend { source, index, send: sends[index] });
+                            }
+                            None => {
+                                sends.push(AuxSend { amount, ..AuxSend::new(bus) });
+                                changed_list = true;
+                            }
+                        }
+                    }
+
+                    match existing {
+                        Some(index) => {
+                            if ui.checkbox(&mut sends[index].pre_fader, "Pre")
+                                .on_hover_text("Send before the channel's volume and pan")
+                                .changed() {
+                                commands.push(Command::SetSend { source, index, send: sends[index] });
+                            }
+                            if ui.small_button("x").on_hover_text("Remove send").clicked() {
+                                sends.remove(index);
+                                changed_list = true;
+                            }
+                        }
+                        None => {
+                            ui.label("");
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+    for command in commands {
+        app.send(command);
+    }
+    if changed_list {
+        app.sync_sends(source);
+    }
+    if !open {
+        app.ui_state.sends_source = None;
+    }
+}
@@ -1,13 +1,25 @@
+use remdaw_engine::resample::Interpolation;
+use remdaw_engine::Command;
+use crate::audio::{self, OutputDevice};
 use crate::models::MyApp;
 
 
 pub(crate) fn render(app: &mut MyApp, ctx: &egui::Context) {
+    // probing devices is slow, so only list them when the window opens or on refresh
+    if app.ui_state.audio_devices.is_none() {
+        app.ui_state.audio_devices = Some(audio::list_devices(&app.config.audio));
+    }
+
+    let mut audio_changed = false;
+    let mut interpolation_changed = false;
+    let mut refresh = false;
+
     egui::Window::new("Settings")
         .min_width(400.0)
         .resizable(false)
         .collapsible(false)
         .open(&mut app.ui_state.is_settings_open)
-        .show(&ctx, |ui| {
+        .show(ctx, |ui| {
             ui.label(egui::RichText::new("Settings").strong().size(20.0));
             ui.separator();
 
@@ -15,16 +27,117 @@ pub(crate) fn render(app: &mut MyApp, ctx: &egui::Context) {
                 ui.label("File Path:");
                 ui.text_edit_singleline(&mut app.config.file_path);
 
-                if ui.button("Browse...").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                        app.config.file_path = path.display().to_string();
-                    }
+                if ui.button("Browse...").clicked()
+                    && let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    app.config.file_path = path.display().to_string();
                 }
             });
 
+            ui.separator();
+            ui.label(egui::RichText::new("Audio").strong());
+
+            let devices = app.ui_state.audio_devices.as_ref().unwrap();
+            let settings = &mut app.config.audio;
+            let before = settings.clone();
+
+            egui::Grid::new("audio_settings").num_columns(2).show(ui, |ui| {
+                ui.label("Host:");
+                egui::ComboBox::from_id_salt("audio_host")
+                    .selected_text(settings.host.as_deref().unwrap_or("Default"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut settings.host, None, "Default");
+                        for host in &devices.hosts {
+                            ui.selectable_value(&mut settings.host, Some(host.clone()), host);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Device:");
+                let device_name = match &settings.device {
+                    OutputDevice::Default => "Default",
+                    OutputDevice::Named(name) => name.as_str(),
+                    OutputDevice::Null => "No output",
+                };
+                egui::ComboBox::from_id_salt("audio_device")
+                    .selected_text(device_name)
+                    .width(240.0)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut settings.device, OutputDevice::Default, "Default");
+                        for device in &devices.devices {
+                            ui.selectable_value(&mut settings.device, OutputDevice::Named(device.clone()), device);
+                        }
+                        ui.selectable_value(&mut settings.device, OutputDevice::Null, "No output")
+                            .on_hover_text("Run without sound hardware. Playback continues silently.");
+                    });
+                ui.end_row();
+
+                ui.label("Sample Rate:");
+                egui::ComboBox::from_id_salt("audio_sample_rate")
+                    .selected_text(settings.sample_rate.map_or("Device default".to_string(), |r| format!("{} Hz", r)))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut settings.sample_rate, None, "Device default");
+                        for &rate in &devices.sample_rates {
+                            ui.selectable_value(&mut settings.sample_rate, Some(rate), format!("{} Hz", rate));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Buffer Size:");
+                egui::ComboBox::from_id_salt("audio_buffer_size")
+                    .selected_text(settings.buffer_size.map_or("Device default".to_string(), |f| format!("{} frames", f)))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut settings.buffer_size, None, "Device default");
+                        for &frames in &devices.buffer_sizes {
+                            ui.selectable_value(&mut settings.buffer_size, Some(frames), format!("{} frames", frames));
+                        }
+                    });
+                ui.end_row();
+            });
+
+            // a new host has different devices
+            if settings.host != before.host {
+                settings.device = OutputDevice::Default;
+            }
+            audio_changed = *settings != before;
+
+            ui.horizontal(|ui| {
+                ui.label("Resampling:");
+                let interpolation = &mut app.config.interpolation;
+                egui::ComboBox::from_id_salt("playback_interpolation")
+                    .selected_text(interpolation.label())
+                    .show_ui(ui, |ui| {
+                        for option in Interpolation::ALL {
+                            interpolation_changed |= ui.selectable_value(interpolation, option, option.label()).changed();
+                        }
+                    })
+                    .response
+                    .on_hover_text("How samples are read when pitched or played at another rate. Linear is cheaper, sinc is cleaner.");
+            });
+
+            ui.horizontal(|ui| {
+                ui.label(format!("Running at {} Hz", app.engine.sampling_rate));
+                if ui.button("Refresh devices").clicked() {
+                    refresh = true;
+                }
+            });
+
+            ui.separator();
             if ui.button("Save").clicked() {
                 app.config.save(); // Save immediately when user clicks
                 // Optionally show a confirmation message
             }
         });
-}
\ No newline at end of file
+
+    if interpolation_changed {
+        app.send(Command::SetInterpolation(app.config.interpolation));
+        app.config.save();
+    }
+    if audio_changed {
+        app.restart_audio();
+        app.config.save();
+    }
+    // the new device may support different rates and buffer sizes
+    if audio_changed || refresh || !app.ui_state.is_settings_open {
+        app.ui_state.audio_devices = None;
+    }
+}
@@ -2,8 +2,9 @@ pub fn render(ui: &mut egui::Ui, app: &mut crate::models::MyApp) {
     ui.horizontal(|ui| {
         if app.ui_state.snap_to_grid {
             ui.label("Snap:");
-            if ui.selectable_label(app.ui_state.snap_division == 4.0, "Bar").clicked() {
-                app.ui_state.snap_division = 4.0; // 4 beats = 1 bar
+            let bar = app.session.time_signature.bar_length() as f32;
+            if ui.selectable_label(app.ui_state.snap_division == bar, "Bar").clicked() {
+                app.ui_state.snap_division = bar; // 4 beats in 4/4, 3.5 in 7/8
             }
             if ui.selectable_label(app.ui_state.snap_division == 1.0, "Beat").clicked() {
                 app.ui_state.snap_division = 1.0; // 1 beat
@@ -0,0 +1,148 @@
+use egui::Ui;
+use remdaw_engine::envelope::Envelope;
+use remdaw_engine::synth::{FilterMode, LfoTarget, Oscillator, SynthPatch, Waveform};
+
+/// Longest attack, decay or release, in seconds
+const MAX_ENVELOPE_TIME: f32 = 5.0;
+
+/// Controls for every setting of a synth patch, shown in the instrument window
+///
+/// # Returns
+/// * `bool` - Whether anything changed
+pub fn controls(ui: &mut Ui, patch: &mut SynthPatch) -> bool {
+    let mut changed = false;
+    for (idx, oscillator) in patch.oscillators.iter_mut().enumerate() {
+        ui.strong(format!("Oscillator {}", idx + 1));
+        changed |= oscillator_controls(ui, idx, oscillator);
+        ui.separator();
+    }
+
+    ui.strong("Filter");
+    egui::Grid::new("synth_filter").num_columns(2).show(ui, |ui| {
+        ui.label("Mode");
+        egui::ComboBox::from_id_salt("synth_filter_mode")
+            .selected_text(patch.filter.label())
+            .show_ui(ui, |ui| {
+                for mode in FilterMode::ALL {
+                    changed |= ui.selectable_value(&mut patch.filter, mode, mode.label()).changed();
+                }
+            });
+        ui.end_row();
+
+        ui.label("Cutoff");
+        changed |= ui.add(egui::Slider::new(&mut patch.cutoff, 20.0..=20000.0)
+            .logarithmic(true)
+            .suffix(" Hz"))
+            .changed();
+        ui.end_row();
+
+        ui.label("Resonance");
+        changed |= ui.add(egui::Slider::new(&mut patch.resonance, 0.5..=10.0)).changed();
+        ui.end_row();
+
+        ui.label("Envelope amount");
+        changed |= ui.add(egui::Slider::new(&mut patch.envelope_amount, -4.0..=8.0).suffix(" oct")).changed();
+        ui.end_row();
+    });
+    changed |= envelope_controls(ui, "synth_filter_envelope", &mut patch.filter_envelope);
+    ui.separator();
+
+    ui.strong("Amplitude");
+    changed |= envelope_controls(ui, "synth_amp_envelope", &mut patch.amp_envelope);
+    ui.separator();
+
+    ui.strong("LFO");
+    egui::Grid::new("synth_lfo").num_columns(2).show(ui, |ui| {
+        ui.label("Shape");
+        changed |= waveform_combo(ui, "synth_lfo_waveform", &mut patch.lfo.waveform);
+        ui.end_row();
+
+        ui.label("Target");
+        egui::ComboBox::from_id_salt("synth_lfo_target")
+            .selected_text(patch.lfo.target.label())
+            .show_ui(ui, |ui| {
+                for target in LfoTarget::ALL {
+                    changed |= ui.selectable_value(&mut patch.lfo.target, target, target.label()).changed();
+                }
+            });
+        ui.end_row();
+
+        ui.label("Rate");
+        changed |= ui.add(egui::Slider::new(&mut patch.lfo.rate, 0.05..=20.0)
+            .logarithmic(true)
+            .suffix(" Hz"))
+            .changed();
+        ui.end_row();
+
+        let (max, suffix) = match patch.lfo.target {
+            LfoTarget::Pitch => (12.0, " st"),
+            LfoTarget::Cutoff => (4.0, " oct"),
+            LfoTarget::Amplitude => (1.0, ""),
+        };
+        ui.label("Depth");
+        changed |= ui.add(egui::Slider::new(&mut patch.lfo.depth, 0.0..=max).suffix(suffix)).changed();
+        ui.end_row();
+    });
+
+    if ui.button("Reset").on_hover_text("Back to the default patch").clicked() {
+        *patch = SynthPatch::default();
+        changed = true;
+    }
+    changed
+}
+
+fn waveform_combo(ui: &mut Ui, id: &str, waveform: &mut Waveform) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(waveform.label())
+        .show_ui(ui, |ui| {
+            for option in Waveform::ALL {
+                changed |= ui.selectable_value(waveform, option, option.label()).changed();
+            }
+        });
+    changed
+}
+
+fn oscillator_controls(ui: &mut Ui, idx: usize, oscillator: &mut Oscillator) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= waveform_combo(ui, &format!("synth_osc_{}", idx), &mut oscillator.waveform);
+        ui.label("Level");
+        changed |= ui.add(egui::Slider::new(&mut oscillator.level, 0.0..=1.0)).changed();
+        ui.label("Octave");
+        changed |= ui.add(egui::DragValue::new(&mut oscillator.octave).range(-3..=3)).changed();
+        ui.label("Detune");
+        changed |= ui.add(egui::DragValue::new(&mut oscillator.detune)
+            .speed(0.5)
+            .range(-100.0..=100.0)
+            .suffix(" ct"))
+            .changed();
+    });
+    changed
+}
+
+fn time_slider(value: &mut f32) -> egui::Slider<'_> {
+    egui::Slider::new(value, 0.0..=MAX_ENVELOPE_TIME)
+        .logarithmic(true)
+        .smallest_positive(0.001)
+        .suffix(" s")
+}
+
+pub fn envelope_controls(ui: &mut Ui, id: &str, envelope: &mut Envelope) -> bool {
+    let mut changed = false;
+    egui::Grid::new(id).num_columns(2).show(ui, |ui| {
+        ui.label("Attack");
+        changed |= ui.add(time_slider(&mut envelope.attack)).changed();
+        ui.end_row();
+        ui.label("Decay");
+        changed |= ui.add(time_slider(&mut envelope.decay)).changed();
+        ui.end_row();
+        ui.label("Sustain");
+        changed |= ui.add(egui::Slider::new(&mut envelope.sustain, 0.0..=1.0)).changed();
+        ui.end_row();
+        ui.label("Release");
+        changed |= ui.add(time_slider(&mut envelope.release)).changed();
+        ui.end_row();
+    });
+    changed
+}
@@ -1,3 +1,7 @@
+use std::path::PathBuf;
+use remdaw_engine::project::{self, Project, PROJECT_EXTENSION};
+use remdaw_engine::models::{MAX_BPM, MIN_BPM};
+use remdaw_engine::{Command, TimeSignature};
 use crate::models::MyApp;
 use eframe::emath::Align::Center;
 
@@ -6,54 +10,100 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
         ui.add_space(12.0);
 
         ui.horizontal(|ui| {
-            let mut state = app.audio_state.lock().unwrap();
-
             // Editable BPM with DragValue
             ui.label("BPM:");
             if ui
-                .add(egui::DragValue::new(&mut state.bpm)
+                .add(egui::DragValue::new(&mut app.session.bpm)
                         .speed(1.0)
-                        .range(40..=300),
+                        .range(MIN_BPM..=MAX_BPM),
                 )
                 .changed()
             {
-                // Recalculate samples_per_beat when BPM changes
-                state.samples_per_beat = state.sampling_rate * 60.0 / state.bpm as f32;
+                // engine recalculates samples_per_beat
+                app.send(Command::SetBpm(app.session.bpm));
+            }
+
+            let signature = app.session.time_signature;
+            egui::ComboBox::from_id_salt("time_signature")
+                .width(50.0)
+                .selected_text(signature.label())
+                .show_ui(ui, |ui| {
+                    for option in TimeSignature::COMMON {
+                        ui.selectable_value(&mut app.session.time_signature, option, option.label());
+                    }
+                });
+            if app.session.time_signature != signature {
+                // keep snapping to whole bars
+                if app.ui_state.snap_division == signature.bar_length() as f32 {
+                    app.ui_state.snap_division = app.session.time_signature.bar_length() as f32;
+                }
+                app.send(Command::SetTimeSignature(app.session.time_signature));
+            }
+
+            ui.label("Swing:");
+            if ui.add(egui::DragValue::new(&mut app.session.swing)
+                    .speed(0.01)
+                    .range(0.0..=1.0)
+                    .custom_formatter(|swing, _| format!("{:.0}%", swing * 100.0)))
+                .on_hover_text("Delays every second step of patterns without their own swing")
+                .changed() {
+                app.send(Command::SetSwing(app.session.swing));
             }
 
             ui.add_space(24.0);
 
-            ui.label(format!("SR: {}", state.sampling_rate));
-            ui.label(format!("SPB: {:.0}", state.samples_per_beat));
+            let sampling_rate = app.engine.sampling_rate;
+            ui.label(format!("SR: {}", sampling_rate));
+            ui.label(format!("SPB: {:.0}", sampling_rate * 60.0 / app.session.bpm as f32));
+
+            ui.add_space(12.0);
+            app.ui_state.meters.master_meter(ui, app.engine.meters(), egui::vec2(120.0, 14.0));
 
             ui.add_space(24.0);
 
-            let label = if state.is_playing { "\u{23F8}" } else { "\u{25B6}" }; // pause else play
+            let is_playing = app.engine.is_playing();
+            let label = if is_playing { "\u{23F8}" } else { "\u{25B6}" }; // pause else play
             if ui.add_sized([25.0, 20.0], egui::Button::new(label)).clicked() {
-                if !state.is_playing {
-                    state.is_playing = true;
-                }
-                else if !state.is_playing && !state.just_started{
-                    state.metronome_counter = 0.0;
-                    state.playhead_position = 0.0;
-                    state.just_started = true;
-                    state.is_playing = true;
-                }
-                else {
-                    state.is_playing = false;
-                }
+                app.send(if is_playing { Command::Pause } else { Command::Play });
             }
             if ui.add(egui::Button::new("⏹")).clicked() {
-                state.is_playing = false;
-                state.playhead_position = 0.0;
-                state.metronome_counter = 0.0;
+                app.send(Command::Stop);
             }
 
             if ui.button("metro").clicked() {
-                state.is_metronome = !state.is_metronome;
+                app.ui_state.is_metronome = !app.ui_state.is_metronome;
+                app.send(Command::SetMetronome(app.ui_state.is_metronome));
+            }
+
+            ui.add_space(24.0);
+
+            if ui.button("open").clicked()
+                && let Some(path) = rfd::FileDialog::new()
+                    .add_filter("remdaw project", &[PROJECT_EXTENSION])
+                    .pick_file() {
+                open_project(app, path);
+            }
+
+            if ui.button("save").clicked() {
+                match app.project_path.clone() {
+                    Some(path) => save_project(app, path),
+                    None => save_project_as(app),
+                }
             }
 
-            drop(state); // Release lock before next button
+            if ui.button("save as").clicked() {
+                save_project_as(app);
+            }
+
+            if ui.button("export").clicked() {
+                // default the range to the whole song
+                let song_length = app.session.playlist.length();
+                if song_length > 0.0 {
+                    app.ui_state.export_settings.start_beat = 0.0;
+                    app.ui_state.export_settings.end_beat = song_length;
+                }
+                app.ui_state.is_export_open = true;
+            }
 
             ui.add_space(24.0);
 
@@ -61,6 +111,10 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
                 app.ui_state.is_channel_rack_open = !app.ui_state.is_channel_rack_open;
             }
 
+            if ui.button("mixer").clicked() {
+                app.ui_state.is_mixer_open = !app.ui_state.is_mixer_open;
+            }
+
             if ui.button("files").clicked() {
                 app.ui_state.is_files_explorer_open = !app.ui_state.is_files_explorer_open;
             }
@@ -73,9 +127,55 @@ pub fn render(app: &mut MyApp, ctx: &egui::Context) {
                 if ui.button("settings").clicked() {
                     app.ui_state.is_settings_open = !app.ui_state.is_settings_open;
                 }
+
+                let errors = app.notifications.error_count();
+                let label = if errors > 0 { format!("log ({})", errors) } else { "log".to_string() };
+                if ui.button(label).clicked() {
+                    app.notifications.is_log_open = !app.notifications.is_log_open;
+                }
             });
         });
         ui.add_space(12.0);
     });
 
 }
+
+/// Asks for a destination and saves the session there
+fn save_project_as(app: &mut MyApp) {
+    if let Some(path) = rfd::FileDialog::new()
+        .add_filter("remdaw project", &[PROJECT_EXTENSION])
+        .set_file_name(format!("untitled.{}", PROJECT_EXTENSION))
+        .save_file() {
+        save_project(app, project::with_extension(path));
+    }
+}
+
+/// Serializes the session to `path` and remembers it for the next save
+fn save_project(app: &mut MyApp, path: PathBuf) {
+    let project = Project::from_session(&app.session, &project::project_dir(&path));
+
+    match project.save(&path) {
+        Ok(()) => {
+            app.notifications.info(format!("Saved {}", path.display()));
+            app.project_path = Some(path);
+        }
+        Err(err) => app.notifications.error(err),
+    }
+}
+
+/// Replaces the session with the project stored at `path`
+fn open_project(app: &mut MyApp, path: PathBuf) {
+    match Project::load(&path) {
+        Ok(project) => {
+            let missing = project.apply(&mut app.session, &project::project_dir(&path));
+            app.sync_session();
+
+            for err in missing {
+                app.notifications.error(err);
+            }
+            app.ui_state.current_pattern_index = Some(0);
+            app.project_path = Some(path);
+        }
+        Err(err) => app.notifications.error(err),
+    }
+}
@@ -0,0 +1,57 @@
+use egui::{Color32, Painter, Rect, Stroke};
+use remdaw_engine::sample::Sample;
+use remdaw_engine::sampler::{Region, SamplerSettings};
+
+/// Draws `sample` as one min/max line per pixel column, read from the
+/// sample's peak cache so long files stay cheap at any zoom. Only the part
+/// of `rect` inside the painter's clip rect is drawn.
+///
+/// # Arguments
+/// * `rect` - Area to draw in, with silence along its middle
+/// * `first_frame` - Frame of the sample at the left edge of `rect`
+/// * `frames_per_pixel` - Zoom: how many frames one pixel column covers
+pub fn draw(painter: &Painter, rect: Rect, sample: &Sample, first_frame: f64, frames_per_pixel: f64, color: Color32) {
+    let whole = SamplerSettings::DEFAULT.region(sample.len());
+    draw_region(painter, rect, sample, &whole, first_frame, frames_per_pixel, color);
+}
+
+/// Like `draw`, for the part of `sample` a voice plays: trimmed, reversed
+/// and looped the same way, so a clip shows what it sounds like.
+///
+/// # Arguments
+/// * `region` - Where voices read in the sample
+/// * `first_position` - Frames into the region, in playback order, at the left edge of `rect`
+pub fn draw_region(painter: &Painter, rect: Rect, sample: &Sample, region: &Region, first_position: f64, frames_per_pixel: f64, color: Color32) {
+    let visible = rect.intersect(painter.clip_rect());
+    if !visible.is_positive() || sample.is_empty() || region.is_empty() || frames_per_pixel <= 0.0 {
+        return;
+    }
+
+    let center = rect.center().y;
+    let half_height = rect.height() / 2.0;
+    let stroke = Stroke::new(1.0, color);
+
+    let mut x = visible.left().floor();
+    while x < visible.right() {
+        let position = first_position + (x - rect.left()) as f64 * frames_per_pixel;
+        if position + frames_per_pixel > 0.0 {
+            // back round the loop the way a voice goes
+            let position = region.advance(0.0, position.max(0.0));
+            if position >= region.len() {
+                break;
+            }
+            let end = (position + frames_per_pixel).min(region.len());
+            let (from, to) = (region.source(position), region.source(end));
+            let start = from.min(to) as usize;
+            let end = (from.max(to).ceil() as usize).max(start + 1);
+            let peak = sample.peak(start, end);
+            if !peak.is_empty() {
+                // at least a pixel tall, so silence still shows as a line
+                let top = center - peak.max.clamp(-1.0, 1.0) * half_height;
+                let bottom = (center - peak.min.clamp(-1.0, 1.0) * half_height).max(top + 1.0);
+                painter.vline(x + 0.5, top..=bottom, stroke);
+            }
+        }
+        x += 1.0;
+    }
+}
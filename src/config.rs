@@ -1,11 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use remdaw_engine::resample::Interpolation;
+use crate::audio::AudioSettings;
 
 // Our app config stores user info that should be remembered between sessions
 #[derive(Serialize, Deserialize)]
 pub struct AppConfig {
     pub file_path: String,
+    #[serde(default)]
+    pub audio: AudioSettings, // output device, rate and buffer size
+    #[serde(default)]
+    pub interpolation: Interpolation, // how samples are resampled during playback
 }
 
 // The config used on first session
@@ -13,6 +19,8 @@ impl Default for AppConfig {
     fn default() -> Self {
         Self {
             file_path: dirs::document_dir().unwrap().to_str().unwrap().to_owned(),
+            audio: AudioSettings::default(),
+            interpolation: Interpolation::default(),
         }
     }
 }
@@ -36,10 +44,9 @@ impl AppConfig {
     pub fn load() -> Self {
         let config_path = Self::get_config_path().join("config.json");
 
-        if let Ok(content) = fs::read_to_string(&config_path) {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return config;
-            }
+        if let Ok(content) = fs::read_to_string(&config_path)
+            && let Ok(config) = serde_json::from_str(&content) {
+            return config;
         }
 
         Self::default()
@@ -0,0 +1,6 @@
+pub mod audio;
+pub mod components;
+pub mod config;
+pub mod models;
+pub mod utils;
+mod ui;
@@ -1,9 +1,4 @@
-mod ui;
-mod audio;
-mod models;
-mod components;
-mod config;
-mod utils;
+use remdaw::{models, utils};
 
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
@@ -20,4 +15,4 @@ fn main() -> eframe::Result {
             Ok(Box::<models::MyApp>::default())
         }),
     )
-}
\ No newline at end of file
+}
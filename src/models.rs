@@ -1,9 +1,16 @@
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use cpal::{Stream};
-use egui::Color32;
-use crate::audio;
-use crate::audio::path_to_vector;
+use std::path::PathBuf;
+use crate::components::channel_rack::StepParam;
+use crate::components::meter::MeterDisplays;
+use crate::components::piano_roll::PianoRollState;
+use crate::components::popups::export::ExportJob;
+use crate::components::notifications::Notifications;
+use remdaw_engine::decoder::SampleInfo;
+use remdaw_engine::effects::{EffectChain, EffectTarget};
+use remdaw_engine::export::ExportSettings;
+use remdaw_engine::routing::SendSource;
+use remdaw_engine::sample::Sample;
+use remdaw_engine::{AuxBus, Command, EngineHandle, Session};
+use crate::audio::{self, AudioSettings, DeviceList, Output};
 use crate::config::AppConfig;
 
 
@@ -21,200 +28,245 @@ pub struct ResizeState {
     pub initial_length: f64,
 }
 
-// Where all music positions are stored for playback and export
-pub struct Playlist {
-    pub(crate) tracks: Vec<Track>,
-    pub(crate) clips: Vec<PlacedClip>,
-    zoom_level: f32,
-    scroll_position: f32,
-}
-
-// one group of patterns of drums from channel rack
-#[derive(Clone)]
-pub struct Pattern {
-    pub name: String,
-    pub data: Vec<Vec<bool>>,  // The actual 16-step pattern for each instrument
-}
-
-// In models.rs
-#[derive(Clone)]
-pub enum ClipType {
-    Pattern(usize), // Index into patterns vec
-    AudioFile(usize), // Index into instruments vec
-}
-
-#[derive(Clone)]
-pub struct PlacedClip {
-    pub clip_type: ClipType,
-    pub name: String,
-    pub track_index: usize,
-    pub start_time: f64, // in beats
-    pub length: f64,
-    pub color: Color32,
-}
-
-pub struct Track {
-    pub(crate) name: String,
-    pub(crate) height: f32,
-    muted: bool,
-    solo: bool,
-}
-
-// loaded sounds
-pub struct Instrument {
-    pub is_playing: bool,
-    pub position: usize,  // where we are in the sample
-    pub samples: Vec<f32>, // the actual WAV data
-    pub name: String,
-    pub file_path: PathBuf,
-}
+// session data lives in the engine crate
+pub use remdaw_engine::{ClipType, Instrument, NotePattern, Pattern, PlacedClip, Playlist, Track};
 
 // app config
 pub struct MyApp {
-    pub _audio_stream: Stream,
-    pub audio_state: Arc<Mutex<AudioState>>,
-    pub selected_file: Option<PathBuf>,
+    pub audio_output: Option<Output>, // keeps the engine running. None while restarting
+    pub engine: EngineHandle, // sends edits to the audio thread, through `send`
+    pub is_out_of_sync: bool, // the engine missed an edit and gets the whole session next frame
+    pub metronome: Sample, // kept to hand to the engine when the output restarts
+    pub session: Session, // the UI's copy of the song
+    pub project_path: Option<PathBuf>, // where the session was last saved/opened
     pub config: AppConfig,
     pub ui_state: UiState,
+    pub notifications: Notifications,
 }
 
 pub struct UiState {
+    pub current_pattern_index: Option<usize>, // pattern shown in the channel rack
+    pub is_metronome: bool,
     pub snap_to_grid: bool,        // Add this
     pub snap_division: f32,
     pub resizing_clip: Option<ResizeState>,
     pub playlist_height: f32,
     pub is_channel_rack_open: bool,
+    pub step_lane: Option<usize>, // instrument whose event lane is open in the channel rack
+    pub step_param: StepParam,    // setting the event lane edits
+    pub piano_roll: PianoRollState,
+    pub instrument_window: Option<usize>, // instrument whose settings are shown
+    pub sample_info: Option<(PathBuf, Option<SampleInfo>)>, // header of the file it plays, read once per file
+    pub is_mixer_open: bool,
+    pub effects_target: Option<EffectTarget>, // chain shown in the effects window
+    pub sends_source: Option<SendSource>, // channel shown in the sends window
     pub is_settings_open: bool,
-    pub is_file_info_open: bool,
     pub is_files_explorer_open: bool,
     pub pattern_rename_popup: Option<usize>, // Changed from bool to Option<usize>
     pub rename_buffer: String, // Store the temporary name
-    pub is_pattern_delete: bool,
     pub is_patterns_open: bool,
-}
-
-// shared state between gui and cpal
-pub struct AudioState {
-    pub current_pattern_index: Option<usize>,
-    pub instruments: Vec<Instrument>,
-    pub bpm: i16,
-    pub sampling_rate: f32,
-    pub samples_per_beat: f32,
-    pub metronome_counter: f32,
-    pub is_playing: bool,
-    pub is_metronome: bool,
-    pub pattern: Vec<Vec<bool>>,
-    pub current_step: usize,
-    pub metronome_sample: Vec<f32>,  // Just the audio data
-    pub metronome_position: usize,
-    pub metronome_playing: bool,
-    pub preview_sound: Option<Instrument>,
-    pub just_started: bool,
-    pub playlist: Playlist,
-    pub playhead_position: f64,
-    pub patterns: Vec<Pattern>,
-}
-
-impl AudioState {
-    pub fn new(sampling_rate: f32) -> Self {
-        let mut instruments = Vec::new();
-        let paths = ["test_instruments/cowbell.wav", "test_instruments/Clap Dance.wav", "test_instruments/St 808.wav"];
-        for path in paths.iter() {
-
-            instruments.push(Instrument {
-                file_path: path.parse().unwrap(),
-                samples: path_to_vector(path),
-                position: 0,
-                is_playing: false,
-                name: Path::new(path).file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("Unknown").parse().unwrap()
-            })
-        }
-
-        let samples_per_beat =  sampling_rate * 60.0 / 130.0 ;
-
-        // Initialize pattern: one row per instrument, 16 steps each
-        let num_instruments = instruments.len();
-        let pattern = vec![vec![false; 16]; num_instruments];
-
-        let metronome_sample = path_to_vector("test_instruments/St 808.wav");
-        let mut patterns = Vec::new();
-        patterns.push(Pattern { name:"Pattern 1".to_string(), data: pattern.clone() } );
-        AudioState {
-            just_started: false,
-            current_pattern_index: Some(0),
-            instruments,
-            bpm: 130,
-            sampling_rate,
-            samples_per_beat,
-            metronome_counter: 0.0,
-            is_playing: false,
-            is_metronome: false,
-            pattern,
-            current_step: 0,
-            metronome_sample,
-            metronome_playing: false,
-            metronome_position: 0,
-            preview_sound: None,
-            playlist: Playlist::new(),
-            playhead_position: 0.0,
-            patterns
-        }
-    }
+    pub is_export_open: bool,
+    pub export_settings: ExportSettings,
+    pub export_job: Option<ExportJob>, // the export being written, if any
+    pub audio_devices: Option<DeviceList>, // listed when the settings open, since probing devices is slow
+    pub meters: MeterDisplays,
 }
 
 impl Default for MyApp {
     fn default() -> Self {
         let ui_state = UiState {
+            current_pattern_index: Some(0),
+            is_metronome: false,
             snap_to_grid: false,
             snap_division: 1.0, // 1.0 = bar, 0.25 = beat, 0.0625 = 16th note)
             is_channel_rack_open: false,
+            step_lane: None,
+            step_param: StepParam::default(),
+            piano_roll: PianoRollState::default(),
+            instrument_window: None,
+            sample_info: None,
+            is_mixer_open: false,
+            effects_target: None,
+            sends_source: None,
             playlist_height: 300.0,
             is_settings_open: false,
             is_patterns_open: true,
             pattern_rename_popup: None,
             is_files_explorer_open: true,
             resizing_clip: None,
-            is_file_info_open: false, rename_buffer: String::new(), is_pattern_delete: false };
+            rename_buffer: String::new(),
+            is_export_open: false,
+            export_settings: ExportSettings::default(),
+            export_job: None,
+            audio_devices: None,
+            meters: MeterDisplays::default() };
 
-        let (_audio_stream, audio_state) = audio::init();
-        Self {
-            _audio_stream,
-            audio_state,
-            ui_state,
-            config: AppConfig::load(),
-            selected_file: None,
+        let mut notifications = Notifications::default();
+
+        let (session, errors) = audio::default_session();
+        for err in errors {
+            notifications.error(err);
         }
+        let metronome = audio::load_metronome().unwrap_or_else(|err| {
+            notifications.error(err);
+            Default::default()
+        });
+
+        let config = AppConfig::load();
+        let (output, engine) = open_audio(&session, &metronome, &config.audio, &mut notifications);
+
+        let mut app = Self {
+            audio_output: Some(output),
+            engine,
+            is_out_of_sync: false,
+            metronome,
+            session,
+            ui_state,
+            notifications,
+            config,
+            project_path: None,
+        };
+        app.send(Command::SetInterpolation(app.config.interpolation));
+        app
     }
 }
 
-impl Playlist {
-    pub fn new() -> Self {
-        Playlist {
-            tracks: vec![
-                Track {
-                    name: "Track 1".to_string(),
-                    height: 60.0,
-                    muted: false,
-                    solo: false,
-                },
-                Track {
-                    name: "Track 2".to_string(),
-                    height: 50.0,
-                    muted: false,
-                    solo: false,
-                },
-                Track {
-                    name: "Track 3".to_string(),
-                    height: 50.0,
-                    muted: false,
-                    solo: false,
-                },
-            ],
-            clips: Vec::new(),  // Empty - user will add clips
-            zoom_level: 1.0,
-            scroll_position: 0.0,
+/// Opens the configured output. Falls back to the null output so the app
+/// stays usable without sound rather than refusing to start.
+fn open_audio(session: &Session, metronome: &Sample, settings: &AudioSettings, notifications: &mut Notifications) -> (Output, EngineHandle) {
+    audio::init(session, metronome.clone(), settings).unwrap_or_else(|err| {
+        notifications.error(err);
+        audio::null_output(session, metronome.clone(), settings)
+    })
+}
+
+// Structural edits are made to the UI's session first, then a copy is sent
+// to the engine which swaps it in without allocating on the audio thread.
+impl MyApp {
+    /// Sends a command to the engine. If its queue is full the edit is lost,
+    /// so the user is told and the whole session is sent again next frame.
+    pub fn send(&mut self, command: Command) {
+        if !self.engine.send(command) && !self.is_out_of_sync {
+            self.is_out_of_sync = true;
+            self.notifications.error("The audio engine fell behind and missed an edit, resyncing");
         }
     }
-}
\ No newline at end of file
+
+    /// Sends the whole session again if the engine missed an edit. Call once per frame.
+    pub fn resync(&mut self) {
+        if self.is_out_of_sync {
+            self.is_out_of_sync = false;
+            self.sync_session();
+        }
+    }
+
+    pub fn sync_instruments(&mut self) {
+        self.send(Command::SetInstruments(self.session.instruments.clone()));
+    }
+
+    pub fn sync_patterns(&mut self) {
+        self.send(Command::SetPatterns(self.session.patterns.clone()));
+    }
+
+    pub fn sync_note_patterns(&mut self) {
+        self.send(Command::SetNotePatterns(self.session.note_patterns.clone()));
+    }
+
+    pub fn sync_clips(&mut self) {
+        self.send(Command::SetClips(self.session.playlist.clips.clone()));
+    }
+
+    pub fn sync_tracks(&mut self) {
+        self.send(Command::SetTracks(self.session.playlist.tracks.clone()));
+    }
+
+    pub fn sync_aux_buses(&mut self) {
+        self.send(Command::SetAuxBuses(self.session.aux_buses.clone()));
+    }
+
+    /// Sends a channel's sends after one was added or removed
+    pub fn sync_sends(&mut self, source: SendSource) {
+        match source {
+            SendSource::Track(_) => self.sync_tracks(),
+            SendSource::Instrument(_) => self.sync_instruments(),
+            SendSource::Aux(_) => self.sync_aux_buses(),
+        }
+    }
+
+    /// Rebuilds the live effects of a track or the master from the session's
+    /// slots. Needed when effects are added, removed or reordered.
+    pub fn sync_effects(&mut self, target: EffectTarget) {
+        if let Some(slots) = self.session.effects(target) {
+            let chain = EffectChain::build(slots, self.engine.sampling_rate);
+            self.send(Command::SetEffects(target, Box::new(chain)));
+        }
+    }
+
+    /// Replaces the whole song, e.g. after opening a project
+    pub fn sync_session(&mut self) {
+        self.send(Command::LoadSession(Box::new(self.session.clone())));
+        self.sync_effects(EffectTarget::Master);
+        for track in 0..self.session.playlist.tracks.len() {
+            self.sync_effects(EffectTarget::Track(track));
+        }
+        for bus in 0..self.session.aux_buses.len() {
+            self.sync_effects(EffectTarget::Aux(bus));
+        }
+    }
+
+    /// Reopens the output with the current audio settings. The new engine
+    /// starts from the UI's session and picks up the transport where the old one was.
+    pub fn restart_audio(&mut self) {
+        let playhead = self.engine.playhead_position();
+        let is_playing = self.engine.is_playing();
+
+        // close the old output first, some devices only allow one stream
+        self.audio_output = None;
+        let (output, engine) = open_audio(&self.session, &self.metronome, &self.config.audio, &mut self.notifications);
+        self.audio_output = Some(output);
+        self.engine = engine;
+
+        self.send(Command::Seek(playhead));
+        self.send(Command::SetMetronome(self.ui_state.is_metronome));
+        self.send(Command::SetInterpolation(self.config.interpolation));
+        if is_playing {
+            self.send(Command::Play);
+        }
+    }
+
+    pub fn add_aux_bus(&mut self, bus: AuxBus) {
+        self.session.aux_buses.push(bus);
+        self.sync_aux_buses();
+        self.sync_effects(EffectTarget::Aux(self.session.aux_buses.len() - 1));
+    }
+
+    /// Removes an aux bus and every send to it. The buses after it move
+    /// down one, so their effects are rebuilt too.
+    pub fn remove_aux_bus(&mut self, index: usize) {
+        self.session.remove_aux_bus(index);
+        self.sync_tracks();
+        self.sync_instruments();
+        self.sync_aux_buses();
+        for bus in index..self.session.aux_buses.len() {
+            self.sync_effects(EffectTarget::Aux(bus));
+        }
+        self.ui_state.effects_target = None;
+        self.ui_state.sends_source = None;
+    }
+
+    /// Removes a note pattern and its clips from the playlist
+    pub fn remove_note_pattern(&mut self, index: usize) {
+        self.session.remove_note_pattern(index);
+        self.sync_note_patterns();
+        self.sync_clips();
+        self.ui_state.piano_roll.pattern = None;
+    }
+
+    /// Adds an instrument (and a row in every pattern) to the session and the engine
+    pub fn add_instrument(&mut self, instrument: Instrument) -> usize {
+        let idx = self.session.add_instrument(instrument);
+        self.sync_instruments();
+        self.sync_patterns();
+        idx
+    }
+}
@@ -1,29 +1,37 @@
+use remdaw_engine::Command;
 use crate::models::{MyApp};
-use crate::components::{channel_rack, file_explorer, file_information, patterns, playlist, settings, toolbar};
-use crate::components::popups::rename_pattern;
+use crate::components::{channel_rack, effects, file_explorer, instrument, mixer, notifications, patterns, piano_roll, playlist, sends, settings, toolbar};
+use crate::components::popups::{export, rename_pattern};
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // free anything the audio thread swapped out since last frame
+        self.engine.collect_garbage();
+        self.resync();
+
+        // read the levels once so every meter drawn this frame agrees
+        let dt = ctx.input(|i| i.stable_dt);
+        self.ui_state.meters.update(
+            self.engine.meters(),
+            self.session.playlist.tracks.len(),
+            self.session.instruments.len(),
+            self.session.aux_buses.len(),
+            dt,
+        );
 
         // test hotkeys here
         ctx.input_mut(|i| {
             if i.consume_key(egui::Modifiers::NONE, egui::Key::T) {
-                let mut state = self.audio_state.lock().unwrap();
-                // Trigger the first instrument (index 0)
-                state.instruments[0].position = 0;
-                state.instruments[0].is_playing = true;
+                // Trigger instrument 0
+                self.send(Command::TriggerInstrument(0));
             }
             if i.consume_key(egui::Modifiers::NONE, egui::Key::Y) {
-                let mut state = self.audio_state.lock().unwrap();
-                // Trigger the first instrument (index 0)
-                state.instruments[1].position = 0;
-                state.instruments[1].is_playing = true;
+                // Trigger instrument 1
+                self.send(Command::TriggerInstrument(1));
             }
             if i.consume_key(egui::Modifiers::NONE, egui::Key::I) {
-                let mut state = self.audio_state.lock().unwrap();
-                // Trigger the first instrument (index 0)
-                state.instruments[2].position = 0;
-                state.instruments[2].is_playing = true;
+                // Trigger instrument 2
+                self.send(Command::TriggerInstrument(2));
             }
         });
 
@@ -32,11 +40,24 @@ impl eframe::App for MyApp {
             channel_rack::render(self, ctx);
         }
 
-        if self.ui_state.is_file_info_open {
-            let file_path = self.selected_file.clone();
-            if let Some(ref path) = file_path {
-                file_information::render(self, ctx, path);
-            }
+        if self.ui_state.piano_roll.pattern.is_some() {
+            piano_roll::render(self, ctx);
+        }
+
+        if self.ui_state.instrument_window.is_some() {
+            instrument::render(self, ctx);
+        }
+
+        if self.ui_state.is_mixer_open {
+            mixer::render(self, ctx);
+        }
+
+        if self.ui_state.effects_target.is_some() {
+            effects::render(self, ctx);
+        }
+
+        if self.ui_state.sends_source.is_some() {
+            sends::render(self, ctx);
         }
 
 
@@ -45,6 +66,11 @@ impl eframe::App for MyApp {
             rename_pattern::render(self, ctx, idx);
         }
 
+        if self.ui_state.is_export_open {
+            export::render(self, ctx);
+        }
+        export::poll(self, ctx);
+
         // render toolbar at top
         toolbar::render(self, ctx);
 
@@ -61,6 +87,9 @@ impl eframe::App for MyApp {
 
         // where the playlist will be modularized
         playlist::render(self, ctx);
+
+        // toasts and the log go on top of everything
+        notifications::render(self, ctx);
     }
 
     // runs on app close. save user config to storage
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::Path;
 use eframe::epaint::text::FontDefinitions;
 
 /// FontDefinition constructor called on app init.
@@ -28,7 +28,7 @@ pub fn prepare_fonts() -> FontDefinitions {
 /// Shortens file path down to the file name.
 /// # Arguments:
 /// `path` - Full path to be shortened to String.
-pub fn get_file_name(path: &PathBuf) -> String {
+pub fn get_file_name(path: &Path) -> String {
     path.file_name()
     .and_then(|n| n.to_str())
     .unwrap_or("Unknown").to_owned()